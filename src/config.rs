@@ -0,0 +1,126 @@
+//! Remapping class names referenced by config files,
+//! like the `main` class of a Bukkit `plugin.yml`.
+//!
+//! These are simple line-based passes that only understand single-line values,
+//! which covers the common cases without needing a full parser.
+use crate::prelude::*;
+
+/// Remap a single (possibly quoted) scalar value, preserving the quotes
+fn remap_scalar<M: Mappings>(mappings: &M, raw: &str) -> String {
+    let trimmed = raw.trim();
+    let leading = &raw[..raw.len() - raw.trim_start().len()];
+    let trailing = &raw[raw.trim_end().len()..];
+    let quote = trimmed.chars().next()
+        .filter(|&c| (c == '"' || c == '\'') && trimmed.len() >= 2 && trimmed.ends_with(c));
+    let mut result = String::with_capacity(raw.len());
+    result.push_str(leading);
+    match quote {
+        Some(quote) => {
+            result.push(quote);
+            result.push_str(&mappings.remap_config_value(&trimmed[1..trimmed.len() - 1]));
+            result.push(quote);
+        },
+        None => result.push_str(&mappings.remap_config_value(trimmed))
+    }
+    result.push_str(trailing);
+    result
+}
+
+/// Split the text into lines, along with their original line endings
+///
+/// Unlike `str::lines` this keeps `\r\n` endings and a missing final newline intact.
+fn lines_with_endings(text: &str) -> impl Iterator<Item=(&str, &str)> {
+    text.split_inclusive('\n').map(|line| {
+        let content = line.trim_end_matches('\n');
+        let content = content.strip_suffix('\r').unwrap_or(content);
+        (content, &line[content.len()..])
+    })
+}
+
+/// Remap every class name given as a value in the specified java properties file
+pub fn remap_properties<M: Mappings>(mappings: &M, text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for (line, ending) in lines_with_endings(text) {
+        let trimmed = line.trim_start();
+        let separator = if trimmed.starts_with('#') || trimmed.starts_with('!') {
+            None
+        } else {
+            line.find(&['=', ':'][..])
+        };
+        match separator {
+            Some(index) => {
+                result.push_str(&line[..=index]);
+                result.push_str(&remap_scalar(mappings, &line[index + 1..]));
+            },
+            None => result.push_str(line)
+        }
+        result.push_str(ending);
+    }
+    result
+}
+
+/// Remap every class name given as a scalar value in the specified yaml file
+///
+/// This handles both `key: value` entries and `- value` list items.
+pub fn remap_yaml<M: Mappings>(mappings: &M, text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for (line, ending) in lines_with_endings(text) {
+        let trimmed = line.trim_start();
+        let value_start = if trimmed.starts_with('#') {
+            None
+        } else if trimmed.starts_with("- ") {
+            Some(line.len() - trimmed.len() + 2)
+        } else {
+            line.find(": ").map(|index| index + 2)
+        };
+        match value_start {
+            Some(index) => {
+                result.push_str(&line[..index]);
+                result.push_str(&remap_scalar(mappings, &line[index..]));
+            },
+            None => result.push_str(line)
+        }
+        result.push_str(ending);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mappings() -> FrozenMappings {
+        SrgMappingsFormat::parse_lines(&[
+            "CL: org/spigotmc/XRay net/techcable/xray/XRay",
+        ]).unwrap()
+    }
+
+    #[test]
+    fn properties() {
+        assert_eq!(
+            remap_properties(&mappings(), "# org.spigotmc.XRay\nmain=org.spigotmc.XRay\nname: XRay\n"),
+            "# org.spigotmc.XRay\nmain=net.techcable.xray.XRay\nname: XRay\n"
+        );
+    }
+
+    #[test]
+    fn yaml() {
+        assert_eq!(
+            remap_yaml(&mappings(), "main: org.spigotmc.XRay\nlisteners:\n  - 'org.spigotmc.XRay$Listener'\nversion: 1.0\n"),
+            "main: net.techcable.xray.XRay\nlisteners:\n  - 'net.techcable.xray.XRay$Listener'\nversion: 1.0\n"
+        );
+    }
+
+    #[test]
+    fn line_endings() {
+        assert_eq!(
+            remap_properties(&mappings(), "main=org.spigotmc.XRay\r\nname=XRay"),
+            "main=net.techcable.xray.XRay\r\nname=XRay"
+        );
+        assert_eq!(
+            remap_yaml(&mappings(), "main: org.spigotmc.XRay\r\n\r\nlisteners:\r\n  - org.spigotmc.XRay\r\n"),
+            "main: net.techcable.xray.XRay\r\n\r\nlisteners:\r\n  - net.techcable.xray.XRay\r\n"
+        );
+        assert_eq!(remap_yaml(&mappings(), ""), "");
+    }
+}
@@ -1,19 +1,111 @@
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
+use failure_derive::Fail;
+
 use crate::utils::*;
 use super::prelude::*;
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+/// JVM access flags, as used by `MethodData::access` and `FieldData::access`
+pub mod access {
+    pub const ACC_PUBLIC: u16 = 0x0001;
+    pub const ACC_PRIVATE: u16 = 0x0002;
+    pub const ACC_PROTECTED: u16 = 0x0004;
+    pub const ACC_STATIC: u16 = 0x0008;
+    pub const ACC_FINAL: u16 = 0x0010;
+}
+
+/// Implement accessors for the optional access flags of a member.
+///
+/// The flags are only informational, so they're ignored by equality and hashing.
+macro_rules! access_flags {
+    ($target:ty) => {
+        impl $target {
+            /// The JVM access flags of this member, if the mapping format gave them to us
+            #[inline]
+            pub fn access(&self) -> Option<u16> {
+                self.access
+            }
+            #[inline]
+            pub fn set_access(&mut self, access: Option<u16>) {
+                self.access = access;
+            }
+            #[inline]
+            pub fn with_access(mut self, access: u16) -> Self {
+                self.access = Some(access);
+                self
+            }
+            /// Check if the specified flag is set,
+            /// which is always false if we don't know the access flags.
+            #[inline]
+            pub fn has_access_flag(&self, flag: u16) -> bool {
+                self.access.map_or(false, |access| access & flag != 0)
+            }
+            #[inline]
+            pub fn is_public(&self) -> bool {
+                self.has_access_flag(access::ACC_PUBLIC)
+            }
+            #[inline]
+            pub fn is_private(&self) -> bool {
+                self.has_access_flag(access::ACC_PRIVATE)
+            }
+            #[inline]
+            pub fn is_protected(&self) -> bool {
+                self.has_access_flag(access::ACC_PROTECTED)
+            }
+            #[inline]
+            pub fn is_static(&self) -> bool {
+                self.has_access_flag(access::ACC_STATIC)
+            }
+            #[inline]
+            pub fn is_final(&self) -> bool {
+                self.has_access_flag(access::ACC_FINAL)
+            }
+        }
+    };
+}
+
+/// Split a joined internal name like `net/minecraft/server/Entity/dead`
+/// into the member's declaring type and its name.
+///
+/// Returns `None` if the name doesn't have a declaring type.
+#[inline]
+pub fn split_member_name(joined: &str) -> Option<(ReferenceType, &str)> {
+    split_member_name_with(joined, '/')
+}
+/// Split a joined internal name using the specified character
+/// to separate the member from its declaring type, like `net/minecraft/server/Entity:dead`
+pub fn split_member_name_with(joined: &str, separator: char) -> Option<(ReferenceType, &str)> {
+    let index = joined.rfind(separator)?;
+    let name = &joined[(index + separator.len_utf8())..];
+    Some((ReferenceType::from_internal_name(&joined[..index]), name))
+}
+
+#[derive(Clone, Debug)]
 pub struct MethodData {
     pub name: String,
     declaring_type: ReferenceType,
-    signature: MethodSignature
+    signature: MethodSignature,
+    access: Option<u16>
 }
+access_flags!(MethodData);
 impl MethodData {
     #[inline]
     pub fn new(name: String, declaring_type: ReferenceType, signature: MethodSignature) -> MethodData {
-        MethodData { name, declaring_type, signature}
+        MethodData { name, declaring_type, signature, access: None }
+    }
+    /// Create a method from its joined internal name and descriptor, like `obfs/a` and `(Lobf4;ID)Z`
+    ///
+    /// Panics if the name doesn't have a declaring type or the descriptor is invalid.
+    pub fn from_internal(joined: &str, descriptor: &str) -> MethodData {
+        let (declaring_type, name) = split_member_name(joined)
+            .unwrap_or_else(|| panic!("Invalid joined name: {:?}", joined));
+        MethodData::new(name.into(), declaring_type, MethodSignature::from_descriptor(descriptor))
+    }
+    /// Give a copy of this method with the specified name
+    #[inline]
+    pub fn with_name(&self, name: String) -> MethodData {
+        MethodData { name, ..self.clone() }
     }
     /// The declaring type of this field
     #[inline]
@@ -30,6 +122,12 @@ impl MethodData {
     pub fn signature(&self) -> &MethodSignature {
         &self.signature
     }
+    /// Extract the numeric id from a searge-style name like `func_12345_a`,
+    /// returning `None` if this method doesn't have a searge name.
+    #[inline]
+    pub fn searge_id(&self) -> Option<u64> {
+        parse_searge_id(&self.name, "func_")
+    }
 }
 impl MapClass for MethodData {
     fn maybe_transform_class<T: TypeTransformer>(&self, transformer: T) -> Option<Self> {
@@ -38,10 +136,27 @@ impl MapClass for MethodData {
         Some(MethodData {
             name: self.name.clone(),
             declaring_type: remapped_class,
-            signature: remapped_signature
+            signature: remapped_signature,
+            access: self.access
         })
     }
 }
+impl PartialEq for MethodData {
+    #[inline]
+    fn eq(&self, other: &MethodData) -> bool {
+        self.name == other.name && self.declaring_type == other.declaring_type
+            && self.signature == other.signature
+    }
+}
+impl Eq for MethodData {}
+impl Hash for MethodData {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.declaring_type.hash(state);
+        self.signature.hash(state);
+    }
+}
 
 impl<'a> From<&'a MethodData> for MethodData {
     #[inline]
@@ -49,15 +164,55 @@ impl<'a> From<&'a MethodData> for MethodData {
         data.clone()
     }
 }
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+/// A field of a class.
+///
+/// The field's type is only known if the mapping format gives it to us,
+/// which lets us tell apart fields that only differ by their type.
+/// When it's known, the type is included in equality and hashing.
+#[derive(Clone, Debug)]
 pub struct FieldData {
     pub name: String,
-    declaring_type: ReferenceType
+    declaring_type: ReferenceType,
+    descriptor: Option<TypeDescriptor>,
+    access: Option<u16>
 }
+access_flags!(FieldData);
 impl FieldData {
     #[inline]
     pub fn new(name: String, declaring_type: ReferenceType) -> FieldData {
-        FieldData { name, declaring_type }
+        FieldData { name, declaring_type, descriptor: None, access: None }
+    }
+    /// The type of this field, if the mapping format gave it to us
+    #[inline]
+    pub fn descriptor(&self) -> Option<&TypeDescriptor> {
+        self.descriptor.as_ref()
+    }
+    #[inline]
+    pub fn set_descriptor(&mut self, descriptor: Option<TypeDescriptor>) {
+        self.descriptor = descriptor;
+    }
+    #[inline]
+    pub fn with_descriptor(mut self, descriptor: TypeDescriptor) -> Self {
+        self.descriptor = Some(descriptor);
+        self
+    }
+    /// Give this field without its type, which is how formats without field types identify it
+    #[inline]
+    pub fn without_descriptor(&self) -> FieldData {
+        FieldData { descriptor: None, ..self.clone() }
+    }
+    /// Create a field from its joined internal name, like `obf4/a`
+    ///
+    /// Panics if the name doesn't have a declaring type.
+    pub fn from_internal(joined: &str) -> FieldData {
+        let (declaring_type, name) = split_member_name(joined)
+            .unwrap_or_else(|| panic!("Invalid joined name: {:?}", joined));
+        FieldData::new(name.into(), declaring_type)
+    }
+    /// Give a copy of this field with the specified name
+    #[inline]
+    pub fn with_name(&self, name: String) -> FieldData {
+        FieldData { name, ..self.clone() }
     }
     /// The declaring type of this field
     #[inline]
@@ -75,14 +230,41 @@ impl FieldData {
         buffer.push_str(&self.name);
         buffer
     }
+    /// Extract the numeric id from a searge-style name like `field_12345_a`,
+    /// returning `None` if this field doesn't have a searge name.
+    #[inline]
+    pub fn searge_id(&self) -> Option<u64> {
+        parse_searge_id(&self.name, "field_")
+    }
 }
 impl MapClass for FieldData {
     fn maybe_transform_class<T: TypeTransformer>(&self, transformer: T) -> Option<Self> {
-        if let Some(reference_type) = transformer.maybe_remap_class(&self.declaring_type) {
-            Some(FieldData { name: self.name.clone(), declaring_type: reference_type })
-        } else {
-            None
-        }
+        let declaring_type = transformer.maybe_remap_class(&self.declaring_type);
+        let descriptor = self.descriptor.as_ref()
+            .and_then(|descriptor| descriptor.maybe_transform_class(&transformer));
+        if declaring_type.is_none() && descriptor.is_none() { return None }
+        Some(FieldData {
+            name: self.name.clone(),
+            declaring_type: declaring_type.unwrap_or_else(|| self.declaring_type.clone()),
+            descriptor: descriptor.or_else(|| self.descriptor.clone()),
+            access: self.access
+        })
+    }
+}
+impl PartialEq for FieldData {
+    #[inline]
+    fn eq(&self, other: &FieldData) -> bool {
+        self.name == other.name && self.declaring_type == other.declaring_type
+            && self.descriptor == other.descriptor
+    }
+}
+impl Eq for FieldData {}
+impl Hash for FieldData {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.declaring_type.hash(state);
+        self.descriptor.hash(state);
     }
 }
 impl<'a> From<&'a FieldData> for FieldData {
@@ -91,6 +273,84 @@ impl<'a> From<&'a FieldData> for FieldData {
         data.clone()
     }
 }
+/// A named parameter of a method.
+///
+/// The index is the parameter's local variable slot, as given by the mapping formats,
+/// so instance methods start at one and wide types like `long` take up two slots.
+/// Parameters are identified by their method and index,
+/// so the name is ignored by equality and hashing since it's often missing from the original side.
+#[derive(Clone, Debug)]
+pub struct ParameterData {
+    pub name: String,
+    method: MethodData,
+    index: u16
+}
+impl ParameterData {
+    #[inline]
+    pub fn new(name: String, method: MethodData, index: u16) -> ParameterData {
+        ParameterData { name, method, index }
+    }
+    /// The method this parameter belongs to
+    #[inline]
+    pub fn method(&self) -> &MethodData {
+        &self.method
+    }
+    /// The local variable index of this parameter
+    #[inline]
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+    /// The name of this parameter
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+impl MapClass for ParameterData {
+    fn maybe_transform_class<T: TypeTransformer>(&self, transformer: T) -> Option<Self> {
+        Some(ParameterData {
+            name: self.name.clone(),
+            method: self.method.transform_class(&transformer),
+            index: self.index
+        })
+    }
+}
+impl PartialEq for ParameterData {
+    #[inline]
+    fn eq(&self, other: &ParameterData) -> bool {
+        self.index == other.index && self.method == other.method
+    }
+}
+impl Eq for ParameterData {}
+impl Hash for ParameterData {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.method.hash(state);
+        self.index.hash(state);
+    }
+}
+
+/// Parse the id out of a searge name, which has the form `<prefix><id>_<suffix>`
+fn parse_searge_id(name: &str, prefix: &str) -> Option<u64> {
+    if !name.starts_with(prefix) { return None }
+    let remaining = &name[prefix.len()..];
+    let separator = remaining.find('_')?;
+    let (id, suffix) = (&remaining[..separator], &remaining[separator + 1..]);
+    if id.is_empty() || suffix.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return None
+    }
+    id.parse().ok()
+}
+
+/// A problem with the types given to `MethodSignature::try_new`
+#[derive(Debug, Fail)]
+pub enum SignatureError {
+    /// The parameter at the specified index is `void`, which is only valid as a return type
+    #[fail(display = "Parameter {} is void", index)]
+    VoidParameter {
+        index: usize
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct MethodSignature(Arc<MethodSignatureInner>);
@@ -101,6 +361,10 @@ struct MethodSignatureInner {
     parameter_types: Vec<TypeDescriptor>
 }
 impl MethodSignature {
+    /// Create a signature from its return type and parameter types.
+    ///
+    /// None of the parameters should be `void`, but this isn't checked.
+    /// Use `try_new` for types that come from untrusted input.
     pub fn new(return_type: TypeDescriptor, parameter_types: Vec<TypeDescriptor>) -> Self {
         let mut descriptor = String::with_capacity(64 * (parameter_types.len() + 1));
         descriptor.push('(');
@@ -111,6 +375,15 @@ impl MethodSignature {
         descriptor.push_str(return_type.descriptor());
         Self::from_raw(descriptor, return_type, parameter_types)
     }
+    /// Create a signature from its return type and parameter types,
+    /// checking that none of the parameters are `void`.
+    pub fn try_new(return_type: TypeDescriptor, parameter_types: Vec<TypeDescriptor>) -> Result<Self, SignatureError> {
+        let void = TypeDescriptor::Primitive(PrimitiveType::Void);
+        if let Some(index) = parameter_types.iter().position(|parameter_type| *parameter_type == void) {
+            return Err(SignatureError::VoidParameter { index })
+        }
+        Ok(Self::new(return_type, parameter_types))
+    }
     #[inline]
     fn from_raw(descriptor: String, return_type: TypeDescriptor, parameter_types: Vec<TypeDescriptor>) -> Self {
         MethodSignature(Arc::new(MethodSignatureInner { descriptor, return_type, parameter_types }))
@@ -175,3 +448,84 @@ impl PartialEq for MethodSignature {
     }
 }
 impl Eq for MethodSignature {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn searge_ids() {
+        let declaring_type = ReferenceType::from_internal_name("a");
+        let field = |name: &str| FieldData::new(name.into(), declaring_type.clone());
+        let method = |name: &str| MethodData::new(
+            name.into(), declaring_type.clone(),
+            MethodSignature::from_descriptor("()V")
+        );
+        assert_eq!(field("field_67890_b").searge_id(), Some(67890));
+        assert_eq!(method("func_12345_a").searge_id(), Some(12345));
+        assert_eq!(field("func_12345_a").searge_id(), None);
+        assert_eq!(method("field_67890_b").searge_id(), None);
+        assert_eq!(field("field_").searge_id(), None);
+        assert_eq!(field("field_123").searge_id(), None);
+        assert_eq!(field("field_+123_a").searge_id(), None);
+        assert_eq!(method("tick").searge_id(), None);
+    }
+    #[test]
+    fn from_internal() {
+        let (declaring_type, name) = split_member_name("net/minecraft/server/Entity/dead").unwrap();
+        assert_eq!((declaring_type.internal_name(), name), ("net/minecraft/server/Entity", "dead"));
+        assert_eq!(split_member_name("dead"), None);
+        let (declaring_type, name) = split_member_name_with("net/minecraft/server/Entity:dead", ':').unwrap();
+        assert_eq!((declaring_type.internal_name(), name), ("net/minecraft/server/Entity", "dead"));
+        assert_eq!(split_member_name_with("net/minecraft/server/Entity/dead", ':'), None);
+        assert_eq!(
+            FieldData::from_internal("obf4/a"),
+            FieldData::new("a".into(), ReferenceType::from_internal_name("obf4"))
+        );
+        assert_eq!(
+            MethodData::from_internal("obfs/a", "(Lobf4;ID)Z"),
+            MethodData::new(
+                "a".into(), ReferenceType::from_internal_name("obfs"),
+                MethodSignature::from_descriptor("(Lobf4;ID)Z")
+            )
+        );
+    }
+    #[test]
+    fn access_flags() {
+        let declaring_type = ReferenceType::from_internal_name("a");
+        let plain = MethodData::new("a".into(), declaring_type.clone(), MethodSignature::from_descriptor("()V"));
+        let flagged = plain.clone().with_access(access::ACC_PUBLIC | access::ACC_STATIC);
+        assert!(flagged.is_public() && flagged.is_static() && !flagged.is_final());
+        assert!(!plain.is_static());
+        assert_eq!(plain.access(), None);
+        // Access flags don't affect equality
+        assert_eq!(plain, flagged);
+        let renamed = flagged.map_class(|_| Some(ReferenceType::from_internal_name("b")));
+        assert_eq!(renamed.access(), flagged.access());
+    }
+    #[test]
+    fn try_new() {
+        let int = TypeDescriptor::parse_descriptor("I").unwrap();
+        let void = TypeDescriptor::parse_descriptor("V").unwrap();
+        let signature = MethodSignature::try_new(void.clone(), vec![int.clone(), int.clone()]).unwrap();
+        assert_eq!(signature, MethodSignature::new(void.clone(), vec![int.clone(), int.clone()]));
+        assert_eq!(signature.descriptor(), "(II)V");
+        match MethodSignature::try_new(int.clone(), vec![int, void.clone()]) {
+            Err(SignatureError::VoidParameter { index: 1 }) => {},
+            other => panic!("Unexpected result: {:?}", other)
+        }
+        let error = MethodSignature::try_new(void.clone(), vec![void]).unwrap_err();
+        assert_eq!(error.to_string(), "Parameter 0 is void");
+    }
+    #[test]
+    fn with_name() {
+        let method = MethodData::from_internal("obfs/a", "(Lobf4;ID)Z").with_access(access::ACC_STATIC);
+        let renamed = method.with_name("isHacking".into());
+        assert_eq!(renamed, MethodData::from_internal("obfs/isHacking", "(Lobf4;ID)Z"));
+        assert!(renamed.is_static());
+        assert_eq!(method.name, "a");
+        let field = FieldData::from_internal("obf4/a").with_descriptor(TypeDescriptor::parse_descriptor("Z").unwrap());
+        let renamed = field.with_name("dead".into());
+        assert_eq!(renamed.internal_name(), "obf4/dead");
+        assert_eq!(renamed.descriptor(), field.descriptor());
+    }
+}
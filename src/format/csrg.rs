@@ -2,24 +2,39 @@ use std::io::{self, Write};
 use std::borrow::Borrow;
 
 use crate::prelude::*;
-use super::{MappingsLineProcessor, MappingsFormat, MappingsParseError};
+use super::{MappingsLineProcessor, MergeableLineProcessor, MappingsFormat, MappingsParseError};
 use crate::utils::*;
 
+/// The compact SRG format used by Bukkit, which omits the line prefixes and renamed signatures.
+///
+/// Files in this format conventionally map from obfuscated names to deobfuscated names.
+/// Some variants give the type of fields before their renamed name, like methods give their signature,
+/// so fields are written with their types if we know them.
 pub struct CompactSrgMappingsFormat;
 impl MappingsFormat for CompactSrgMappingsFormat {
     type Processor = CompactSrgLineProcessor;
+    const SUPPORTS_COLLAPSE_WHITESPACE: bool = true;
 
     fn write<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, mut writer: W) -> io::Result<()> {
         for (original, renamed) in mappings.classes() {
             writeln!(writer, "{} {}", original.internal_name(), renamed.borrow().internal_name())?;
         }
         for (original, renamed) in mappings.fields() {
-            writeln!(
-                writer, "{} {} {}",
-                original.declaring_type().internal_name(),
-                original.name,
-                renamed.borrow().name
-            )?;
+            match original.descriptor() {
+                Some(descriptor) => writeln!(
+                    writer, "{} {} {} {}",
+                    original.declaring_type().internal_name(),
+                    original.name,
+                    descriptor.descriptor(),
+                    renamed.borrow().name
+                )?,
+                None => writeln!(
+                    writer, "{} {} {}",
+                    original.declaring_type().internal_name(),
+                    original.name,
+                    renamed.borrow().name
+                )?
+            }
         }
         for (original, renamed) in mappings.methods() {
             writeln!(
@@ -46,8 +61,26 @@ pub struct CompactSrgLineProcessor {
 impl CompactSrgLineProcessor {
     fn parse_line(&mut self, parser: &mut SimpleParser) -> Result<(), SimpleParseError> {
         parser.skip_whitespace();
-        if parser.is_finished() || parser.peek()? == '#' { return Ok(()) }
-        match parser.remaining().split_whitespace().count() {
+        parser.strip_comment();
+        if parser.is_finished() { return Ok(()) }
+        let column_count = parser.remaining().split_whitespace().count();
+        match column_count {
+            // Methods always have a signature, while the type of a field is optional
+            4 if !parser.remaining().split_whitespace().nth(2).unwrap().starts_with('(') => {
+                let original_declaring_type = ReferenceType::from_internal_name(
+                    parser.parse_internal_name()?);
+                parser.expect(' ')?;
+                let original_name = parser.take_until(|c| c == ' ');
+                parser.expect(' ')?;
+                let original_type = parser.parse::<TypeDescriptor>()?;
+                parser.expect(' ')?;
+                let renamed_name = parser.take_until(|c| c == ' ');
+                let original_data = FieldData::new(
+                    original_name.into(),
+                    original_declaring_type,
+                ).with_descriptor(original_type);
+                self.result.set_field_name(original_data, renamed_name.into());
+            },
             4 => {
                 let original_declaring_type = ReferenceType::from_internal_name(
                     parser.parse_internal_name()?);
@@ -85,7 +118,7 @@ impl CompactSrgLineProcessor {
                     parser.parse_internal_name()?);
                 self.result.set_remapped_class(original, renamed);
             },
-            _ => return Err(parser.error())
+            _ => return Err(parser.column_count_error("2, 3 or 4", column_count))
         }
         parser.skip_whitespace();
         parser.ensure_finished()?;
@@ -94,11 +127,12 @@ impl CompactSrgLineProcessor {
 }
 impl MappingsLineProcessor for CompactSrgLineProcessor {
     fn process_line(&mut self, s: &str) -> Result<(), MappingsParseError> {
-        let mut parser = SimpleParser::new(s);
+        let mut parser = SimpleParser::for_line(s);
         self.parse_line(&mut parser)
             .map_err(|cause| MappingsParseError::InvalidLine {
                 index: cause.index,
                 line: s.into(),
+                line_number: None,
                 reason: cause.reason
             })
     }
@@ -108,3 +142,10 @@ impl MappingsLineProcessor for CompactSrgLineProcessor {
         Ok(self.result.frozen())
     }
 }
+impl MergeableLineProcessor for CompactSrgLineProcessor {
+    #[inline]
+    fn merge(&mut self, other: Self) -> Result<(), MappingsParseError> {
+        self.result.extend(other.result);
+        Ok(())
+    }
+}
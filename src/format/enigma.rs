@@ -0,0 +1,244 @@
+use std::fs::{self, File};
+use std::ffi::OsStr;
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::borrow::Borrow;
+
+use crate::prelude::*;
+use super::{MappingsLineProcessor, MappingsFormat, MappingsParseError, UNKNOWN_FIELD_DESCRIPTOR, process_stream};
+use super::tsrg::ClassData;
+use crate::utils::*;
+
+
+/// The Enigma mapping format, with `CLASS`, `FIELD`, `METHOD` and `ARG` entries
+/// nested using tab indentation.
+///
+/// Inner classes are nested under their outer class, and are given by their simple names.
+/// Descriptors are given using the original class names, and entries without
+/// a renamed name keep their original one.
+/// Comments are attached to the entry they're nested under, while argument entries are discarded.
+///
+/// When writing, each class is written at the top level using its full name,
+/// which this parser also accepts.
+pub struct EnigmaMappingsFormat;
+impl EnigmaMappingsFormat {
+    /// Parse all the `.mapping` files in the specified directory and its subdirectories,
+    /// merging them into a single mapping.
+    pub fn parse_directory(path: &Path) -> Result<FrozenMappings, MappingsParseError> {
+        let mut files = Vec::new();
+        find_mapping_files(path, &mut files)?;
+        // Sort so the result doesn't depend on the order of the directory listing
+        files.sort();
+        let mut processer = Self::processor();
+        for file in files {
+            process_stream(&mut processer, BufReader::new(File::open(file)?))?;
+        }
+        processer.finish()
+    }
+}
+fn find_mapping_files(directory: &Path, result: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_mapping_files(&path, result)?;
+        } else if path.extension() == Some(OsStr::new("mapping")) {
+            result.push(path);
+        }
+    }
+    Ok(())
+}
+impl MappingsFormat for EnigmaMappingsFormat {
+    type Processor = EnigmaLineProcessor;
+
+    fn write<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, mut writer: W) -> io::Result<()> {
+        let data = ClassData::from_mappings(mappings);
+        for (declaring_type, data) in data.iter() {
+            match data.renamed_type {
+                Some(renamed_type) if renamed_type != *declaring_type => {
+                    writeln!(writer, "CLASS {} {}", declaring_type.internal_name(), renamed_type.internal_name())?;
+                },
+                _ => writeln!(writer, "CLASS {}", declaring_type.internal_name())?
+            }
+            write_comment(&mut writer, "\t", mappings.class_comment(declaring_type))?;
+            for (original, renamed) in &data.fields {
+                writeln!(
+                    writer, "\tFIELD {} {} {}",
+                    original.name, renamed.borrow().name,
+                    original.descriptor().map_or(UNKNOWN_FIELD_DESCRIPTOR, TypeDescriptor::descriptor)
+                )?;
+                write_comment(&mut writer, "\t\t", mappings.field_comment(original))?;
+            }
+            for (original, renamed) in &data.methods {
+                writeln!(
+                    writer, "\tMETHOD {} {} {}",
+                    original.name, renamed.borrow().name,
+                    original.signature().descriptor()
+                )?;
+                write_comment(&mut writer, "\t\t", mappings.method_comment(original))?;
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn processor() -> EnigmaLineProcessor {
+        EnigmaLineProcessor::default()
+    }
+}
+
+/// Write each line of the comment as a separate `COMMENT` entry
+fn write_comment<W: Write>(writer: &mut W, indent: &str, comment: Option<&str>) -> io::Result<()> {
+    if let Some(comment) = comment {
+        for line in comment.lines() {
+            writeln!(writer, "{}COMMENT {}", indent, line)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct EnigmaLineProcessor {
+    result: SimpleMappings,
+    /// The original and renamed names of the enclosing classes, indexed by their indentation
+    classes: Vec<(ReferenceType, ReferenceType)>,
+    /// The most recent entry at each indentation level, which any nested comments belong to
+    entries: Vec<CommentTarget>
+}
+enum CommentTarget {
+    Class(ReferenceType),
+    Field(FieldData),
+    Method(MethodData),
+    Ignored
+}
+impl EnigmaLineProcessor {
+    fn push_entry(&mut self, depth: usize, target: CommentTarget) {
+        self.entries.truncate(depth);
+        if self.entries.len() == depth {
+            self.entries.push(target);
+        }
+    }
+    fn add_comment(&mut self, depth: usize, line: &str) {
+        self.entries.truncate(depth);
+        if depth == 0 || self.entries.len() != depth { return }
+        let result = &mut self.result;
+        // Comments spanning multiple lines are given as multiple entries
+        let join = |existing: Option<&str>| match existing {
+            Some(existing) => format!("{}\n{}", existing, line),
+            None => line.into()
+        };
+        match self.entries[depth - 1] {
+            CommentTarget::Class(ref class) => {
+                let comment = join(result.class_comment(class));
+                result.set_class_comment(class.clone(), comment);
+            },
+            CommentTarget::Field(ref field) => {
+                let comment = join(result.field_comment(field));
+                result.set_field_comment(field.clone(), comment);
+            },
+            CommentTarget::Method(ref method) => {
+                let comment = join(result.method_comment(method));
+                result.set_method_comment(method.clone(), comment);
+            },
+            CommentTarget::Ignored => {}
+        }
+    }
+    fn parse_line(&mut self, parser: &mut SimpleParser) -> Result<(), SimpleParseError> {
+        // Comments are free text, so they can contain a `#`
+        if !parser.remaining().trim_start().starts_with("COMMENT") {
+            parser.strip_comment();
+        }
+        if parser.remaining().trim().is_empty() { return Ok(()) }
+        let depth = parser.remaining().len() - parser.remaining().trim_start_matches('\t').len();
+        parser.skip(depth);
+        let start = parser.current_index();
+        let parts: Vec<&str> = parser.remaining().split(' ').collect();
+        match parts[0] {
+            "CLASS" => {
+                if depth > self.classes.len() {
+                    return Err(SimpleParseError { index: start, reason: Some("Missing outer class".into()) })
+                }
+                self.classes.truncate(depth);
+                let (original_name, renamed_name) = match parts.len() {
+                    2 => (parts[1], parts[1]),
+                    3 => (parts[1], parts[2]),
+                    _ => return Err(parser.column_count_error("2 or 3", parts.len()))
+                };
+                for name in &[original_name, renamed_name] {
+                    if name.is_empty() || name.contains('.') {
+                        return Err(SimpleParseError { index: start, reason: Some(format!("Invalid internal name: {:?}", name)) })
+                    }
+                }
+                let (original, renamed) = match self.classes.last() {
+                    // Inner classes are given by their simple name, relative to the outer class
+                    Some((outer_original, outer_renamed)) if !original_name.contains('$') => (
+                        ReferenceType::from_internal_name(&format!("{}${}", outer_original.internal_name(), original_name)),
+                        ReferenceType::from_internal_name(&format!("{}${}", outer_renamed.internal_name(), renamed_name))
+                    ),
+                    _ => (
+                        ReferenceType::from_internal_name(original_name),
+                        ReferenceType::from_internal_name(renamed_name)
+                    )
+                };
+                if original != renamed {
+                    self.result.set_remapped_class(original.clone(), renamed.clone());
+                }
+                self.classes.push((original.clone(), renamed));
+                self.push_entry(depth, CommentTarget::Class(original));
+            },
+            kind @ "FIELD" | kind @ "METHOD" => {
+                self.classes.truncate(depth);
+                let declaring_type = match self.classes.last() {
+                    Some((original, _)) if self.classes.len() == depth => original.clone(),
+                    _ => return Err(SimpleParseError { index: start, reason: Some("Missing current class".into()) })
+                };
+                let (original_name, renamed_name, descriptor) = match parts.len() {
+                    3 => (parts[1], parts[1], parts[2]),
+                    4 => (parts[1], parts[2], parts[3]),
+                    _ => return Err(parser.column_count_error("3 or 4", parts.len()))
+                };
+                let descriptor_index = start + parser.remaining().rfind(' ').unwrap() + 1;
+                let invalid_descriptor = || SimpleParseError {
+                    index: descriptor_index,
+                    reason: Some(format!("Invalid descriptor: {:?}", descriptor))
+                };
+                if kind == "FIELD" {
+                    let descriptor = TypeDescriptor::parse_descriptor(descriptor).ok_or_else(invalid_descriptor)?;
+                    let original = FieldData::new(original_name.into(), declaring_type).with_descriptor(descriptor);
+                    self.result.set_field_name(original.clone(), renamed_name.into());
+                    self.push_entry(depth, CommentTarget::Field(original));
+                } else {
+                    let signature = MethodSignature::parse_descriptor(descriptor)
+                        .ok_or_else(invalid_descriptor)?;
+                    let original = MethodData::new(original_name.into(), declaring_type, signature);
+                    self.result.set_method_name(original.clone(), renamed_name.into());
+                    self.push_entry(depth, CommentTarget::Method(original));
+                }
+            },
+            // Arguments are ignored, along with any comments on them
+            "ARG" => self.push_entry(depth, CommentTarget::Ignored),
+            "COMMENT" => {
+                let line = &parser.remaining()["COMMENT".len()..];
+                self.add_comment(depth, line.strip_prefix(' ').unwrap_or(line));
+            },
+            _ => return Err(parser.expected("CLASS, FIELD, METHOD, ARG or COMMENT"))
+        }
+        Ok(())
+    }
+}
+impl MappingsLineProcessor for EnigmaLineProcessor {
+    fn process_line(&mut self, s: &str) -> Result<(), MappingsParseError> {
+        let mut parser = SimpleParser::for_line(s);
+        self.parse_line(&mut parser)
+            .map_err(|cause| MappingsParseError::InvalidLine {
+                index: cause.index,
+                line: s.into(),
+                line_number: None,
+                reason: cause.reason
+            })
+    }
+
+    #[inline]
+    fn finish(self) -> Result<FrozenMappings, MappingsParseError> {
+        Ok(self.result.frozen())
+    }
+}
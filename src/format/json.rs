@@ -0,0 +1,414 @@
+//! A stable JSON representation of mappings, for caching and for interop with other tools.
+//!
+//! The document is an object with `classes`, `fields` and `methods` arrays,
+//! each of which is written in the mapping's iteration order.
+//! Members give both their original and renamed declaring types, along with
+//! method descriptors, so other tools don't need to remap them themselves.
+//! Fields only give their descriptors if we know their types.
+//! Since the renamed declaring types and descriptors are implied by the class mappings,
+//! the parser only reads the renamed member names and ignores any unknown keys.
+use std::io::{self, Write};
+use std::borrow::Borrow;
+use std::fmt::Write as FmtWrite;
+
+use crate::prelude::*;
+use super::{MappingsLineProcessor, MappingsFormat, MappingsParseError};
+
+/// A JSON document describing the mappings, written without any external dependencies.
+///
+/// ```json
+/// {
+///   "classes": [
+///     {"original": "obf4", "renamed": "net/techcable/Player"}
+///   ],
+///   "fields": [
+///     {"owner": "obf4", "original": "a", "renamedOwner": "net/techcable/Player", "renamed": "dead"}
+///   ],
+///   "methods": [
+///     {"owner": "obf4", "original": "b", "descriptor": "(Lobf4;)V", "renamedOwner": "net/techcable/Player", "renamed": "kill", "renamedDescriptor": "(Lnet/techcable/Player;)V"}
+///   ]
+/// }
+/// ```
+pub struct JsonMappingsFormat;
+impl MappingsFormat for JsonMappingsFormat {
+    type Processor = JsonLineProcessor;
+
+    fn write<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, mut writer: W) -> io::Result<()> {
+        // Reuse the same buffer for every entry, so we don't allocate for each escaped string
+        let mut line = String::new();
+        writer.write_all(b"{\n  \"classes\": [")?;
+        for (index, (original, renamed)) in mappings.classes().enumerate() {
+            line.clear();
+            start_entry(&mut line, index);
+            push_key(&mut line, "original", original.internal_name());
+            line.push_str(", ");
+            push_key(&mut line, "renamed", renamed.internal_name());
+            line.push('}');
+            writer.write_all(line.as_bytes())?;
+        }
+        writer.write_all(b"\n  ],\n  \"fields\": [")?;
+        for (index, (original, renamed)) in mappings.fields().enumerate() {
+            let renamed = renamed.borrow();
+            line.clear();
+            start_entry(&mut line, index);
+            push_key(&mut line, "owner", original.declaring_type().internal_name());
+            line.push_str(", ");
+            push_key(&mut line, "original", &original.name);
+            if let Some(descriptor) = original.descriptor() {
+                line.push_str(", ");
+                push_key(&mut line, "descriptor", descriptor.descriptor());
+            }
+            line.push_str(", ");
+            push_key(&mut line, "renamedOwner", renamed.declaring_type().internal_name());
+            line.push_str(", ");
+            push_key(&mut line, "renamed", &renamed.name);
+            if let Some(descriptor) = renamed.descriptor() {
+                line.push_str(", ");
+                push_key(&mut line, "renamedDescriptor", descriptor.descriptor());
+            }
+            line.push('}');
+            writer.write_all(line.as_bytes())?;
+        }
+        writer.write_all(b"\n  ],\n  \"methods\": [")?;
+        for (index, (original, renamed)) in mappings.methods().enumerate() {
+            let renamed = renamed.borrow();
+            line.clear();
+            start_entry(&mut line, index);
+            push_key(&mut line, "owner", original.declaring_type().internal_name());
+            line.push_str(", ");
+            push_key(&mut line, "original", &original.name);
+            line.push_str(", ");
+            push_key(&mut line, "descriptor", original.signature().descriptor());
+            line.push_str(", ");
+            push_key(&mut line, "renamedOwner", renamed.declaring_type().internal_name());
+            line.push_str(", ");
+            push_key(&mut line, "renamed", &renamed.name);
+            line.push_str(", ");
+            push_key(&mut line, "renamedDescriptor", renamed.signature().descriptor());
+            line.push('}');
+            writer.write_all(line.as_bytes())?;
+        }
+        writer.write_all(b"\n  ]\n}\n")
+    }
+
+    #[inline]
+    fn processor() -> JsonLineProcessor {
+        JsonLineProcessor::default()
+    }
+}
+
+fn start_entry(line: &mut String, index: usize) {
+    if index > 0 {
+        line.push(',');
+    }
+    line.push_str("\n    {");
+}
+fn push_key(line: &mut String, key: &str, value: &str) {
+    push_string(line, key);
+    line.push_str(": ");
+    push_string(line, value);
+}
+fn push_string(line: &mut String, value: &str) {
+    line.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => line.push_str("\\\""),
+            '\\' => line.push_str("\\\\"),
+            '\n' => line.push_str("\\n"),
+            '\r' => line.push_str("\\r"),
+            '\t' => line.push_str("\\t"),
+            _ if c.is_control() => write!(line, "\\u{:04x}", c as u32).unwrap(),
+            _ => line.push(c)
+        }
+    }
+    line.push('"');
+}
+
+/// Since JSON isn't line-based, this buffers the entire document and parses it once it's finished.
+#[derive(Default)]
+pub struct JsonLineProcessor {
+    text: String
+}
+impl MappingsLineProcessor for JsonLineProcessor {
+    #[inline]
+    fn process_line(&mut self, s: &str) -> Result<(), MappingsParseError> {
+        self.text.push_str(s);
+        self.text.push('\n');
+        Ok(())
+    }
+
+    fn finish(self) -> Result<FrozenMappings, MappingsParseError> {
+        let mut parser = JsonParser { text: &self.text, index: 0 };
+        parser.parse_document()
+            .and_then(|document| parse_mappings(&document))
+            .map_err(|(index, reason)| {
+                // Report the line containing the error, like the line-based formats do
+                let line_start = self.text[..index].rfind('\n').map_or(0, |newline| newline + 1);
+                let line_end = self.text[index..].find('\n').map_or(self.text.len(), |newline| index + newline);
+                MappingsParseError::InvalidLine {
+                    index: index - line_start,
+                    line: self.text[line_start..line_end].into(),
+                    line_number: Some(self.text[..line_start].matches('\n').count() + 1),
+                    reason: Some(reason)
+                }
+            })
+    }
+}
+
+fn parse_mappings(document: &JsonValue) -> JsonResult<FrozenMappings> {
+    let mut result = SimpleMappings::default();
+    for entry in document.array("classes")? {
+        result.set_remapped_class(
+            ReferenceType::from_internal_name(entry.internal_name("original")?),
+            ReferenceType::from_internal_name(entry.internal_name("renamed")?)
+        );
+    }
+    for entry in document.array("fields")? {
+        let mut original = FieldData::new(
+            entry.string("original")?.into(),
+            ReferenceType::from_internal_name(entry.internal_name("owner")?)
+        );
+        if let Some(descriptor) = entry.optional_string("descriptor")? {
+            let field_type = TypeDescriptor::parse_descriptor(descriptor)
+                .ok_or_else(|| entry.error(format!("Invalid descriptor: {:?}", descriptor)))?;
+            original.set_descriptor(Some(field_type));
+        }
+        result.set_field_name(original, entry.string("renamed")?.into());
+    }
+    for entry in document.array("methods")? {
+        let descriptor = entry.string("descriptor")?;
+        let signature = MethodSignature::parse_descriptor(descriptor)
+            .ok_or_else(|| entry.error(format!("Invalid descriptor: {:?}", descriptor)))?;
+        result.set_method_name(
+            MethodData::new(
+                entry.string("original")?.into(),
+                ReferenceType::from_internal_name(entry.internal_name("owner")?),
+                signature
+            ),
+            entry.string("renamed")?.into()
+        );
+    }
+    Ok(result.frozen())
+}
+
+/// A parsed JSON value, along with the index where it starts
+struct JsonValue {
+    start: usize,
+    kind: JsonKind
+}
+enum JsonKind {
+    /// A literal or number, whose value we never need
+    Other,
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>)
+}
+impl JsonValue {
+    fn error(&self, reason: String) -> (usize, String) {
+        (self.start, reason)
+    }
+    fn get(&self, key: &str) -> JsonResult<&JsonValue> {
+        match self.kind {
+            JsonKind::Object(ref entries) => entries.iter()
+                .find(|(entry_key, _)| entry_key == key)
+                .map(|(_, value)| value)
+                .ok_or_else(|| self.error(format!("Missing key {:?}", key))),
+            _ => Err(self.error(format!("Expected an object with the key {:?}", key)))
+        }
+    }
+    fn array(&self, key: &str) -> JsonResult<&[JsonValue]> {
+        let value = self.get(key)?;
+        match value.kind {
+            JsonKind::Array(ref values) => Ok(values),
+            _ => Err(value.error(format!("Expected {:?} to be an array", key)))
+        }
+    }
+    /// Get the string with the specified key, or `None` if the object doesn't have it
+    fn optional_string(&self, key: &str) -> JsonResult<Option<&str>> {
+        match self.kind {
+            JsonKind::Object(ref entries) if entries.iter().all(|(entry_key, _)| entry_key != key) => Ok(None),
+            _ => self.string(key).map(Some)
+        }
+    }
+    fn string(&self, key: &str) -> JsonResult<&str> {
+        let value = self.get(key)?;
+        match value.kind {
+            JsonKind::String(ref s) => Ok(s),
+            _ => Err(value.error(format!("Expected {:?} to be a string", key)))
+        }
+    }
+    fn internal_name(&self, key: &str) -> JsonResult<&str> {
+        let name = self.string(key)?;
+        if name.is_empty() || name.contains('.') {
+            return Err(self.get(key)?.error(format!("Invalid internal name: {:?}", name)))
+        }
+        Ok(name)
+    }
+}
+
+/// A minimal JSON parser, giving the index and reason of any errors
+struct JsonParser<'a> {
+    text: &'a str,
+    index: usize
+}
+type JsonResult<T> = Result<T, (usize, String)>;
+impl<'a> JsonParser<'a> {
+    fn parse_document(&mut self) -> JsonResult<JsonValue> {
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        if self.index != self.text.len() {
+            return Err(self.error("Unexpected trailing data"))
+        }
+        Ok(value)
+    }
+    fn error(&self, reason: &str) -> (usize, String) {
+        (self.index, reason.into())
+    }
+    fn skip_whitespace(&mut self) {
+        let remaining = &self.text[self.index..];
+        self.index += remaining.len() - remaining.trim_start_matches(&[' ', '\t', '\n', '\r'][..]).len();
+    }
+    fn peek(&self) -> Option<char> {
+        self.text[self.index..].chars().next()
+    }
+    fn expect(&mut self, expected: char) -> JsonResult<()> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.index += expected.len_utf8();
+            Ok(())
+        } else {
+            Err((self.index, format!("Expected {:?}", expected)))
+        }
+    }
+    fn parse_value(&mut self) -> JsonResult<JsonValue> {
+        self.skip_whitespace();
+        let start = self.index;
+        let kind = self.parse_kind()?;
+        Ok(JsonValue { start, kind })
+    }
+    fn parse_kind(&mut self) -> JsonResult<JsonKind> {
+        let remaining = &self.text[self.index..];
+        match self.peek() {
+            Some('{') => {
+                self.index += 1;
+                let mut entries = Vec::new();
+                self.parse_sequence('}', |parser| {
+                    parser.skip_whitespace();
+                    let key = parser.parse_string()?;
+                    parser.expect(':')?;
+                    entries.push((key, parser.parse_value()?));
+                    Ok(())
+                })?;
+                Ok(JsonKind::Object(entries))
+            },
+            Some('[') => {
+                self.index += 1;
+                let mut values = Vec::new();
+                self.parse_sequence(']', |parser| {
+                    values.push(parser.parse_value()?);
+                    Ok(())
+                })?;
+                Ok(JsonKind::Array(values))
+            },
+            Some('"') => Ok(JsonKind::String(self.parse_string()?)),
+            _ if remaining.starts_with("null") || remaining.starts_with("true") => {
+                self.index += 4;
+                Ok(JsonKind::Other)
+            },
+            _ if remaining.starts_with("false") => {
+                self.index += 5;
+                Ok(JsonKind::Other)
+            },
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                // We never need the value of numbers, just skip over them
+                let length = remaining.find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+                    .unwrap_or(remaining.len());
+                self.index += length;
+                Ok(JsonKind::Other)
+            },
+            _ => Err(self.error("Expected a value"))
+        }
+    }
+    /// Parse comma-separated entries until the closing character
+    fn parse_sequence<F>(&mut self, end: char, mut func: F) -> JsonResult<()>
+        where F: FnMut(&mut Self) -> JsonResult<()> {
+        self.skip_whitespace();
+        if self.peek() == Some(end) {
+            self.index += 1;
+            return Ok(())
+        }
+        loop {
+            func(self)?;
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.index += 1,
+                Some(c) if c == end => {
+                    self.index += 1;
+                    return Ok(())
+                },
+                _ => return Err((self.index, format!("Expected ',' or {:?}", end)))
+            }
+        }
+    }
+    fn parse_string(&mut self) -> JsonResult<String> {
+        if self.peek() != Some('"') {
+            return Err(self.error("Expected a string"))
+        }
+        self.index += 1;
+        let mut result = String::new();
+        loop {
+            let remaining = &self.text[self.index..];
+            let length = remaining.find(&['"', '\\'][..])
+                .ok_or_else(|| self.error("Unterminated string"))?;
+            result.push_str(&remaining[..length]);
+            self.index += length;
+            if self.peek() == Some('"') {
+                self.index += 1;
+                return Ok(result)
+            }
+            let escape_start = self.index;
+            self.index += 1;
+            let escaped = match self.peek() {
+                Some('"') => '"',
+                Some('\\') => '\\',
+                Some('/') => '/',
+                Some('b') => '\u{8}',
+                Some('f') => '\u{c}',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('u') => {
+                    self.index += 1;
+                    let first = self.parse_hex()?;
+                    let code = if (0xD800..0xDC00).contains(&first) {
+                        // Characters outside the BMP are encoded as surrogate pairs
+                        if !self.text[self.index..].starts_with("\\u") {
+                            return Err((escape_start, "Unpaired surrogate".into()))
+                        }
+                        self.index += 2;
+                        let second = self.parse_hex()?;
+                        if !(0xDC00..0xE000).contains(&second) {
+                            return Err((escape_start, "Unpaired surrogate".into()))
+                        }
+                        0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00)
+                    } else {
+                        first
+                    };
+                    result.push(std::char::from_u32(code)
+                        .ok_or_else(|| (escape_start, "Invalid unicode escape".to_string()))?);
+                    continue
+                },
+                _ => return Err((escape_start, "Invalid escape".into()))
+            };
+            self.index += 1;
+            result.push(escaped);
+        }
+    }
+    fn parse_hex(&mut self) -> JsonResult<u32> {
+        let digits = self.text.get(self.index..self.index + 4)
+            .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("Invalid unicode escape"))?;
+        self.index += 4;
+        Ok(u32::from_str_radix(digits, 16).unwrap())
+    }
+}
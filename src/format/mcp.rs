@@ -0,0 +1,58 @@
+//! Support for the legacy MCP `fields.csv` and `methods.csv` files,
+//! which give human names for searge names like `field_12345_a`.
+use crate::prelude::*;
+use crate::utils::{FnvIndexMap, FnvIndexSet};
+
+/// Apply the human names from MCP's `fields.csv` and `methods.csv` to the renamed side of the base mappings.
+///
+/// Each CSV has the columns `searge,name,side,desc`, and only the first two are used.
+/// Members are matched by their current renamed name,
+/// and members without a human name are left unchanged.
+pub fn apply_mcp_csv(base: &FrozenMappings, fields_csv: &str, methods_csv: &str) -> FrozenMappings {
+    let field_names = parse_csv_names(fields_csv);
+    let method_names = parse_csv_names(methods_csv);
+    base.transform_fields(|field| field_names.get(&*field.name).map(|&name| name.into()))
+        .transform_methods(|method| method_names.get(&*method.name).map(|&name| name.into()))
+}
+
+/// Export the human names of the mappings as MCP's `fields.csv` and `methods.csv`,
+/// assuming the mappings go from searge names to human names.
+///
+/// Each CSV has the header `searge,name,side,desc`, and the side is always `2`.
+/// Methods give their searge descriptor in the `desc` column, while fields leave it blank.
+/// Members which share a searge name (like overriding methods) are only exported once.
+pub fn export_mcp_csv(mappings: &FrozenMappings) -> (String, String) {
+    let fields = write_csv(mappings.fields().map(|(original, renamed)| {
+        (&*original.name, &*renamed.name, "")
+    }));
+    let methods = write_csv(mappings.methods().map(|(original, renamed)| {
+        (&*original.name, &*renamed.name, original.signature().descriptor())
+    }));
+    (fields, methods)
+}
+
+fn write_csv<'a, I: Iterator<Item=(&'a str, &'a str, &'a str)>>(rows: I) -> String {
+    let mut result = String::from("searge,name,side,desc\n");
+    let mut seen = FnvIndexSet::default();
+    for (searge, name, desc) in rows {
+        if !seen.insert(searge) { continue }
+        result.push_str(&format!("{},{},2,{}\n", searge, name, desc));
+    }
+    result
+}
+
+/// Parse the searge and human names from the first two columns of each row,
+/// skipping the header row and any rows that are missing a name.
+fn parse_csv_names(csv: &str) -> FnvIndexMap<&str, &str> {
+    let mut result = FnvIndexMap::default();
+    for line in csv.lines() {
+        let mut columns = line.trim_end_matches('\r').split(',');
+        let (searge, name) = match (columns.next(), columns.next()) {
+            (Some(searge), Some(name)) => (searge.trim(), name.trim()),
+            _ => continue
+        };
+        if searge.is_empty() || name.is_empty() || searge == "searge" { continue }
+        result.insert(searge, name);
+    }
+    result
+}
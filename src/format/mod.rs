@@ -1,4 +1,13 @@
+use std::fmt;
+use std::borrow::Cow;
 use std::io::{self, BufRead, Write};
+#[cfg(feature = "gzip")]
+use std::io::{Read, BufReader};
+
+#[cfg(feature = "gzip")]
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
 use failure_derive::Fail;
 
@@ -7,19 +16,51 @@ use crate::prelude::*;
 pub mod srg;
 pub mod csrg;
 pub mod tsrg;
+pub mod tiny;
+pub mod proguard;
+pub mod enigma;
+pub mod mcp;
+pub mod json;
 
 #[derive(Debug, Fail)]
 pub enum MappingsParseError {
-    #[fail(display = "{}", _0)]
     Io(#[cause] io::Error),
-    // TODO: Somehow include reason
-    #[fail(display = "Invalid line at {}: {:?}", index, line)]
     InvalidLine {
         line: String,
+        /// The index of the error within the line
         index: usize,
+        /// The 1-based line number of the line within the file,
+        /// which is only known when the whole file is parsed at once.
+        line_number: Option<usize>,
         reason: Option<String>
     }
 }
+impl MappingsParseError {
+    /// Give the line number of an invalid line, unless it already has one
+    fn at_line(mut self, number: usize) -> Self {
+        if let MappingsParseError::InvalidLine { ref mut line_number, .. } = self {
+            line_number.get_or_insert(number);
+        }
+        self
+    }
+}
+impl fmt::Display for MappingsParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MappingsParseError::Io(ref cause) => write!(f, "{}", cause),
+            MappingsParseError::InvalidLine { ref line, index, line_number, ref reason } => {
+                match line_number {
+                    Some(line_number) => write!(f, "Invalid line {} at {}: {:?}", line_number, index, line)?,
+                    None => write!(f, "Invalid line at {}: {:?}", index, line)?
+                }
+                match *reason {
+                    Some(ref reason) => write!(f, " ({})", reason),
+                    None => Ok(())
+                }
+            }
+        }
+    }
+}
 impl From<io::Error> for MappingsParseError {
     #[inline]
     fn from(e: io::Error) -> Self {
@@ -27,30 +68,233 @@ impl From<io::Error> for MappingsParseError {
     }
 }
 
+/// Options controlling how the text formats are parsed.
+///
+/// The default is the strict behavior of `parse_text`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Treat any run of spaces and tabs as a single separator,
+    /// for files whose whitespace has been reformatted by an editor.
+    ///
+    /// The indentation at the start of a line is left alone, since nested formats depend on it.
+    /// This is only supported by the SRG, CSRG, TSRG and proguard formats,
+    /// and is ignored by formats where whitespace can be part of a value (like comments).
+    pub collapse_whitespace: bool,
+    /// Keep the package renames given by `PK:` lines, so writing the mappings gives the package lines back.
+    ///
+    /// This is only supported by the SRG formats, which otherwise apply the renames to the classes and discard them.
+    pub keep_packages: bool
+}
+impl ParseOptions {
+    #[inline]
+    pub fn collapse_whitespace(mut self, collapse_whitespace: bool) -> Self {
+        self.collapse_whitespace = collapse_whitespace;
+        self
+    }
+    #[inline]
+    pub fn keep_packages(mut self, keep_packages: bool) -> Self {
+        self.keep_packages = keep_packages;
+        self
+    }
+}
+
+/// One of the supported mapping formats, for choosing a format at runtime
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MappingsFormatKind {
+    Srg,
+    ColonSrg,
+    CompactSrg,
+    TabSrg,
+    TabSrg2,
+    TinyV1,
+    TinyV2,
+    Proguard,
+    Enigma,
+    Json
+}
+impl MappingsFormatKind {
+    /// Parse the text using this format
+    pub fn parse_text(self, text: &str) -> Result<FrozenMappings, MappingsParseError> {
+        match self {
+            MappingsFormatKind::Srg => SrgMappingsFormat::parse_text(text),
+            MappingsFormatKind::ColonSrg => ColonSrgMappingsFormat::parse_text(text),
+            MappingsFormatKind::CompactSrg => CompactSrgMappingsFormat::parse_text(text),
+            MappingsFormatKind::TabSrg => TabSrgMappingsFormat::parse_text(text),
+            MappingsFormatKind::TabSrg2 => TabSrg2MappingsFormat::parse_text(text),
+            MappingsFormatKind::TinyV1 => TinyV1MappingsFormat::parse_text(text),
+            MappingsFormatKind::TinyV2 => TinyV2MappingsFormat::parse_text(text),
+            MappingsFormatKind::Proguard => ProguardMappingsFormat::parse_text(text),
+            MappingsFormatKind::Enigma => EnigmaMappingsFormat::parse_text(text),
+            MappingsFormatKind::Json => JsonMappingsFormat::parse_text(text)
+        }
+    }
+    /// Parse the text using this format and the specified options
+    pub fn parse_text_with_options(self, text: &str, options: ParseOptions) -> Result<FrozenMappings, MappingsParseError> {
+        match self {
+            MappingsFormatKind::Srg => SrgMappingsFormat::parse_text_with_options(text, options),
+            MappingsFormatKind::ColonSrg => ColonSrgMappingsFormat::parse_text_with_options(text, options),
+            MappingsFormatKind::CompactSrg => CompactSrgMappingsFormat::parse_text_with_options(text, options),
+            MappingsFormatKind::TabSrg => TabSrgMappingsFormat::parse_text_with_options(text, options),
+            MappingsFormatKind::TabSrg2 => TabSrg2MappingsFormat::parse_text_with_options(text, options),
+            MappingsFormatKind::TinyV1 => TinyV1MappingsFormat::parse_text_with_options(text, options),
+            MappingsFormatKind::TinyV2 => TinyV2MappingsFormat::parse_text_with_options(text, options),
+            MappingsFormatKind::Proguard => ProguardMappingsFormat::parse_text_with_options(text, options),
+            MappingsFormatKind::Enigma => EnigmaMappingsFormat::parse_text_with_options(text, options),
+            MappingsFormatKind::Json => JsonMappingsFormat::parse_text_with_options(text, options)
+        }
+    }
+}
+
+/// Parse text in an unknown format by trying SRG, TSRG and then CSRG,
+/// giving the result of the first one that succeeds along with its format.
+///
+/// If none of them succeed, this gives the error from each format in the same order.
+#[inline]
+pub fn parse_try_all(text: &str) -> Result<(FrozenMappings, MappingsFormatKind), Vec<MappingsParseError>> {
+    parse_try_each(text, &[MappingsFormatKind::Srg, MappingsFormatKind::TabSrg, MappingsFormatKind::CompactSrg])
+}
+/// Parse text in an unknown format by trying each of the specified formats in order,
+/// giving the result of the first one that succeeds along with its format.
+///
+/// Since some formats are more lenient than others, the strictest ones should be tried first.
+/// For example, SRG lines also happen to be valid compact SRG lines.
+pub fn parse_try_each(text: &str, formats: &[MappingsFormatKind]) -> Result<(FrozenMappings, MappingsFormatKind), Vec<MappingsParseError>> {
+    let mut errors = Vec::with_capacity(formats.len());
+    for &format in formats {
+        match format.parse_text(text) {
+            Ok(mappings) => return Ok((mappings, format)),
+            Err(error) => errors.push(error)
+        }
+    }
+    Err(errors)
+}
+
 pub trait MappingsFormat {
     type Processor: MappingsLineProcessor;
-    fn parse_stream<R: BufRead>(mut read: R) -> Result<FrozenMappings, MappingsParseError> {
-        let mut buffer = String::new();
+    /// Whether this format supports `ParseOptions::collapse_whitespace`,
+    /// which is ignored by the other formats.
+    const SUPPORTS_COLLAPSE_WHITESPACE: bool = false;
+    fn parse_stream<R: BufRead>(read: R) -> Result<FrozenMappings, MappingsParseError> {
         let mut processer = Self::processor();
-        loop {
-            buffer.clear();
-            if read.read_line(&mut buffer)? == 0 { break }
-            processer.process_line(buffer.trim_right_matches('\n'))?;
-        }
+        process_stream(&mut processer, read)?;
         processer.finish()
     }
     fn parse_lines<I: IntoIterator>(lines: I) -> Result<FrozenMappings, MappingsParseError>
         where I::Item: AsRef<str>  {
         let mut processer = Self::processor();
-        for line in lines {
-            processer.process_line(line.as_ref())?;
+        for (index, line) in lines.into_iter().enumerate() {
+            processer.process_line(line.as_ref())
+                .map_err(|e| e.at_line(index + 1))?;
         }
         processer.finish()
     }
     fn parse_text(text: &str) -> Result<FrozenMappings, MappingsParseError> {
         Self::parse_lines(text.lines())
     }
+    /// Parse the lines using the specified options.
+    ///
+    /// Any errors refer to the lines as they were adjusted by the options.
+    fn parse_lines_with_options<I: IntoIterator>(lines: I, options: ParseOptions) -> Result<FrozenMappings, MappingsParseError>
+        where I::Item: AsRef<str> {
+        let mut processer = Self::processor_with_options(options);
+        for (index, line) in lines.into_iter().enumerate() {
+            processer.process_line(&Self::normalize_line(line.as_ref(), options))
+                .map_err(|e| e.at_line(index + 1))?;
+        }
+        processer.finish()
+    }
+    /// Parse the text using the specified options
+    #[inline]
+    fn parse_text_with_options(text: &str, options: ParseOptions) -> Result<FrozenMappings, MappingsParseError> {
+        Self::parse_lines_with_options(text.lines(), options)
+    }
+    /// Adjust the line according to the options, before it's given to the processor.
+    ///
+    /// Formats which don't support an option just ignore it.
+    #[inline]
+    fn normalize_line(line: &str, options: ParseOptions) -> Cow<'_, str> {
+        if Self::SUPPORTS_COLLAPSE_WHITESPACE && options.collapse_whitespace {
+            collapse_whitespace(line, ' ')
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+    /// Parse the text in parallel, splitting it into chunks at the start of each class.
+    ///
+    /// Each chunk is processed separately and the results are merged in order,
+    /// so this gives the same result as `parse_text` regardless of scheduling.
+    /// This only helps large files, and is only supported by formats whose classes are self-contained.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    fn parse_parallel(text: &str) -> Result<FrozenMappings, MappingsParseError>
+        where Self::Processor: MergeableLineProcessor + Send {
+        Self::parse_parallel_chunks(text, PARALLEL_CHUNK_SIZE)
+    }
+    /// Parse the text in parallel, using chunks of roughly the specified number of bytes.
+    #[cfg(feature = "rayon")]
+    fn parse_parallel_chunks(text: &str, chunk_size: usize) -> Result<FrozenMappings, MappingsParseError>
+        where Self::Processor: MergeableLineProcessor + Send {
+        let chunks = split_chunks(text, chunk_size);
+        let processors: Vec<Result<Self::Processor, MappingsParseError>> = chunks.par_iter()
+            .map(|&(first_line, chunk)| {
+                let mut processer = Self::processor();
+                for (index, line) in chunk.lines().enumerate() {
+                    processer.process_line(line)
+                        .map_err(|e| e.at_line(first_line + index))?;
+                }
+                Ok(processer)
+            })
+            .collect();
+        let mut processors = processors.into_iter();
+        let mut result = match processors.next() {
+            Some(first) => first?,
+            None => Self::processor()
+        };
+        for processer in processors {
+            result.merge(processer?)?;
+        }
+        result.finish()
+    }
+    /// Parse a gzip-compressed stream, like a `.tsrg.gz` file.
+    ///
+    /// Any errors decompressing the stream are given as IO errors.
+    #[cfg(feature = "gzip")]
+    fn parse_gzip<R: Read>(read: R) -> Result<FrozenMappings, MappingsParseError> {
+        Self::parse_stream(BufReader::new(GzDecoder::new(read)))
+    }
+    /// Parse the stream, then invert the result so it maps renamed names back to the originals.
+    #[inline]
+    fn parse_stream_inverted<R: BufRead>(read: R) -> Result<FrozenMappings, MappingsParseError> {
+        Ok(Self::parse_stream(read)?.inverted())
+    }
+    /// Parse the lines, then invert the result so it maps renamed names back to the originals.
+    #[inline]
+    fn parse_lines_inverted<I: IntoIterator>(lines: I) -> Result<FrozenMappings, MappingsParseError>
+        where I::Item: AsRef<str> {
+        Ok(Self::parse_lines(lines)?.inverted())
+    }
+    /// Parse the text, then invert the result so it maps renamed names back to the originals.
+    #[inline]
+    fn parse_text_inverted(text: &str) -> Result<FrozenMappings, MappingsParseError> {
+        Ok(Self::parse_text(text)?.inverted())
+    }
     fn write<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, writer: W) -> io::Result<()>;
+    /// Write the mappings to the writer, compressing them with gzip
+    #[cfg(feature = "gzip")]
+    fn write_gzip<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, writer: W) -> io::Result<()> {
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        Self::write(mappings, &mut encoder)?;
+        encoder.finish()?;
+        Ok(())
+    }
+    /// Write the mappings to the writer, sorted by their original names
+    ///
+    /// This gives deterministic output regardless of the order the mappings were built in,
+    /// which keeps diffs between versions small.
+    /// Sorting has a cost, so `write` should be preferred if the order doesn't matter.
+    fn write_sorted<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, writer: W) -> io::Result<()> {
+        Self::write(&mappings.frozen().sorted(), writer)
+    }
     fn write_line_array<'a, T: IterableMappings<'a>>(mappings: &'a T) -> Vec<String> {
         Self::write_string(mappings).lines().map(String::from).collect()
     }
@@ -60,8 +304,88 @@ pub trait MappingsFormat {
         String::from_utf8(buffer).unwrap()
     }
     fn processor() -> Self::Processor;
+    /// Create a processor for the options that affect the parsed mappings instead of the lines.
+    ///
+    /// Formats which don't support any of those options just give the default processor.
+    #[inline]
+    fn processor_with_options(_options: ParseOptions) -> Self::Processor {
+        Self::processor()
+    }
 }
 pub trait MappingsLineProcessor {
     fn process_line(&mut self, s: &str) -> Result<(), MappingsParseError>;
     fn finish(self) -> Result<FrozenMappings, MappingsParseError>;
+}
+/// A line processor which can be combined with another one,
+/// so separate chunks of a file can be processed independently.
+pub trait MergeableLineProcessor: MappingsLineProcessor {
+    /// Add the results of the other processor, which processed the lines after ours.
+    fn merge(&mut self, other: Self) -> Result<(), MappingsParseError>;
+}
+/// The rough number of bytes in each chunk parsed by `parse_parallel`
+#[cfg(feature = "rayon")]
+const PARALLEL_CHUNK_SIZE: usize = 256 * 1024;
+/// Split the text into chunks of at least the specified size, along with the line number each one starts at.
+///
+/// We only split before lines that don't start with whitespace or a comment,
+/// so the members nested under a class stay in the same chunk.
+#[cfg(feature = "rayon")]
+fn split_chunks(text: &str, chunk_size: usize) -> Vec<(usize, &str)> {
+    let mut chunks = Vec::new();
+    let (mut start, mut offset) = (0, 0);
+    let (mut first_line, mut line_number) = (1, 1);
+    for line in text.split_inclusive('\n') {
+        if offset - start >= chunk_size && !line.starts_with(|c: char| c.is_whitespace() || c == '#') {
+            chunks.push((first_line, &text[start..offset]));
+            start = offset;
+            first_line = line_number;
+        }
+        offset += line.len();
+        line_number += 1;
+    }
+    if start < text.len() {
+        chunks.push((first_line, &text[start..]));
+    }
+    chunks
+}
+/// The placeholder descriptor we write for fields whose actual types we don't know,
+/// in formats which need a type for every field.
+///
+/// This is read back as the field's type, since it could also be a field that really is an `Object`.
+pub(crate) const UNKNOWN_FIELD_DESCRIPTOR: &str = "Ljava/lang/Object;";
+/// Replace each run of whitespace after the line's indentation with a single separator,
+/// removing any trailing whitespace.
+pub(crate) fn collapse_whitespace(line: &str, separator: char) -> Cow<'_, str> {
+    let content = line.trim_start();
+    let mut result = String::with_capacity(line.len());
+    result.push_str(&line[..line.len() - content.len()]);
+    for (index, part) in content.split_whitespace().enumerate() {
+        if index > 0 {
+            result.push(separator);
+        }
+        result.push_str(part);
+    }
+    if result == line {
+        Cow::Borrowed(line)
+    } else {
+        Cow::Owned(result)
+    }
+}
+/// Process each line of the stream, giving any errors the number of the line.
+///
+/// Line endings are stripped just like `str::lines`,
+/// so we report the same line numbers as parsing the text directly.
+pub(crate) fn process_stream<P: MappingsLineProcessor, R: BufRead>(processer: &mut P, mut read: R) -> Result<(), MappingsParseError> {
+    let mut buffer = String::new();
+    let mut line_number = 0;
+    loop {
+        buffer.clear();
+        if read.read_line(&mut buffer)? == 0 { break }
+        line_number += 1;
+        let line = buffer.strip_suffix('\n').unwrap_or(&buffer);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        processer.process_line(line)
+            .map_err(|e| e.at_line(line_number))?;
+    }
+    Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,216 @@
+use std::io::{self, Write};
+use std::borrow::Borrow;
+
+use crate::prelude::*;
+use super::{MappingsLineProcessor, MappingsFormat, MappingsParseError};
+use super::tsrg::ClassData;
+use crate::utils::*;
+
+/// The placeholder type we write for fields whose actual types we don't know
+const UNKNOWN_FIELD_TYPE: &str = "java.lang.Object";
+
+/// The ProGuard mapping format, with indented members using dotted names and readable types.
+///
+/// ProGuard lists mappings from the original names to the obfuscated names,
+/// so the renamed side of the parsed mappings is the short obfuscated name.
+/// Line number ranges are ignored, as are the entries for inlined methods.
+pub struct ProguardMappingsFormat;
+impl MappingsFormat for ProguardMappingsFormat {
+    type Processor = ProguardLineProcessor;
+    const SUPPORTS_COLLAPSE_WHITESPACE: bool = true;
+
+    fn write<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, mut writer: W) -> io::Result<()> {
+        let data = ClassData::from_mappings(mappings);
+        // Reuse the same buffer for every line, so we don't allocate for readable names
+        let mut line = String::new();
+        for (declaring_type, data) in data.iter() {
+            let renamed_type = data.renamed_type.unwrap_or(declaring_type);
+            line.clear();
+            declaring_type.push_name(&mut line);
+            line.push_str(" -> ");
+            renamed_type.push_name(&mut line);
+            line.push_str(":\n");
+            writer.write_all(line.as_bytes())?;
+            for (original, renamed) in &data.fields {
+                line.clear();
+                line.push_str("    ");
+                match original.descriptor() {
+                    Some(field_type) => field_type.push_name(&mut line),
+                    None => line.push_str(UNKNOWN_FIELD_TYPE)
+                }
+                line.push(' ');
+                line.push_str(&original.name);
+                line.push_str(" -> ");
+                line.push_str(&renamed.borrow().name);
+                line.push('\n');
+                writer.write_all(line.as_bytes())?;
+            }
+            for (original, renamed) in &data.methods {
+                let signature = original.signature();
+                line.clear();
+                line.push_str("    ");
+                signature.return_type().push_name(&mut line);
+                line.push(' ');
+                line.push_str(&original.name);
+                line.push('(');
+                for (index, parameter_type) in signature.parameter_types().iter().enumerate() {
+                    if index > 0 {
+                        line.push(',');
+                    }
+                    parameter_type.push_name(&mut line);
+                }
+                line.push_str(") -> ");
+                line.push_str(&renamed.borrow().name);
+                line.push('\n');
+                writer.write_all(line.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn processor() -> ProguardLineProcessor {
+        ProguardLineProcessor::default()
+    }
+}
+
+#[derive(Default)]
+pub struct ProguardLineProcessor {
+    result: SimpleMappings,
+    current_class: Option<ReferenceType>
+}
+/// Parse a readable type name like `int[]` or `java.lang.String`
+fn parse_readable_type(name: &str) -> Option<TypeDescriptor> {
+    let element_name = name.trim_end_matches("[]");
+    let dimensions = (name.len() - element_name.len()) / 2;
+    let element_type = match element_name {
+        "byte" => PrimitiveType::Byte.into_type_descriptor(),
+        "short" => PrimitiveType::Short.into_type_descriptor(),
+        "int" => PrimitiveType::Int.into_type_descriptor(),
+        "long" => PrimitiveType::Long.into_type_descriptor(),
+        "float" => PrimitiveType::Float.into_type_descriptor(),
+        "double" => PrimitiveType::Double.into_type_descriptor(),
+        "char" => PrimitiveType::Char.into_type_descriptor(),
+        "boolean" => PrimitiveType::Boolean.into_type_descriptor(),
+        "void" if dimensions == 0 => PrimitiveType::Void.into_type_descriptor(),
+        _ if is_class_name(element_name) => ReferenceType::from_name(element_name).into_type_descriptor(),
+        _ => return None
+    };
+    if dimensions == 0 {
+        Some(element_type)
+    } else {
+        Some(ArrayType::new(dimensions, element_type).into_type_descriptor())
+    }
+}
+fn is_class_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(|c: char| c == '/' || c == '[' || c.is_whitespace())
+}
+fn readable_type(name: &str, index: usize) -> Result<TypeDescriptor, SimpleParseError> {
+    parse_readable_type(name).ok_or_else(|| SimpleParseError {
+        index, reason: Some(format!("Invalid type: {:?}", name))
+    })
+}
+/// Skip a line number prefix like `12:34:`
+fn skip_line_numbers(parser: &mut SimpleParser) -> Result<(), SimpleParseError> {
+    while parser.peek()?.is_ascii_digit() {
+        parser.take_until(|c| !c.is_ascii_digit());
+        parser.expect(':')?;
+    }
+    Ok(())
+}
+impl ProguardLineProcessor {
+    fn parse_line(&mut self, parser: &mut SimpleParser) -> Result<(), SimpleParseError> {
+        parser.strip_comment();
+        if parser.remaining().trim().is_empty() { return Ok(()) }
+        if !parser.peek()?.is_whitespace() {
+            // We have a new class entry
+            let start = parser.current_index();
+            let original = parser.take_until(|c| c == ' ');
+            parser.expect_str(" -> ")?;
+            let renamed = parser.take_until(|c| c == ':');
+            parser.expect(':')?;
+            parser.ensure_finished()?;
+            for name in &[original, renamed] {
+                if !is_class_name(name) {
+                    return Err(SimpleParseError { index: start, reason: Some(format!("Invalid class name: {:?}", name)) })
+                }
+            }
+            let original = ReferenceType::from_name(original);
+            self.result.set_remapped_class(original.clone(), ReferenceType::from_name(renamed));
+            self.current_class = Some(original);
+            return Ok(())
+        }
+        parser.skip_whitespace();
+        let current_class = self.current_class.clone()
+            .ok_or_else(|| SimpleParseError {
+                index: parser.current_index(),
+                reason: Some("Missing current class".into()),
+            })?;
+        skip_line_numbers(parser)?;
+        let type_start = parser.current_index();
+        let type_name = parser.take_until(|c| c == ' ');
+        let member_type = readable_type(type_name, type_start)?;
+        parser.expect(' ')?;
+        let original_name = parser.take_until(|c| c == ' ' || c == '(');
+        if parser.peek()? == '(' {
+            parser.expect('(')?;
+            let mut parameter_types = Vec::new();
+            let parameters_start = parser.current_index();
+            let parameters = parser.take_until(|c| c == ')');
+            if !parameters.is_empty() {
+                for parameter in parameters.split(',') {
+                    parameter_types.push(readable_type(parameter, parameters_start)?);
+                }
+            }
+            parser.expect(')')?;
+            let signature = MethodSignature::try_new(member_type, parameter_types)
+                .map_err(|cause| SimpleParseError {
+                    index: parameters_start,
+                    reason: Some(cause.to_string())
+                })?;
+            // Skip the original line numbers of inlined methods
+            if parser.peek()? == ':' {
+                parser.expect(':')?;
+                parser.take_until(|c| c == ' ');
+            }
+            parser.expect_str(" -> ")?;
+            let renamed_name = parser.take_until(|c| c == ' ');
+            parser.ensure_finished()?;
+            // Methods inlined from other classes are qualified with their declaring class
+            if !original_name.contains('.') {
+                self.result.set_method_name(
+                    MethodData::new(
+                        original_name.into(), current_class, signature
+                    ),
+                    renamed_name.into()
+                );
+            }
+        } else {
+            parser.expect_str(" -> ")?;
+            let renamed_name = parser.take_until(|c| c == ' ');
+            parser.ensure_finished()?;
+            self.result.set_field_name(
+                FieldData::new(original_name.into(), current_class).with_descriptor(member_type),
+                renamed_name.into()
+            );
+        }
+        Ok(())
+    }
+}
+impl MappingsLineProcessor for ProguardLineProcessor {
+    fn process_line(&mut self, s: &str) -> Result<(), MappingsParseError> {
+        let mut parser = SimpleParser::for_line(s);
+        self.parse_line(&mut parser)
+            .map_err(|cause| MappingsParseError::InvalidLine {
+                index: cause.index,
+                line: s.into(),
+                line_number: None,
+                reason: cause.reason
+            })
+    }
+
+    #[inline]
+    fn finish(self) -> Result<FrozenMappings, MappingsParseError> {
+        Ok(self.result.frozen())
+    }
+}
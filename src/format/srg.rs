@@ -2,55 +2,204 @@ use std::io::{self, Write};
 use std::borrow::Borrow;
 
 use crate::prelude::*;
-use super::{MappingsLineProcessor, MappingsFormat, MappingsParseError};
+use super::{MappingsLineProcessor, MergeableLineProcessor, MappingsFormat, MappingsParseError};
 use crate::utils::*;
+use crate::descriptor::split_member_name_with;
 
+/// The classic SRG format, with `CL:`, `FD:`, `MD:` and `PK:` lines.
+///
+/// Files in this format conventionally map from obfuscated names to deobfuscated (or searge) names.
+///
+/// The `PK:` lines are applied to the classes when parsing and then discarded,
+/// unless they're kept with `ParseOptions::keep_packages` or `SrgLineProcessor::keep_packages`.
 pub struct SrgMappingsFormat;
 impl MappingsFormat for SrgMappingsFormat {
     type Processor = SrgLineProcessor;
+    const SUPPORTS_COLLAPSE_WHITESPACE: bool = true;
 
-    fn write<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, mut writer: W) -> io::Result<()> {
-        for (original, renamed) in mappings.classes() {
-            writeln!(writer, "CL: {} {}", original.internal_name(), renamed.borrow().internal_name())?;
-        }
-        for (original, renamed) in mappings.fields() {
-            writeln!(writer, "FD: {} {}", original.internal_name(), renamed.borrow().internal_name())?;
-        }
-        for (original, renamed) in mappings.methods() {
-            writeln!(
-                writer, "MD: {} {} {} {}",
-                original.internal_name(),
-                original.signature().descriptor(),
-                renamed.borrow().internal_name(),
-                renamed.borrow().signature().descriptor()
-            )?;
-        }
-        Ok(())
+    #[inline]
+    fn write<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, writer: W) -> io::Result<()> {
+        write_srg(mappings, writer, '/')
+    }
+
+    #[inline]
+    fn write_sorted<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, writer: W) -> io::Result<()> {
+        write_srg_sorted(mappings, writer, '/')
     }
 
     #[inline]
     fn processor() -> SrgLineProcessor {
         SrgLineProcessor::default()
     }
+
+    #[inline]
+    fn processor_with_options(options: ParseOptions) -> SrgLineProcessor {
+        SrgLineProcessor::default().with_options(options)
+    }
+}
+
+/// A variant of the SRG format that separates members from their declaring type with a `:`,
+/// like `FD: obf4:a net/techcable/minecraft/Player:dead`
+pub struct ColonSrgMappingsFormat;
+impl MappingsFormat for ColonSrgMappingsFormat {
+    type Processor = SrgLineProcessor;
+    const SUPPORTS_COLLAPSE_WHITESPACE: bool = true;
+
+    #[inline]
+    fn write<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, writer: W) -> io::Result<()> {
+        write_srg(mappings, writer, ':')
+    }
+
+    #[inline]
+    fn write_sorted<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, writer: W) -> io::Result<()> {
+        write_srg_sorted(mappings, writer, ':')
+    }
+
+    #[inline]
+    fn processor() -> SrgLineProcessor {
+        SrgLineProcessor::with_member_separator(':')
+    }
+
+    #[inline]
+    fn processor_with_options(options: ParseOptions) -> SrgLineProcessor {
+        SrgLineProcessor::with_member_separator(':').with_options(options)
+    }
+}
+
+fn write_srg<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, mut writer: W, separator: char) -> io::Result<()> {
+    // The package renames are already applied to the classes, so we have to undo them,
+    // leaving out the package lines if that's impossible for some class
+    let packages = mappings.package_mappings().filter(|packages| {
+        mappings.classes().all(|(_, renamed)| unapplied_package(packages, renamed.split_name().0).is_some())
+    });
+    for (original, renamed) in packages.into_iter().flatten() {
+        writeln!(writer, "PK: {} {}", package_name(original), package_name(renamed))?;
+    }
+    for (original, renamed) in mappings.classes() {
+        match packages {
+            Some(packages) => {
+                let (package, simple_name) = renamed.split_name();
+                match unapplied_package(packages, package).unwrap() {
+                    "" => writeln!(writer, "CL: {} {}", original.internal_name(), simple_name)?,
+                    package => writeln!(writer, "CL: {} {}/{}", original.internal_name(), package, simple_name)?
+                }
+            },
+            None => writeln!(writer, "CL: {} {}", original.internal_name(), renamed.internal_name())?
+        }
+    }
+    for (original, renamed) in mappings.fields() {
+        let renamed = renamed.borrow();
+        writeln!(
+            writer, "FD: {}{}{} {}{}{}",
+            original.declaring_type().internal_name(), separator, original.name,
+            renamed.declaring_type().internal_name(), separator, renamed.name
+        )?;
+    }
+    for (original, renamed) in mappings.methods() {
+        let renamed = renamed.borrow();
+        writeln!(
+            writer, "MD: {}{}{} {} {}{}{} {}",
+            original.declaring_type().internal_name(), separator, original.name,
+            original.signature().descriptor(),
+            renamed.declaring_type().internal_name(), separator, renamed.name,
+            renamed.signature().descriptor()
+        )?;
+    }
+    Ok(())
+}
+
+/// Give a package that the package renames move into the specified package,
+/// so writing a class there gives back the class once the renames are applied again.
+///
+/// Returns `None` if the package is renamed, but no package is renamed to it.
+fn unapplied_package<'a>(packages: &'a FnvIndexMap<String, String>, package: &'a str) -> Option<&'a str> {
+    if packages.contains_key(package) {
+        packages.iter()
+            .find(|&(_, renamed)| renamed == package)
+            .map(|(original, _)| original.as_str())
+    } else {
+        Some(package)
+    }
+}
+
+/// The name of the package in a `PK:` line, using the magic `./` indicator for the default package
+#[inline]
+fn package_name(package: &str) -> &str {
+    if package.is_empty() { "./" } else { package }
+}
+
+/// Write the SRG lines in lexicographic order,
+/// which is the same order `FrozenMappings::srg_difference` compares them in.
+fn write_srg_sorted<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, mut writer: W, separator: char) -> io::Result<()> {
+    let mut buffer = Vec::new();
+    write_srg(mappings, &mut buffer, separator)?;
+    let text = String::from_utf8(buffer).unwrap();
+    let mut lines = text.lines().collect::<Vec<_>>();
+    lines.sort();
+    for line in lines {
+        writeln!(writer, "{}", line)?;
+    }
+    Ok(())
 }
 
-#[derive(Default)]
 pub struct SrgLineProcessor {
     result: SimpleMappings,
-    packages: FnvIndexMap<String, String>
+    packages: FnvIndexMap<String, String>,
+    member_separator: char,
+    strict: bool,
+    keep_packages: bool
+}
+impl Default for SrgLineProcessor {
+    #[inline]
+    fn default() -> Self {
+        SrgLineProcessor::with_member_separator('/')
+    }
 }
 impl SrgLineProcessor {
+    /// Create a processor which separates members from their declaring type with the specified character
+    #[inline]
+    pub fn with_member_separator(member_separator: char) -> Self {
+        SrgLineProcessor {
+            result: SimpleMappings::default(),
+            packages: FnvIndexMap::default(),
+            member_separator,
+            strict: false,
+            keep_packages: false
+        }
+    }
+    /// Error on conflicting `PK:` lines for the same package.
+    ///
+    /// Otherwise, the last `PK:` line for a package wins.
+    #[inline]
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+    /// Keep the `PK:` lines in the parsed mappings, so writing them gives the package lines back.
+    ///
+    /// Otherwise, the package renames are only applied to the classes and then discarded.
+    #[inline]
+    pub fn keep_packages(mut self) -> Self {
+        self.keep_packages = true;
+        self
+    }
+    #[inline]
+    fn with_options(mut self, options: ParseOptions) -> Self {
+        self.keep_packages = options.keep_packages;
+        self
+    }
     fn parse_line(&mut self, parser: &mut SimpleParser) -> Result<(), SimpleParseError> {
         parser.skip_whitespace();
-        if parser.is_finished() || parser.peek()? == '#' { return Ok(()) }
-        match parser.peek_str(2)? {
+        parser.strip_comment();
+        if parser.is_finished() { return Ok(()) }
+        match parser.peek_chars(2).unwrap_or_default() {
             "MD" => {
                 parser.expect_str("MD: ")?;
-                let original_internal_name = parser.parse::<JoinedInternalName>()?;
+                let original_internal_name = JoinedInternalName::parse_with_separator(parser, self.member_separator)?;
                 parser.expect(' ')?;
                 let original_signature = parser.parse::<MethodSignature>()?;
                 parser.expect(' ')?;
-                let renamed_internal_name = parser.parse::<JoinedInternalName>()?;
+                let renamed_internal_name = JoinedInternalName::parse_with_separator(parser, self.member_separator)?;
                 parser.expect(' ')?;
                 let renamed_signature = parser.parse::<MethodSignature>()?;
                 let original_data = MethodData::new(
@@ -67,9 +216,9 @@ impl SrgLineProcessor {
             },
             "FD" => {
                 parser.expect_str("FD: ")?;
-                let original_internal_name = parser.parse::<JoinedInternalName>()?;
+                let original_internal_name = JoinedInternalName::parse_with_separator(parser, self.member_separator)?;
                 parser.expect(' ')?;
-                let renamed_internal_name = parser.parse::<JoinedInternalName>()?;
+                let renamed_internal_name = JoinedInternalName::parse_with_separator(parser, self.member_separator)?;
                 let original_data = FieldData::new(
                     original_internal_name.name,
                     original_internal_name.declaring_type
@@ -97,10 +246,26 @@ impl SrgLineProcessor {
                     original.clear();
                 }
                 parser.expect(' ')?;
-                let renamed = parser.take_until(|c| c == ' ').into();
+                let renamed_start = parser.current_index();
+                let mut renamed: String = parser.take_until(|c| c == ' ').into();
+                if renamed == "./" {
+                    renamed.clear();
+                }
+                match self.packages.get(&original) {
+                    Some(existing) if self.strict && *existing != renamed => {
+                        return Err(SimpleParseError {
+                            index: renamed_start,
+                            reason: Some(format!(
+                                "Conflicting renames for package {:?}: {:?} and {:?}",
+                                original, existing, renamed
+                            ))
+                        })
+                    },
+                    _ => {}
+                }
                 self.packages.insert(original, renamed);
             }
-            _ => return Err(parser.error())
+            _ => return Err(parser.expected("CL, FD, MD or PK"))
         }
         parser.skip_whitespace();
         parser.ensure_finished()?;
@@ -112,33 +277,71 @@ struct JoinedInternalName {
     declaring_type: ReferenceType,
     name: String
 }
-impl SimpleParse for JoinedInternalName {
-    fn parse(parser: &mut SimpleParser) -> Result<Self, SimpleParseError> {
+impl JoinedInternalName {
+    /// Parse a joined name, using the specified character to separate the member from its declaring type
+    fn parse_with_separator(parser: &mut SimpleParser, separator: char) -> Result<Self, SimpleParseError> {
         let start = parser.current_index();
         let s = parser.parse_internal_name()?;
-        match s.rfind('/') {
-            Some(index) => {
-                let declaring_type = ReferenceType::from_internal_name(&s[..index]);
-                let name = String::from(&s[(index + 1)..]);
-                Ok(JoinedInternalName { declaring_type, name })
-            },
+        match split_member_name_with(s, separator) {
+            Some((declaring_type, name)) => Ok(JoinedInternalName { declaring_type, name: name.into() }),
             None => Err(SimpleParseError { index: start, reason: Some(format!("Invalid joined name: {:?}", s)) })
         }
     }
 }
+impl SimpleParse for JoinedInternalName {
+    #[inline]
+    fn parse(parser: &mut SimpleParser) -> Result<Self, SimpleParseError> {
+        Self::parse_with_separator(parser, '/')
+    }
+}
 impl MappingsLineProcessor for SrgLineProcessor {
     fn process_line(&mut self, s: &str) -> Result<(), MappingsParseError> {
-        let mut parser = SimpleParser::new(s);
+        let mut parser = SimpleParser::for_line(s);
         self.parse_line(&mut parser)
             .map_err(|cause| MappingsParseError::InvalidLine {
                 index: cause.index,
                 line: s.into(),
+                line_number: None,
                 reason: cause.reason
             })
     }
 
     #[inline]
     fn finish(self) -> Result<FrozenMappings, MappingsParseError> {
-        Ok(self.result.transform_packages(|s| self.packages.get(s).cloned()))
+        if self.packages.is_empty() {
+            // Most files don't have any package directives, so we can skip the transform
+            return Ok(self.result.frozen())
+        }
+        let result = self.result.transform_packages(|s| self.packages.get(s).cloned());
+        if self.keep_packages {
+            Ok(result.with_package_mappings(self.packages))
+        } else {
+            Ok(result)
+        }
+    }
+}
+impl MergeableLineProcessor for SrgLineProcessor {
+    fn merge(&mut self, other: Self) -> Result<(), MappingsParseError> {
+        self.result.extend(other.result);
+        for (original, renamed) in other.packages {
+            match self.packages.get(&original) {
+                Some(existing) if self.strict && *existing != renamed => {
+                    // We don't have the original line anymore, so give the equivalent one
+                    let original_name = package_name(&original);
+                    return Err(MappingsParseError::InvalidLine {
+                        index: "PK: ".len() + original_name.len() + 1,
+                        line: format!("PK: {} {}", original_name, package_name(&renamed)),
+                        line_number: None,
+                        reason: Some(format!(
+                            "Conflicting renames for package {:?}: {:?} and {:?}",
+                            original, existing, renamed
+                        ))
+                    })
+                },
+                _ => {}
+            }
+            self.packages.insert(original, renamed);
+        }
+        Ok(())
     }
 }
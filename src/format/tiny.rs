@@ -0,0 +1,458 @@
+use std::io::{self, BufRead, Write};
+use std::borrow::Borrow;
+
+use crate::prelude::*;
+use super::{MappingsLineProcessor, MappingsFormat, MappingsParseError, UNKNOWN_FIELD_DESCRIPTOR, process_stream};
+use super::tsrg::ClassData;
+use crate::utils::*;
+
+
+#[inline]
+fn field_descriptor(field: &FieldData) -> &str {
+    field.descriptor().map_or(UNKNOWN_FIELD_DESCRIPTOR, TypeDescriptor::descriptor)
+}
+
+/// The tab-separated Tiny v1 format used by Fabric, with `CLASS`, `FIELD` and `METHOD` lines.
+///
+/// The header line gives the names of each column's namespace,
+/// and we map from the first namespace to the second, ignoring any extra columns.
+/// When writing, we call these namespaces `official` and `named`.
+pub struct TinyV1MappingsFormat;
+impl MappingsFormat for TinyV1MappingsFormat {
+    type Processor = TinyV1LineProcessor;
+
+    fn write<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "v1\tofficial\tnamed")?;
+        for (original, renamed) in mappings.classes() {
+            writeln!(writer, "CLASS\t{}\t{}", original.internal_name(), renamed.internal_name())?;
+        }
+        for (original, renamed) in mappings.fields() {
+            writeln!(
+                writer, "FIELD\t{}\t{}\t{}\t{}",
+                original.declaring_type().internal_name(),
+                field_descriptor(original),
+                original.name,
+                renamed.borrow().name
+            )?;
+        }
+        for (original, renamed) in mappings.methods() {
+            writeln!(
+                writer, "METHOD\t{}\t{}\t{}\t{}",
+                original.declaring_type().internal_name(),
+                original.signature().descriptor(),
+                original.name,
+                renamed.borrow().name
+            )?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn processor() -> TinyV1LineProcessor {
+        TinyV1LineProcessor::default()
+    }
+}
+
+#[derive(Default)]
+pub struct TinyV1LineProcessor {
+    result: SimpleMappings,
+    seen_header: bool
+}
+/// Take the next tab-separated column, consuming the tab after it (if any)
+fn column<'a>(parser: &mut SimpleParser<'a>) -> Result<&'a str, SimpleParseError> {
+    let start = parser.current_index();
+    let value = parser.take_until(|c| c == '\t');
+    if value.is_empty() {
+        return Err(SimpleParseError { index: start, reason: Some("Missing column".into()) })
+    }
+    if !parser.is_finished() {
+        parser.expect('\t')?;
+    }
+    Ok(value)
+}
+fn internal_name_column<'a>(parser: &mut SimpleParser<'a>) -> Result<&'a str, SimpleParseError> {
+    let start = parser.current_index();
+    let name = column(parser)?;
+    if let Some(bad_index) = name.find('.') {
+        Err(SimpleParseError { index: start + bad_index, reason: Some(format!("Invalid internal name: {:?}", name)) })
+    } else {
+        Ok(name)
+    }
+}
+fn descriptor_column<T: SimpleParse>(parser: &mut SimpleParser) -> Result<T, SimpleParseError> {
+    let descriptor = parser.parse::<T>()?;
+    if !parser.is_finished() {
+        parser.expect('\t')?;
+    }
+    Ok(descriptor)
+}
+impl TinyV1LineProcessor {
+    fn parse_line(&mut self, parser: &mut SimpleParser) -> Result<(), SimpleParseError> {
+        parser.strip_comment();
+        if parser.remaining().trim().is_empty() { return Ok(()) }
+        if !self.seen_header {
+            parser.expect_str("v1\t")?;
+            // We need at least two namespaces to map between
+            column(parser)?;
+            column(parser)?;
+            self.seen_header = true;
+            return Ok(())
+        }
+        match column(parser)? {
+            "CLASS" => {
+                let original = ReferenceType::from_internal_name(internal_name_column(parser)?);
+                let renamed = ReferenceType::from_internal_name(internal_name_column(parser)?);
+                self.result.set_remapped_class(original, renamed);
+            },
+            "FIELD" => {
+                let declaring_type = ReferenceType::from_internal_name(internal_name_column(parser)?);
+                let descriptor = descriptor_column::<TypeDescriptor>(parser)?;
+                let original_name = column(parser)?;
+                let renamed_name = column(parser)?;
+                self.result.set_field_name(
+                    FieldData::new(original_name.into(), declaring_type).with_descriptor(descriptor),
+                    renamed_name.into()
+                );
+            },
+            "METHOD" => {
+                let declaring_type = ReferenceType::from_internal_name(internal_name_column(parser)?);
+                let signature = descriptor_column::<MethodSignature>(parser)?;
+                let original_name = column(parser)?;
+                let renamed_name = column(parser)?;
+                self.result.set_method_name(
+                    MethodData::new(original_name.into(), declaring_type, signature),
+                    renamed_name.into()
+                );
+            },
+            _ => return Err(parser.expected("CLASS, FIELD or METHOD"))
+        }
+        // Ignore any extra namespaces
+        Ok(())
+    }
+}
+impl MappingsLineProcessor for TinyV1LineProcessor {
+    fn process_line(&mut self, s: &str) -> Result<(), MappingsParseError> {
+        let mut parser = SimpleParser::for_line(s);
+        self.parse_line(&mut parser)
+            .map_err(|cause| MappingsParseError::InvalidLine {
+                index: cause.index,
+                line: s.into(),
+                line_number: None,
+                reason: cause.reason
+            })
+    }
+
+    #[inline]
+    fn finish(self) -> Result<FrozenMappings, MappingsParseError> {
+        Ok(self.result.frozen())
+    }
+}
+
+/// The tab-indented Tiny v2 format used by Fabric, which can have any number of namespaces.
+///
+/// Members are nested under their class, and their descriptors are always
+/// given using the class names of the first namespace.
+/// By default we map from the first namespace to the second,
+/// but `parse_stream_namespaces` can choose any two namespaces by name.
+/// Comments on classes and members are kept, as are parameter names,
+/// while local variables and the comments on parameters are parsed but discarded.
+pub struct TinyV2MappingsFormat;
+impl TinyV2MappingsFormat {
+    /// Parse the stream, mapping from the `from` namespace to the `to` namespace.
+    pub fn parse_stream_namespaces<R: BufRead>(read: R, from: &str, to: &str) -> Result<FrozenMappings, MappingsParseError> {
+        let mut processer = TinyV2LineProcessor::with_namespaces(from, to);
+        process_stream(&mut processer, read)?;
+        processer.finish()
+    }
+}
+impl MappingsFormat for TinyV2MappingsFormat {
+    type Processor = TinyV2LineProcessor;
+
+    fn write<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "tiny\t2\t0\tofficial\tnamed")?;
+        let data = ClassData::from_mappings(mappings);
+        for (declaring_type, data) in data.iter() {
+            let renamed_type = data.renamed_type.unwrap_or(declaring_type);
+            writeln!(writer, "c\t{}\t{}", declaring_type.internal_name(), renamed_type.internal_name())?;
+            if let Some(comment) = mappings.class_comment(declaring_type) {
+                writeln!(writer, "\tc\t{}", escape(comment))?;
+            }
+            for (original, renamed) in &data.fields {
+                writeln!(writer, "\tf\t{}\t{}\t{}", field_descriptor(original), original.name, renamed.borrow().name)?;
+                if let Some(comment) = mappings.field_comment(original) {
+                    writeln!(writer, "\t\tc\t{}", escape(comment))?;
+                }
+            }
+            for (original, renamed) in &data.methods {
+                writeln!(
+                    writer, "\tm\t{}\t{}\t{}",
+                    original.signature().descriptor(),
+                    original.name, renamed.borrow().name
+                )?;
+                if let Some(comment) = mappings.method_comment(original) {
+                    writeln!(writer, "\t\tc\t{}", escape(comment))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn processor() -> TinyV2LineProcessor {
+        TinyV2LineProcessor::default()
+    }
+}
+
+#[derive(Default)]
+pub struct TinyV2LineProcessor {
+    /// The names of the namespaces to map between, or `None` to use the first two
+    namespaces: Option<(String, String)>,
+    /// The indexes of the namespaces we map between, once we've seen the header
+    columns: Option<(usize, usize)>,
+    namespace_count: usize,
+    escaped_names: bool,
+    result: SimpleMappings,
+    /// The names of classes in the source namespace, keyed by their name in the first namespace
+    source_classes: FnvIndexMap<ReferenceType, ReferenceType>,
+    /// Members using the declaring types and descriptors of the first namespace,
+    /// which we can't remap until we've seen every class.
+    fields: Vec<(FieldData, String, Option<String>)>,
+    methods: Vec<(MethodData, String, Option<String>)>,
+    /// The index of each parameter's method, along with its local variable index and its names
+    parameters: Vec<(usize, u16, String, String)>,
+    /// The current class's names in the first namespace and the source namespace
+    current_class: Option<(ReferenceType, ReferenceType)>,
+    current_member: Option<TinyMember>
+}
+/// The index of a member we've parsed, which any nested comments belong to
+#[derive(Copy, Clone)]
+enum TinyMember {
+    Field(usize),
+    Method(usize)
+}
+impl TinyV2LineProcessor {
+    /// Create a processor mapping from the `from` namespace to the `to` namespace.
+    pub fn with_namespaces(from: &str, to: &str) -> TinyV2LineProcessor {
+        TinyV2LineProcessor {
+            namespaces: Some((from.into(), to.into())),
+            ..Default::default()
+        }
+    }
+    fn parse_header(&mut self, parser: &mut SimpleParser) -> Result<(), SimpleParseError> {
+        parser.expect_str("tiny\t2\t")?;
+        // Any minor version is fine
+        column(parser)?;
+        let mut names = Vec::new();
+        while !parser.is_finished() {
+            names.push(column(parser)?);
+        }
+        // We need at least two namespaces to map between
+        if names.len() < 2 {
+            return Err(SimpleParseError { index: parser.current_index(), reason: Some("Missing namespaces".into()) })
+        }
+        let columns = match self.namespaces {
+            Some((ref from, ref to)) => {
+                let find = |namespace: &str| names.iter().position(|&name| name == namespace)
+                    .ok_or_else(|| SimpleParseError {
+                        index: parser.current_index(),
+                        reason: Some(format!("Unknown namespace {:?}", namespace))
+                    });
+                (find(from)?, find(to)?)
+            },
+            None => (0, 1)
+        };
+        self.columns = Some(columns);
+        self.namespace_count = names.len();
+        Ok(())
+    }
+    /// Parse the name in each namespace, giving the names in the first, source, and target namespaces.
+    ///
+    /// Missing names default to the name in the first namespace.
+    fn parse_names(&self, parser: &mut SimpleParser) -> Result<(String, String, String), SimpleParseError> {
+        let (from, to) = self.columns.unwrap();
+        let mut names = Vec::with_capacity(self.namespace_count);
+        names.push(column(parser)?);
+        while names.len() < self.namespace_count {
+            let name = parser.take_until(|c| c == '\t');
+            if !parser.is_finished() {
+                parser.expect('\t')?;
+            }
+            names.push(if name.is_empty() { names[0] } else { name });
+        }
+        let name = |index: usize| if self.escaped_names {
+            unescape(names[index])
+        } else {
+            names[index].to_owned()
+        };
+        Ok((name(0), name(from), name(to)))
+    }
+    fn parse_line(&mut self, parser: &mut SimpleParser) -> Result<(), SimpleParseError> {
+        // Comment entries are free text, so they can contain a `#`
+        let indent = parser.remaining().len() - parser.remaining().trim_start_matches('\t').len();
+        if indent == 0 || !parser.remaining()[indent..].starts_with("c\t") {
+            parser.strip_comment();
+        }
+        if parser.remaining().trim().is_empty() { return Ok(()) }
+        if self.columns.is_none() {
+            return self.parse_header(parser)
+        }
+        let depth = parser.remaining().len() - parser.remaining().trim_start_matches('\t').len();
+        parser.skip(depth);
+        match (depth, column(parser)?) {
+            (0, "c") => {
+                let start = parser.current_index();
+                let (first, original, renamed) = self.parse_names(parser)?;
+                for name in &[&first, &original, &renamed] {
+                    if name.contains('.') {
+                        return Err(SimpleParseError { index: start, reason: Some(format!("Invalid internal name: {:?}", name)) })
+                    }
+                }
+                let first = ReferenceType::from_internal_name(&first);
+                let original = ReferenceType::from_internal_name(&original);
+                let renamed = ReferenceType::from_internal_name(&renamed);
+                if first != original {
+                    self.source_classes.insert(first.clone(), original.clone());
+                }
+                self.result.set_remapped_class(original.clone(), renamed);
+                self.current_class = Some((first, original));
+                self.current_member = None;
+            },
+            (1, property) if self.current_class.is_none() => {
+                // Properties are given after the header, before the first class
+                if property == "escaped-names" {
+                    self.escaped_names = true;
+                }
+            },
+            (1, kind @ "f") | (1, kind @ "m") => {
+                let declaring_type = self.current_class.clone().unwrap().0;
+                if kind == "f" {
+                    let descriptor = descriptor_column::<TypeDescriptor>(parser)?;
+                    let (_, original_name, renamed_name) = self.parse_names(parser)?;
+                    self.current_member = Some(TinyMember::Field(self.fields.len()));
+                    let original = FieldData::new(original_name, declaring_type).with_descriptor(descriptor);
+                    self.fields.push((original, renamed_name, None));
+                } else {
+                    let signature = descriptor_column::<MethodSignature>(parser)?;
+                    let (_, original_name, renamed_name) = self.parse_names(parser)?;
+                    self.current_member = Some(TinyMember::Method(self.methods.len()));
+                    self.methods.push((MethodData::new(original_name, declaring_type, signature), renamed_name, None));
+                }
+            },
+            (1, "c") => {
+                let original = self.current_class.clone().unwrap().1;
+                self.result.set_class_comment(original, unescape(parser.remaining()));
+            },
+            (2, "c") => {
+                let comment = Some(unescape(parser.remaining()));
+                match self.current_member {
+                    Some(TinyMember::Field(index)) => self.fields[index].2 = comment,
+                    Some(TinyMember::Method(index)) => self.methods[index].2 = comment,
+                    None => return Err(parser.error_with("Missing current member".into()))
+                }
+            },
+            (2, "p") => {
+                let method = match self.current_member {
+                    Some(TinyMember::Method(index)) => index,
+                    _ => return Err(parser.error_with("Missing current method".into()))
+                };
+                let start = parser.current_index();
+                let index = column(parser)?.parse::<u16>().map_err(|_| SimpleParseError {
+                    index: start,
+                    reason: Some("Invalid parameter index".into())
+                })?;
+                // Parameters often lack a name in the first namespace, so any name may be empty
+                let (from, to) = self.columns.unwrap();
+                let names: Vec<&str> = parser.remaining().split('\t').collect();
+                let name = |index: usize| {
+                    let name = names.get(index).cloned().unwrap_or("");
+                    if self.escaped_names { unescape(name) } else { name.to_owned() }
+                };
+                let (original_name, renamed_name) = (name(from), name(to));
+                if !renamed_name.is_empty() {
+                    self.parameters.push((method, index, original_name, renamed_name));
+                }
+            },
+            // Local variables and comments on parameters are ignored
+            (2, "v") | (3, "c") => {},
+            _ => return Err(parser.error())
+        }
+        Ok(())
+    }
+}
+/// Escape a comment (or name) so it fits on a single line.
+fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\0' => result.push_str("\\0"),
+            _ => result.push(c)
+        }
+    }
+    result
+}
+/// Undo the escaping used for comments, and for names when the `escaped-names` property is present.
+fn unescape(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue
+        }
+        match chars.next() {
+            Some('\\') => result.push('\\'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('0') => result.push('\0'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            },
+            None => result.push('\\')
+        }
+    }
+    result
+}
+impl MappingsLineProcessor for TinyV2LineProcessor {
+    fn process_line(&mut self, s: &str) -> Result<(), MappingsParseError> {
+        let mut parser = SimpleParser::for_line(s);
+        self.parse_line(&mut parser)
+            .map_err(|cause| MappingsParseError::InvalidLine {
+                index: cause.index,
+                line: s.into(),
+                line_number: None,
+                reason: cause.reason
+            })
+    }
+
+    fn finish(self) -> Result<FrozenMappings, MappingsParseError> {
+        let TinyV2LineProcessor { mut result, source_classes, fields, methods, parameters, .. } = self;
+        // Now that we know all the classes, switch members over to the source namespace
+        for (original, renamed, comment) in fields {
+            let original = original.transform_class(&source_classes);
+            if let Some(comment) = comment {
+                result.set_field_comment(original.clone(), comment);
+            }
+            result.set_field_name(original, renamed);
+        }
+        let mut original_methods = Vec::with_capacity(methods.len());
+        for (original, renamed, comment) in methods {
+            let original = original.transform_class(&source_classes);
+            if let Some(comment) = comment {
+                result.set_method_comment(original.clone(), comment);
+            }
+            result.set_method_name(original.clone(), renamed);
+            original_methods.push(original);
+        }
+        for (method, index, original_name, renamed_name) in parameters {
+            let original = ParameterData::new(original_name, original_methods[method].clone(), index);
+            result.set_parameter_name(original, renamed_name);
+        }
+        Ok(result.frozen())
+    }
+}
@@ -1,34 +1,25 @@
 use std::io::{self, Write};
+use std::borrow::Borrow;
 
 use itertools::Itertools;
 
 use crate::utils::{SimpleParser, SimpleParseError, FnvIndexMap};
 use crate::prelude::*;
-use super::{MappingsFormat, MappingsLineProcessor};
+use crate::descriptor::access;
+use super::{MappingsFormat, MappingsLineProcessor, MergeableLineProcessor};
 
 
+/// The tab-indented SRG format used by Forge, which nests members under their class.
+///
+/// Files in this format conventionally map from obfuscated names to searge names.
 pub struct TabSrgMappingsFormat;
 impl MappingsFormat for TabSrgMappingsFormat {
     type Processor = TabSrgLineProcessor;
+    const SUPPORTS_COLLAPSE_WHITESPACE: bool = true;
 
-    fn write<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, mut writer: W) -> io::Result<()> {
-        let data = ClassData::from_mappings(mappings);
-        for (declaring_type, data) in data.iter() {
-            let renamed_type = data.renamed_type.as_ref()
-                .unwrap_or(declaring_type);
-            writeln!(writer, "{} {}", declaring_type.internal_name(), renamed_type.internal_name())?;
-            for (original, renamed) in &data.fields {
-                writeln!(writer, "\t{} {}", original.name, renamed.name)?;
-            }
-            for (original, renamed) in &data.methods {
-                writeln!(
-                    writer, "\t{} {} {}",
-                    original.name, original.signature().descriptor(),
-                    renamed.name
-                )?;
-            }
-        }
-        Ok(())
+    #[inline]
+    fn write<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, writer: W) -> io::Result<()> {
+        write_tsrg(mappings, writer, false)
     }
 
     fn processor() -> TabSrgLineProcessor {
@@ -43,7 +34,8 @@ pub struct TabSrgLineProcessor {
 }
 impl TabSrgLineProcessor {
     fn parse_line(&mut self, parser: &mut SimpleParser) -> Result<(), SimpleParseError> {
-        if parser.is_finished() || parser.remaining().trim_left().starts_with('#') { return Ok(()) }
+        parser.strip_comment();
+        if parser.is_finished() { return Ok(()) }
         if parser.peek()? != '\t' {
             // We have a new class entry
             let original = ReferenceType::from_internal_name(
@@ -51,6 +43,8 @@ impl TabSrgLineProcessor {
             parser.expect(' ')?;
             let renamed = ReferenceType::from_internal_name(
                 parser.parse_internal_name()?);
+            parser.skip_whitespace();
+            parser.ensure_finished()?;
             self.result.set_remapped_class(original.clone(), renamed);
             self.current_class = Some(original);
             return Ok(())
@@ -62,7 +56,8 @@ impl TabSrgLineProcessor {
                 reason: Some("Missing current class".into()),
             })?;
         // Otherwise it's a member entry, implied to be part of the current class
-        match parser.remaining().split_whitespace().count() {
+        let column_count = parser.remaining().split_whitespace().count();
+        match column_count {
             3 => {
                 let original_name = parser.take_until(|c| c == ' ');
                 parser.expect(' ')?;
@@ -86,7 +81,7 @@ impl TabSrgLineProcessor {
                 );
                 self.result.set_field_name(original_data, renamed_name.into());
             },
-            _ => return Err(parser.error())
+            _ => return Err(parser.column_count_error("2 or 3", column_count))
         }
         parser.skip_whitespace();
         parser.ensure_finished()?;
@@ -95,11 +90,207 @@ impl TabSrgLineProcessor {
 }
 impl MappingsLineProcessor for TabSrgLineProcessor {
     fn process_line(&mut self, s: &str) -> Result<(), MappingsParseError> {
-        let mut parser = SimpleParser::new(s);
+        let mut parser = SimpleParser::for_line(s);
+        self.parse_line(&mut parser)
+            .map_err(|cause| MappingsParseError::InvalidLine {
+                index: cause.index,
+                line: s.into(),
+                line_number: None,
+                reason: cause.reason
+            })
+    }
+
+    #[inline]
+    fn finish(self) -> Result<FrozenMappings, MappingsParseError> {
+        Ok(self.result.frozen())
+    }
+}
+impl MergeableLineProcessor for TabSrgLineProcessor {
+    #[inline]
+    fn merge(&mut self, other: Self) -> Result<(), MappingsParseError> {
+        self.result.extend(other.result);
+        self.current_class = other.current_class;
+        Ok(())
+    }
+}
+
+/// The second version of the tab-indented SRG format used by newer versions of Forge,
+/// which begins with a header like `tsrg2 obf srg` naming any number of namespaces.
+///
+/// Since mappings only have two sides, we map from the first namespace to the last one.
+/// Fields may optionally give their descriptor, while methods always do.
+/// Parameter names are kept, and `static` markers give the access flags of their method.
+/// Since that's the only flag the format gives, marked methods only have `ACC_STATIC` set.
+pub struct TabSrg2MappingsFormat;
+impl MappingsFormat for TabSrg2MappingsFormat {
+    type Processor = TabSrg2LineProcessor;
+    const SUPPORTS_COLLAPSE_WHITESPACE: bool = true;
+
+    fn write<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "tsrg2 left right")?;
+        write_tsrg(mappings, writer, true)
+    }
+
+    fn processor() -> TabSrg2LineProcessor {
+        TabSrg2LineProcessor::default()
+    }
+}
+
+/// Write the classes with their members nested under them.
+///
+/// The second version of the format also gives the types of fields if we know them,
+/// along with marking static methods.
+fn write_tsrg<'a, T: IterableMappings<'a>, W: Write>(mappings: &'a T, mut writer: W, version2: bool) -> io::Result<()> {
+    let data = ClassData::from_mappings(mappings);
+    for (declaring_type, data) in data.iter() {
+        let renamed_type = data.renamed_type.unwrap_or(declaring_type);
+        writeln!(writer, "{} {}", declaring_type.internal_name(), renamed_type.internal_name())?;
+        for (original, renamed) in &data.fields {
+            match original.descriptor() {
+                Some(descriptor) if version2 => {
+                    writeln!(writer, "\t{} {} {}", original.name, descriptor.descriptor(), renamed.borrow().name)?;
+                },
+                _ => writeln!(writer, "\t{} {}", original.name, renamed.borrow().name)?
+            }
+        }
+        for (original, renamed) in &data.methods {
+            writeln!(
+                writer, "\t{} {} {}",
+                original.name, original.signature().descriptor(),
+                renamed.borrow().name
+            )?;
+            if version2 && original.is_static() {
+                writeln!(writer, "\t\tstatic")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct TabSrg2LineProcessor {
+    result: SimpleMappings,
+    /// The number of namespaces, once we've seen the header
+    namespace_count: Option<usize>,
+    current_class: Option<ReferenceType>,
+    /// The method any parameter lines belong to
+    current_method: Option<MethodData>
+}
+impl TabSrg2LineProcessor {
+    fn parse_line(&mut self, parser: &mut SimpleParser) -> Result<(), SimpleParseError> {
+        parser.strip_comment();
+        if parser.is_finished() { return Ok(()) }
+        let namespace_count = match self.namespace_count {
+            Some(count) => count,
+            None => {
+                parser.expect_str("tsrg2 ")?;
+                let count = parser.remaining().split(' ').count();
+                // We need at least two namespaces to map between
+                if count < 2 || parser.remaining().split(' ').any(str::is_empty) {
+                    return Err(parser.expected("at least two namespaces"))
+                }
+                self.namespace_count = Some(count);
+                return Ok(())
+            }
+        };
+        let start = parser.current_index();
+        let depth = parser.remaining().len() - parser.remaining().trim_start_matches('\t').len();
+        parser.skip(depth);
+        let mut names: Vec<&str> = parser.remaining().split(' ').collect();
+        if names.iter().any(|name| name.is_empty()) {
+            return Err(parser.error_with(format!("Unexpected empty name: {:?}", parser.remaining())))
+        }
+        match depth {
+            0 => {
+                if names.len() != namespace_count {
+                    return Err(parser.column_count_error(&namespace_count.to_string(), names.len()))
+                }
+                for name in &names {
+                    if name.contains('.') {
+                        return Err(SimpleParseError { index: start, reason: Some(format!("Invalid internal name: {:?}", name)) })
+                    }
+                }
+                let original = ReferenceType::from_internal_name(names[0]);
+                let renamed = ReferenceType::from_internal_name(names[namespace_count - 1]);
+                self.result.set_remapped_class(original.clone(), renamed);
+                self.current_class = Some(original);
+                self.current_method = None;
+            },
+            1 => {
+                let current_class = self.current_class.clone()
+                    .ok_or_else(|| SimpleParseError {
+                        index: parser.current_index(),
+                        reason: Some("Missing current class".into()),
+                    })?;
+                // Some writers put the static marker on the method's line
+                let is_static = names.len() == namespace_count + 2 && names.last() == Some(&"static");
+                if is_static {
+                    names.pop();
+                }
+                let invalid_descriptor = |descriptor: &str| SimpleParseError {
+                    index: start,
+                    reason: Some(format!("Invalid descriptor: {:?}", descriptor))
+                };
+                if names.len() == namespace_count + 1 && names[1].starts_with('(') {
+                    let signature = MethodSignature::parse_descriptor(names[1])
+                        .ok_or_else(|| invalid_descriptor(names[1]))?;
+                    let mut original = MethodData::new(names[0].into(), current_class, signature);
+                    if is_static {
+                        original.set_access(Some(access::ACC_STATIC));
+                    }
+                    self.result.set_method_name(original.clone(), names[namespace_count].into());
+                    self.current_method = Some(original);
+                } else if names.len() == namespace_count || names.len() == namespace_count + 1 {
+                    let mut original = FieldData::new(names[0].into(), current_class);
+                    if names.len() > namespace_count {
+                        let descriptor = TypeDescriptor::parse_descriptor(names[1])
+                            .ok_or_else(|| invalid_descriptor(names[1]))?;
+                        original.set_descriptor(Some(descriptor));
+                    }
+                    self.result.set_field_name(original, names[names.len() - 1].into());
+                    self.current_method = None;
+                } else {
+                    let expected = format!("{} or {}", namespace_count, namespace_count + 1);
+                    return Err(parser.column_count_error(&expected, names.len()))
+                }
+            },
+            2 if names == ["static"] => {
+                if let Some(ref mut method) = self.current_method {
+                    method.set_access(Some(access::ACC_STATIC));
+                    self.result.set_method_access(method, Some(access::ACC_STATIC));
+                }
+            },
+            2 => {
+                let method = self.current_method.clone()
+                    .ok_or_else(|| SimpleParseError {
+                        index: parser.current_index(),
+                        reason: Some("Missing current method".into()),
+                    })?;
+                if names.len() != namespace_count + 1 {
+                    return Err(parser.column_count_error(&(namespace_count + 1).to_string(), names.len()))
+                }
+                let index = names[0].parse::<u16>().map_err(|_| SimpleParseError {
+                    index: start,
+                    reason: Some(format!("Invalid parameter index: {:?}", names[0]))
+                })?;
+                self.result.set_parameter_name(
+                    ParameterData::new(names[1].into(), method, index),
+                    names[namespace_count].into()
+                );
+            },
+            _ => return Err(parser.error_with(format!("Unexpected indentation of {} tabs", depth)))
+        }
+        Ok(())
+    }
+}
+impl MappingsLineProcessor for TabSrg2LineProcessor {
+    fn process_line(&mut self, s: &str) -> Result<(), MappingsParseError> {
+        let mut parser = SimpleParser::for_line(s);
         self.parse_line(&mut parser)
             .map_err(|cause| MappingsParseError::InvalidLine {
                 index: cause.index,
                 line: s.into(),
+                line_number: None,
                 reason: cause.reason
             })
     }
@@ -115,32 +306,43 @@ impl MappingsLineProcessor for TabSrgLineProcessor {
  * Personally, I think it needs to become part of
  * the new internal representation of `FrozenMappings`
  */
-#[derive(Default)]
-struct ClassData {
-    renamed_type: Option<ReferenceType>,
-    fields: Vec<(FieldData, FieldData)>,
-    methods: Vec<(MethodData, MethodData)>
+pub(super) struct ClassData<'a, F, M> {
+    pub(super) renamed_type: Option<&'a ReferenceType>,
+    pub(super) fields: Vec<(&'a FieldData, F)>,
+    pub(super) methods: Vec<(&'a MethodData, M)>
 }
-impl ClassData {
-    fn from_mappings<'a, T: IterableMappings<'a>>(mappings: &'a T) -> FnvIndexMap<ReferenceType, ClassData> {
-        let mut classes: FnvIndexMap<ReferenceType, ClassData> = FnvIndexMap::with_capacity_and_hasher(
+impl<'a, F, M> Default for ClassData<'a, F, M> {
+    #[inline]
+    fn default() -> Self {
+        ClassData { renamed_type: None, fields: Vec::new(), methods: Vec::new() }
+    }
+}
+/// The class data of a mapping, borrowing its members instead of cloning them
+pub(super) type ClassDataMap<'a, T> = FnvIndexMap<
+    &'a ReferenceType,
+    ClassData<'a, <T as IterableMappings<'a>>::FieldValue, <T as IterableMappings<'a>>::MethodValue>
+>;
+impl<'a, F: Borrow<FieldData>, M: Borrow<MethodData>> ClassData<'a, F, M> {
+    pub(super) fn from_mappings<T>(mappings: &'a T) -> ClassDataMap<'a, T>
+        where T: IterableMappings<'a, FieldValue=F, MethodValue=M> {
+        let mut classes: ClassDataMap<'a, T> = FnvIndexMap::with_capacity_and_hasher(
             mappings.original_classes().size_hint().1.unwrap_or(0), Default::default());
         for (declaring_type, renamed_type) in mappings.classes() {
-            let data = classes.entry(declaring_type.clone())
+            let data = classes.entry(declaring_type)
                 .or_insert_with(Default::default);
-            data.renamed_type = Some(renamed_type.clone());
+            data.renamed_type = Some(renamed_type);
         }
         for (declaring_type, group) in &mappings.fields()
             .group_by(|(original, _)| original.declaring_type()) {
-            let data = classes.entry(declaring_type.clone())
+            let data = classes.entry(declaring_type)
                 .or_insert_with(Default::default);
-            data.fields.extend(group.map(|(original, renamed)| (original.clone(), renamed.into())));
+            data.fields.extend(group);
         }
         for (declaring_type, group) in &mappings.methods()
             .group_by(|(original, _)| original.declaring_type()) {
-            let data = classes.entry(declaring_type.clone())
+            let data = classes.entry(declaring_type)
                 .or_insert_with(Default::default);
-            data.methods.extend(group.map(|(original, renamed)| (original.clone(), renamed.into())));
+            data.methods.extend(group);
         }
         classes
     }
@@ -190,6 +392,31 @@ b net/minecraft/crash/CrashReport
             panic!("serialized != TEST_TEXT:\n{}", changelog);
         }
     }
+    #[test]
+    fn roundtrip_edge_cases() {
+        let mut builder = SimpleMappings::default();
+        let a = ReferenceType::from_internal_name("a");
+        builder.set_remapped_class(a.clone(), ReferenceType::from_internal_name("Edge"));
+        // A field and a method sharing the same obfuscated name
+        builder.set_field_name(FieldData::new("a".into(), a.clone()), "field".into());
+        builder.set_method_name(
+            MethodData::new("a".into(), a.clone(), MethodSignature::from_descriptor("()V")),
+            "voidMethod".into()
+        );
+        builder.set_method_name(
+            MethodData::new("b".into(), a.clone(), MethodSignature::from_descriptor("()[[La;")),
+            "arrayMethod".into()
+        );
+        let expected = builder.frozen();
+        let serialized = TabSrgMappingsFormat::write_string(&expected);
+        assert_eq!(serialized, "a Edge\n\ta field\n\ta ()V voidMethod\n\tb ()[[La; arrayMethod\n");
+        TabSrgMappingsFormat::parse_text(&serialized).unwrap().assert_equal(&expected);
+    }
+    #[test]
+    fn trailing_class_text() {
+        assert!(TabSrgMappingsFormat::parse_text("a Edge extra\n").is_err());
+        assert!(TabSrgMappingsFormat::parse_text("a Edge \n").is_ok());
+    }
 
     fn expected_mappings() -> FrozenMappings {
         let mut builder = SimpleMappings::default();
@@ -4,3 +4,8 @@ pub mod mappings;
 pub mod prelude;
 pub mod format;
 pub mod utils;
+pub mod signature;
+#[cfg(feature = "config")]
+pub mod config;
+#[cfg(feature = "serde")]
+mod serde_impls;
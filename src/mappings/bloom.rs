@@ -0,0 +1,94 @@
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+
+use fnv::FnvHasher;
+use lazycell::AtomicLazyCell;
+
+use crate::prelude::*;
+use crate::utils::FnvIndexMap;
+
+const BITS_PER_CLASS: usize = 10;
+const NUM_HASHES: u32 = 4;
+
+/// A bloom filter over the original classes of a mapping,
+/// which lets us reject most unmapped classes without probing the map.
+///
+/// This is a blocked bloom filter, where all the bits for a class are in the same word,
+/// so each lookup only needs a single memory access.
+pub(super) struct ClassFilter {
+    words: Vec<u64>,
+    mask: usize
+}
+impl ClassFilter {
+    fn new(classes: &FnvIndexMap<ReferenceType, ReferenceType>) -> ClassFilter {
+        let num_words = (classes.len() * BITS_PER_CLASS / 64).next_power_of_two();
+        let mut filter = ClassFilter { words: vec![0; num_words], mask: num_words - 1 };
+        for class in classes.keys() {
+            let (index, bits) = filter.locate(class);
+            filter.words[index] |= bits;
+        }
+        filter
+    }
+    /// Check if the class may be in the mapping, which is always true if it's actually present
+    #[inline]
+    pub(super) fn may_contain(&self, class: &ReferenceType) -> bool {
+        let (index, bits) = self.locate(class);
+        self.words[index] & bits == bits
+    }
+    /// Find the word for the class, and the bits it sets in that word
+    #[inline]
+    fn locate(&self, class: &ReferenceType) -> (usize, u64) {
+        let mut hasher = FnvHasher::default();
+        class.hash(&mut hasher);
+        let hash = hasher.finish();
+        let mut bits = 0;
+        for i in 0..NUM_HASHES {
+            bits |= 1 << ((hash >> (32 + i * 6)) & 63);
+        }
+        (hash as usize & self.mask, bits)
+    }
+}
+/// A class filter which is only built once it's first needed
+#[derive(Default)]
+pub(super) struct LazyClassFilter(AtomicLazyCell<ClassFilter>);
+impl LazyClassFilter {
+    pub(super) fn get(&self, classes: &FnvIndexMap<ReferenceType, ReferenceType>) -> &ClassFilter {
+        match self.0.borrow() {
+            Some(filter) => filter,
+            None => {
+                // We don't care if we're the ones who fill it or if someone else already has
+                drop(self.0.fill(ClassFilter::new(classes)));
+                self.0.borrow().unwrap()
+            }
+        }
+    }
+}
+impl Debug for LazyClassFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LazyClassFilter")
+            .field("built", &self.0.filled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn no_false_negatives() {
+        let classes: FnvIndexMap<ReferenceType, ReferenceType> = (0..1000)
+            .map(|i| (
+                ReferenceType::from_internal_name(&format!("obf{}", i)),
+                ReferenceType::from_internal_name(&format!("net/techcable/Class{}", i))
+            ))
+            .collect();
+        let filter = ClassFilter::new(&classes);
+        assert!(classes.keys().all(|class| filter.may_contain(class)));
+        let false_positives = (0..1000)
+            .filter(|i| filter.may_contain(&ReferenceType::from_internal_name(&format!("unmapped{}", i))))
+            .count();
+        // With ten bits per class, we expect a few percent
+        assert!(false_positives < 100, "{} false positives", false_positives);
+        assert!(!ClassFilter::new(&FnvIndexMap::default()).may_contain(&ReferenceType::from_internal_name("a")));
+    }
+}
@@ -0,0 +1,229 @@
+use std::borrow::Cow;
+
+use crate::prelude::*;
+use crate::utils::{FnvIndexMap, SimpleParser, SimpleParseError};
+
+/// Mappings whose member names borrow from the text they were parsed from,
+/// avoiding an allocation for every entry.
+///
+/// This is meant for one-shot conversions of huge files, where the text outlives the mappings.
+/// Only the renamed classes are allocated up front, since most entries are members.
+/// Lookups allocate their results, so convert to `FrozenMappings` if you need to do many of them.
+#[derive(Clone, Debug, Default)]
+pub struct BorrowedMappings<'a> {
+    classes: FnvIndexMap<&'a str, ReferenceType>,
+    /// The renamed names of fields, keyed by their original declaring type and name
+    fields: FnvIndexMap<(&'a str, &'a str), &'a str>,
+    /// The renamed names of methods, keyed by their original declaring type, name and descriptor
+    methods: FnvIndexMap<(&'a str, &'a str, &'a str), &'a str>
+}
+impl<'a> BorrowedMappings<'a> {
+    /// Parse text in the classic SRG format, borrowing the names from the text.
+    ///
+    /// Package directives aren't supported, since they'd require transforming every class.
+    pub fn parse_srg(text: &'a str) -> Result<Self, MappingsParseError> {
+        let mut result = BorrowedMappings::default();
+        for (index, line) in text.lines().enumerate() {
+            result.parse_srg_line(&mut SimpleParser::for_line(line))
+                .map_err(|cause| invalid_line(index + 1, line, cause))?;
+        }
+        Ok(result)
+    }
+    /// Parse text in the compact SRG format, borrowing the names from the text.
+    pub fn parse_compact_srg(text: &'a str) -> Result<Self, MappingsParseError> {
+        let mut result = BorrowedMappings::default();
+        for (index, line) in text.lines().enumerate() {
+            result.parse_compact_srg_line(&mut SimpleParser::for_line(line))
+                .map_err(|cause| invalid_line(index + 1, line, cause))?;
+        }
+        Ok(result)
+    }
+    fn parse_srg_line(&mut self, parser: &mut SimpleParser<'a>) -> Result<(), SimpleParseError> {
+        parser.skip_whitespace();
+        parser.strip_comment();
+        if parser.is_finished() { return Ok(()) }
+        match parser.peek_chars(2).unwrap_or_default() {
+            "CL" => {
+                parser.expect_str("CL: ")?;
+                let original = parser.parse_internal_name()?;
+                parser.expect(' ')?;
+                let renamed = parser.parse_internal_name()?;
+                self.classes.insert(original, ReferenceType::from_internal_name(renamed));
+            },
+            "FD" => {
+                parser.expect_str("FD: ")?;
+                let (declaring_type, name) = parse_joined_name(parser)?;
+                parser.expect(' ')?;
+                let (_, renamed) = parse_joined_name(parser)?;
+                self.fields.insert((declaring_type, name), renamed);
+            },
+            "MD" => {
+                parser.expect_str("MD: ")?;
+                let (declaring_type, name) = parse_joined_name(parser)?;
+                parser.expect(' ')?;
+                let descriptor = parse_method_descriptor(parser)?;
+                parser.expect(' ')?;
+                let (_, renamed) = parse_joined_name(parser)?;
+                parser.expect(' ')?;
+                parse_method_descriptor(parser)?;
+                self.methods.insert((declaring_type, name, descriptor), renamed);
+            },
+            _ => return Err(parser.expected("CL, FD or MD"))
+        }
+        parser.skip_whitespace();
+        parser.ensure_finished()
+    }
+    fn parse_compact_srg_line(&mut self, parser: &mut SimpleParser<'a>) -> Result<(), SimpleParseError> {
+        parser.skip_whitespace();
+        parser.strip_comment();
+        if parser.is_finished() { return Ok(()) }
+        let token_count = parser.remaining().split_whitespace().count();
+        let declaring_type = parser.parse_internal_name()?;
+        parser.expect(' ')?;
+        match token_count {
+            2 => {
+                let renamed = parser.parse_internal_name()?;
+                self.classes.insert(declaring_type, ReferenceType::from_internal_name(renamed));
+            },
+            3 => {
+                let name = parser.take_until(|c| c == ' ');
+                parser.expect(' ')?;
+                let renamed = parser.take_until(|c| c == ' ');
+                self.fields.insert((declaring_type, name), renamed);
+            },
+            // We don't keep the types of fields, but still check they're valid
+            4 if !parser.remaining().split_whitespace().nth(1).unwrap().starts_with('(') => {
+                let name = parser.take_until(|c| c == ' ');
+                parser.expect(' ')?;
+                parser.parse::<TypeDescriptor>()?;
+                parser.expect(' ')?;
+                let renamed = parser.take_until(|c| c == ' ');
+                self.fields.insert((declaring_type, name), renamed);
+            },
+            4 => {
+                let name = parser.take_until(|c| c == ' ');
+                parser.expect(' ')?;
+                let descriptor = parse_method_descriptor(parser)?;
+                parser.expect(' ')?;
+                let renamed = parser.take_until(|c| c == ' ');
+                self.methods.insert((declaring_type, name, descriptor), renamed);
+            },
+            _ => return Err(parser.column_count_error("2, 3 or 4", token_count))
+        }
+        parser.skip_whitespace();
+        parser.ensure_finished()
+    }
+}
+fn invalid_line(line_number: usize, line: &str, cause: SimpleParseError) -> MappingsParseError {
+    MappingsParseError::InvalidLine {
+        index: cause.index,
+        line: line.into(),
+        line_number: Some(line_number),
+        reason: cause.reason
+    }
+}
+/// Parse a name like `java/lang/String/concat` into its declaring type and member name
+fn parse_joined_name<'a>(parser: &mut SimpleParser<'a>) -> Result<(&'a str, &'a str), SimpleParseError> {
+    let start = parser.current_index();
+    let joined = parser.parse_internal_name()?;
+    match joined.rfind('/') {
+        Some(index) => Ok((&joined[..index], &joined[index + 1..])),
+        None => Err(SimpleParseError { index: start, reason: Some(format!("Invalid joined name: {:?}", joined)) })
+    }
+}
+/// Parse a method descriptor, checking that it's valid without allocating a signature
+fn parse_method_descriptor<'a>(parser: &mut SimpleParser<'a>) -> Result<&'a str, SimpleParseError> {
+    let start = parser.current_index();
+    let descriptor = parser.take_until(|c| c == ' ');
+    if is_method_descriptor(descriptor) {
+        Ok(descriptor)
+    } else {
+        Err(SimpleParseError { index: start, reason: Some(format!("Invalid descriptor: {:?}", descriptor)) })
+    }
+}
+fn is_method_descriptor(descriptor: &str) -> bool {
+    let mut remaining = match descriptor.strip_prefix('(') {
+        Some(remaining) => remaining,
+        None => return false
+    };
+    while !remaining.starts_with(')') {
+        remaining = match skip_field_descriptor(remaining) {
+            Some(remaining) => remaining,
+            None => return false
+        };
+    }
+    match &remaining[1..] {
+        "V" => true,
+        return_type => skip_field_descriptor(return_type) == Some("")
+    }
+}
+/// Skip over the field descriptor at the start of the string, giving the remaining text
+fn skip_field_descriptor(s: &str) -> Option<&str> {
+    let s = s.trim_start_matches('[');
+    match s.chars().next()? {
+        'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' => Some(&s[1..]),
+        'L' => {
+            let end = s.find(';')?;
+            let name = &s[1..end];
+            if name.is_empty() || name.contains(&['.', '(', ')', '['][..]) {
+                return None
+            }
+            Some(&s[end + 1..])
+        },
+        _ => None
+    }
+}
+impl<'a> Mappings for BorrowedMappings<'a> {
+    #[inline]
+    fn get_remapped_class(&self, original: &ReferenceType) -> Option<&ReferenceType> {
+        self.classes.get(original.internal_name())
+    }
+
+    fn get_remapped_field(&self, original: &FieldData) -> Option<Cow<'_, FieldData>> {
+        let key = (original.declaring_type().internal_name(), &*original.name);
+        self.fields.get(&key).map(|&renamed| {
+            let mut data = original.transform_class(self);
+            data.name = renamed.into();
+            Cow::Owned(data)
+        })
+    }
+
+    fn get_remapped_method(&self, original: &MethodData) -> Option<Cow<'_, MethodData>> {
+        let key = (
+            original.declaring_type().internal_name(),
+            &*original.name,
+            original.signature().descriptor()
+        );
+        self.methods.get(&key).map(|&renamed| {
+            let mut data = original.transform_class(self);
+            data.name = renamed.into();
+            Cow::Owned(data)
+        })
+    }
+
+    fn frozen(&self) -> FrozenMappings {
+        FrozenMappings::new(
+            self.classes.iter().map(|(&original, renamed)| {
+                (ReferenceType::from_internal_name(original), renamed.clone())
+            }),
+            self.fields.iter().map(|(&(declaring_type, name), &renamed)| (
+                FieldData::new(name.into(), ReferenceType::from_internal_name(declaring_type)),
+                renamed.into()
+            )),
+            self.methods.iter().map(|(&(declaring_type, name, descriptor), &renamed)| (
+                MethodData::new(
+                    name.into(),
+                    ReferenceType::from_internal_name(declaring_type),
+                    MethodSignature::from_descriptor(descriptor)
+                ),
+                renamed.into()
+            ))
+        )
+    }
+}
+impl<'a> TypeTransformer for BorrowedMappings<'a> {
+    #[inline]
+    fn maybe_remap_class(&self, original: &ReferenceType) -> Option<ReferenceType> {
+        self.get_remapped_class(original).cloned()
+    }
+}
@@ -0,0 +1,109 @@
+use std::fmt;
+
+use failure_derive::Fail;
+
+use crate::prelude::*;
+use crate::utils::FnvIndexMap;
+
+/// A problem at a stage boundary that would make chaining silently produce the wrong result.
+#[derive(Debug, Fail)]
+pub enum ChainValidationError {
+    /// The stage maps two different classes to the same name,
+    /// so it can't be inverted without losing one of them.
+    #[fail(display = "Stage {} maps both {} and {} to {}", stage, first, second, renamed)]
+    NonInjective {
+        stage: usize,
+        renamed: String,
+        first: String,
+        second: String
+    },
+    /// The stage's originals look like the previous stage's originals,
+    /// not like its renamed names.
+    #[fail(display = "Stage {} doesn't start from the names produced by stage {}", stage, previous)]
+    NamespaceMismatch {
+        stage: usize,
+        previous: usize
+    }
+}
+
+/// A problem found while chaining, which would make the result confusing.
+#[derive(Debug, Fail)]
+pub enum ChainError {
+    /// The class is renamed through each of the members and then back to the first one.
+    ///
+    /// The members are given by their internal names, starting with the original.
+    Cycle {
+        members: Vec<String>
+    }
+}
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ChainError::Cycle { ref members } => {
+                write!(f, "Rename cycle: ")?;
+                for member in members {
+                    write!(f, "{} -> ", member)?;
+                }
+                write!(f, "{}", members[0])
+            }
+        }
+    }
+}
+
+/// Check that none of the classes in `first` are renamed back to their original name by `second`.
+///
+/// Classes which both mappings leave alone aren't cycles.
+pub(super) fn check_cycles<T: Mappings>(first: &FrozenMappings, second: &T) -> Result<(), ChainError> {
+    for (original, renamed) in first.classes() {
+        if original != renamed && second.get_remapped_class(renamed) == Some(original) {
+            return Err(ChainError::Cycle {
+                members: vec![original.internal_name().into(), renamed.internal_name().into()]
+            })
+        }
+    }
+    Ok(())
+}
+
+fn check_injective(stage: usize, mappings: &FrozenMappings) -> Result<(), ChainValidationError> {
+    let mut seen: FnvIndexMap<&ReferenceType, &ReferenceType> = FnvIndexMap::default();
+    for (original, renamed) in mappings.classes() {
+        if let Some(first) = seen.insert(renamed, original) {
+            return Err(ChainValidationError::NonInjective {
+                stage,
+                renamed: renamed.internal_name().into(),
+                first: first.internal_name().into(),
+                second: original.internal_name().into()
+            })
+        }
+    }
+    Ok(())
+}
+
+/// Check that the originals of `next` come from the renamed namespace of `previous`.
+///
+/// Classes which both stages leave alone don't tell us anything,
+/// so we only count the originals of `next` that show up on exactly one side of `previous`.
+fn check_compatible(previous: &FrozenMappings, next: &FrozenMappings) -> bool {
+    let inverted = previous.inverted();
+    let (mut matching, mut mismatched) = (0usize, 0usize);
+    for original in next.original_classes() {
+        let is_renamed = inverted.get_remapped_class(original).is_some();
+        let is_original = previous.get_remapped_class(original).is_some();
+        match (is_renamed, is_original) {
+            (true, false) => matching += 1,
+            (false, true) => mismatched += 1,
+            _ => {}
+        }
+    }
+    mismatched <= matching
+}
+
+pub(super) fn validate_chain(stages: &[&FrozenMappings]) -> Result<(), ChainValidationError> {
+    for (stage, mappings) in stages.iter().enumerate() {
+        if stage > 0 && !check_compatible(stages[stage - 1], mappings) {
+            return Err(ChainValidationError::NamespaceMismatch { stage, previous: stage - 1 })
+        }
+        check_injective(stage, mappings)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use crate::prelude::*;
+use crate::utils::{FnvIndexMap, FnvIndexSet};
+
+/// How many of a package's classes are renamed by a mapping.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PackageCoverage {
+    /// The number of classes which are renamed
+    pub mapped: usize,
+    /// The number of classes which keep their original name
+    pub unmapped: usize,
+    pub total: usize
+}
+
+pub(super) fn coverage_by_package(mappings: &FrozenMappings) -> FnvIndexMap<String, PackageCoverage> {
+    // Classes with members are known to the mapping, even if the class itself isn't renamed
+    let mut classes: FnvIndexSet<&ReferenceType> = mappings.original_classes().collect();
+    classes.extend(mappings.original_fields().map(FieldData::declaring_type));
+    classes.extend(mappings.original_methods().map(MethodData::declaring_type));
+    let mut result: FnvIndexMap<String, PackageCoverage> = FnvIndexMap::default();
+    for class in classes {
+        let coverage = result.entry(class.package_name().into()).or_default();
+        match mappings.get_remapped_class(class) {
+            Some(renamed) if renamed != class => coverage.mapped += 1,
+            _ => coverage.unmapped += 1
+        }
+        coverage.total += 1;
+    }
+    result
+}
@@ -0,0 +1,145 @@
+use std::hash::Hash;
+
+use crate::prelude::*;
+use crate::utils::FnvIndexMap;
+
+/// A change in a method's signature between two mappings.
+///
+/// Methods are matched by their declaring type and name,
+/// so a signature change shows up as drift instead of a removal and an addition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MethodDrift {
+    /// The method's signature changed from `original` to `revised`
+    Changed {
+        original: MethodData,
+        revised: MethodData
+    },
+    /// Several overloads changed at once, so we can't tell which one became which
+    Ambiguous {
+        original: Vec<MethodData>,
+        revised: Vec<MethodData>
+    }
+}
+
+fn methods_by_name(mappings: &FrozenMappings) -> FnvIndexMap<(&ReferenceType, &str), Vec<&MethodData>> {
+    let mut result: FnvIndexMap<_, Vec<_>> = FnvIndexMap::default();
+    for original in mappings.original_methods() {
+        result.entry((original.declaring_type(), &*original.name))
+            .or_default()
+            .push(original);
+    }
+    result
+}
+
+pub(super) fn diff_methods_by_name(first: &FrozenMappings, second: &FrozenMappings) -> Vec<MethodDrift> {
+    let second_methods = methods_by_name(second);
+    let mut result = Vec::new();
+    for (key, first_overloads) in methods_by_name(first) {
+        let second_overloads = match second_methods.get(&key) {
+            Some(overloads) => overloads,
+            None => continue
+        };
+        // Overloads with an identical signature haven't drifted
+        let original: Vec<MethodData> = first_overloads.iter()
+            .filter(|data| !second_overloads.contains(data))
+            .map(|&data| data.clone())
+            .collect();
+        let revised: Vec<MethodData> = second_overloads.iter()
+            .filter(|data| !first_overloads.contains(data))
+            .map(|&data| data.clone())
+            .collect();
+        match (original.len(), revised.len()) {
+            // Either nothing changed, or it's a plain addition/removal
+            (0, _) | (_, 0) => {},
+            (1, 1) => result.push(MethodDrift::Changed {
+                original: original.into_iter().next().unwrap(),
+                revised: revised.into_iter().next().unwrap()
+            }),
+            _ => result.push(MethodDrift::Ambiguous { original, revised })
+        }
+    }
+    result
+}
+
+/// An entry of a mapping, giving the renamed value of the original
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MappingEntry<T> {
+    pub original: T,
+    pub renamed: T
+}
+/// An original entry which is renamed differently in each mapping
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangedEntry<T> {
+    pub original: T,
+    /// The renamed value in the first mapping
+    pub old: T,
+    /// The renamed value in the second mapping
+    pub new: T
+}
+/// The differences between one kind of entry in two mappings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntryDiff<T> {
+    /// The entries which are only in the second mapping
+    pub added: Vec<MappingEntry<T>>,
+    /// The entries which are only in the first mapping
+    pub removed: Vec<MappingEntry<T>>,
+    pub changed: Vec<ChangedEntry<T>>
+}
+impl<T> EntryDiff<T> {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+/// The differences between the class, field and method entries of two mappings.
+///
+/// Entries are matched by their originals, and their renamed values are compared
+/// including their declaring types and signatures.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MappingsDiff {
+    pub classes: EntryDiff<ReferenceType>,
+    pub fields: EntryDiff<FieldData>,
+    pub methods: EntryDiff<MethodData>
+}
+impl MappingsDiff {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty() && self.fields.is_empty() && self.methods.is_empty()
+    }
+}
+
+fn diff_entries<'a, T, I>(first: I, second: I) -> EntryDiff<T>
+    where T: Hash + Eq + Clone + 'a, I: Iterator<Item=(&'a T, &'a T)> {
+    let second: FnvIndexMap<&T, &T> = second.collect();
+    let mut seen = vec![false; second.len()];
+    let mut result = EntryDiff { added: Vec::new(), removed: Vec::new(), changed: Vec::new() };
+    for (original, renamed) in first {
+        match second.get_full(original) {
+            Some((index, _, &new)) => {
+                seen[index] = true;
+                if renamed != new {
+                    result.changed.push(ChangedEntry {
+                        original: original.clone(),
+                        old: renamed.clone(),
+                        new: new.clone()
+                    });
+                }
+            },
+            None => result.removed.push(MappingEntry { original: original.clone(), renamed: renamed.clone() })
+        }
+    }
+    for ((&original, &renamed), seen) in second.iter().zip(seen) {
+        if !seen {
+            result.added.push(MappingEntry { original: original.clone(), renamed: renamed.clone() });
+        }
+    }
+    result
+}
+
+pub(super) fn diff(first: &FrozenMappings, second: &FrozenMappings) -> MappingsDiff {
+    MappingsDiff {
+        classes: diff_entries(first.classes(), second.classes()),
+        fields: diff_entries(first.fields(), second.fields()),
+        methods: diff_entries(first.methods(), second.methods())
+    }
+}
@@ -1,16 +1,30 @@
 use std::ptr;
+use std::iter;
 use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
 use std::borrow::Cow;
 use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
 
 use indexmap::{map};
 use lazy_static::*;
 use difference::Changeset;
 use owning_ref::ArcRef;
 use lazycell::AtomicLazyCell;
+use fnv::{FnvHasher, FnvHashMap};
+use parking_lot::Mutex;
 
-use crate::utils::{FnvIndexMap};
+use crate::utils::{FnvIndexMap, FnvIndexSet, FnvLruCache};
 use crate::prelude::*;
+use super::diff::{MethodDrift, MappingsDiff};
+use super::chain::{ChainValidationError, ChainError};
+use super::merge::MergeConflict;
+use super::reflection::ReflectionTable;
+use super::coverage::PackageCoverage;
+use super::inheritance::InheritanceMappings;
+use super::visitor::MappingsVisitor;
+#[cfg(feature = "bloom")]
+use super::bloom::LazyClassFilter;
 
 
 #[derive(Clone)]
@@ -31,6 +45,9 @@ impl FrozenMappingsBox {
         }
     }
 }
+/// Mappings are equal if they have the same entries, regardless of the order they were inserted in.
+///
+/// Comments, parameters and access flags are ignored.
 impl PartialEq for FrozenMappings {
     fn eq(&self, other: &FrozenMappings) -> bool {
         self.0 == other.0
@@ -45,22 +62,106 @@ impl Debug for FrozenMappings {
             .finish()
     }
 }
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 struct FrozenMappingsInner {
     classes: FnvIndexMap<ReferenceType, ReferenceType>,
     methods: FnvIndexMap<MethodData, MethodData>,
-    fields: FnvIndexMap<FieldData, FieldData>
+    fields: FnvIndexMap<FieldData, FieldData>,
+    /// The index of the first field with each name, for fields we know the types of
+    untyped_fields: FnvHashMap<FieldData, usize>,
+    /// The renamed parameters, which most formats don't include
+    parameters: FnvIndexMap<ParameterData, ParameterData>,
+    comments: EntryComments,
+    /// The package renames these mappings were parsed from, if they were kept
+    packages: FnvIndexMap<String, String>,
+    /// Remapped signatures keyed by their original descriptor, if caching is enabled
+    signature_cache: Mutex<Option<FnvLruCache<String, MethodSignature>>>,
+    #[cfg(feature = "bloom")]
+    class_filter: LazyClassFilter
+}
+/// The user-supplied comments attached to a mapping's entries, keyed by their original data.
+///
+/// Like access flags, these don't affect the equality of the mappings.
+#[derive(Clone, Debug, Default)]
+pub(super) struct EntryComments {
+    pub(super) classes: FnvIndexMap<ReferenceType, String>,
+    pub(super) fields: FnvIndexMap<FieldData, String>,
+    pub(super) methods: FnvIndexMap<MethodData, String>
+}
+impl PartialEq for FrozenMappingsInner {
+    fn eq(&self, other: &FrozenMappingsInner) -> bool {
+        // NOTE: IndexMap equality already ignores the order of the entries
+        self.classes == other.classes && self.methods == other.methods && self.fields == other.fields
+    }
 }
 impl FrozenMappingsInner {
+    fn untyped_field_index(fields: &FnvIndexMap<FieldData, FieldData>) -> FnvHashMap<FieldData, usize> {
+        let mut result = FnvHashMap::default();
+        for (index, original) in fields.keys().enumerate() {
+            if original.descriptor().is_some() {
+                result.entry(original.without_descriptor()).or_insert(index);
+            }
+        }
+        result
+    }
+    /// Find the entry for the specified field.
+    ///
+    /// If there's no exact match, we fall back to a field with the same name,
+    /// since the field's type could be missing from either the mappings or the original.
+    fn find_field(&self, original: &FieldData) -> Option<(&FieldData, &FieldData)> {
+        if let Some(entry) = self.fields.get_key_value(original) {
+            return Some(entry)
+        }
+        if original.descriptor().is_none() {
+            return self.untyped_fields.get(original).and_then(|&index| self.fields.get_index(index))
+        }
+        let untyped = original.without_descriptor();
+        self.fields.get_key_value(&untyped)
+            .or_else(|| self.untyped_fields.get(&untyped).and_then(|&index| self.fields.get_index(index)))
+    }
     fn inverted(&self) -> Self {
+        // Comments on entries without a mapping still need to be keyed by their renamed data
+        let comments = EntryComments {
+            classes: self.comments.classes.iter()
+                .map(|(original, comment)| (
+                    self.classes.get(original).unwrap_or(original).clone(),
+                    comment.clone()
+                ))
+                .collect(),
+            fields: self.comments.fields.iter()
+                .map(|(original, comment)| (
+                    self.fields.get(original).cloned()
+                        .unwrap_or_else(|| original.transform_class(&self.classes)),
+                    comment.clone()
+                ))
+                .collect(),
+            methods: self.comments.methods.iter()
+                .map(|(original, comment)| (
+                    self.methods.get(original).cloned()
+                        .unwrap_or_else(|| original.transform_class(&self.classes)),
+                    comment.clone()
+                ))
+                .collect(),
+        };
+        let fields = self.fields.iter()
+            .map(|(original, revised)| (revised.clone(), original.clone()))
+            .collect();
         FrozenMappingsInner {
-            classes: self.classes.iter()
+            untyped_fields: Self::untyped_field_index(&fields),
+            fields, comments,
+            packages: self.packages.iter()
+                .map(|(original, renamed)| (renamed.clone(), original.clone()))
+                .collect(),
+            signature_cache: Mutex::new(None),
+            parameters: self.parameters.iter()
                 .map(|(original, revised)| (revised.clone(), original.clone()))
                 .collect(),
-            methods: self.methods.iter()
+            #[cfg(feature = "bloom")]
+            class_filter: LazyClassFilter::default(),
+            classes: self.classes.iter()
                 .map(|(original, revised)| (revised.clone(), original.clone()))
                 .collect(),
-            fields: self.fields.iter()
+            methods: self.methods.iter()
                 .map(|(original, revised)| (revised.clone(), original.clone()))
                 .collect(),
         }
@@ -82,6 +183,20 @@ impl FrozenMappings {
     pub fn empty() -> FrozenMappings {
         EMPTY_MAPPINGS.clone()
     }
+    #[inline]
+    pub fn builder() -> FrozenMappingsBuilder {
+        FrozenMappingsBuilder::default()
+    }
+    /// Create mappings that know about the specified classes, but don't rename anything.
+    ///
+    /// Each class is mapped to itself, so the mappings are their own inverse.
+    pub fn identity_classes<I: IntoIterator<Item=ReferenceType>>(classes: I) -> FrozenMappings {
+        FrozenMappings::new(
+            classes.into_iter().map(|class| (class.clone(), class)),
+            iter::empty(),
+            iter::empty()
+        )
+    }
     pub fn new_ref<'a, C, F, M>(classes: C, fields: F, methods: M) -> FrozenMappings
         where C: IntoIterator<Item=(&'a ReferenceType, &'a ReferenceType)>,
               F: IntoIterator<Item=(&'a FieldData, &'a String)>,
@@ -93,6 +208,16 @@ impl FrozenMappings {
         )
     }
     pub fn new<C, F, M>(classes: C, fields: F, methods: M) -> FrozenMappings
+        where C: IntoIterator<Item=(ReferenceType, ReferenceType)>,
+              F: IntoIterator<Item=(FieldData, String)>,
+              M: IntoIterator<Item=(MethodData, String)> {
+        Self::new_full(classes, fields, methods, FnvIndexMap::default(), EntryComments::default())
+    }
+    pub(super) fn new_full<C, F, M>(
+        classes: C, fields: F, methods: M,
+        parameters: FnvIndexMap<ParameterData, String>,
+        comments: EntryComments
+    ) -> FrozenMappings
         where C: IntoIterator<Item=(ReferenceType, ReferenceType)>,
               F: IntoIterator<Item=(FieldData, String)>,
               M: IntoIterator<Item=(MethodData, String)> {
@@ -106,8 +231,14 @@ impl FrozenMappings {
             let mut second = first.transform_class(&classes);
             second.name = name.clone();
             (first, second)
+        }).collect::<FnvIndexMap<MethodData, MethodData>>();
+        let parameters = parameters.into_iter().map(|(first, name)| {
+            let method = methods.get(first.method()).cloned()
+                .unwrap_or_else(|| first.method().transform_class(&classes));
+            let second = ParameterData::new(name, method, first.index());
+            (first, second)
         }).collect();
-        Self::new_raw(classes, fields, methods)
+        Self::new_raw_full(classes, fields, methods, parameters, comments)
     }
     /// Create a new FrozenMappings from the specified indexmaps,
     /// without checking that the mappings are consistent.
@@ -116,14 +247,37 @@ impl FrozenMappings {
         fields: FnvIndexMap<FieldData, FieldData>,
         methods: FnvIndexMap<MethodData, MethodData>
     ) -> FrozenMappings {
-        let primary = FrozenMappingsInner { classes, fields, methods };
+        Self::new_raw_full(classes, fields, methods, FnvIndexMap::default(), EntryComments::default())
+    }
+    fn new_raw_full(
+        classes: FnvIndexMap<ReferenceType, ReferenceType>,
+        fields: FnvIndexMap<FieldData, FieldData>,
+        methods: FnvIndexMap<MethodData, MethodData>,
+        parameters: FnvIndexMap<ParameterData, ParameterData>,
+        comments: EntryComments
+    ) -> FrozenMappings {
+        let primary = FrozenMappingsInner {
+            untyped_fields: FrozenMappingsInner::untyped_field_index(&fields),
+            classes, fields, methods, parameters, comments,
+            packages: FnvIndexMap::default(),
+            signature_cache: Mutex::new(None),
+            #[cfg(feature = "bloom")]
+            class_filter: LazyClassFilter::default()
+        };
+        FrozenMappings::from_inner(primary)
+    }
+    fn from_inner(primary: FrozenMappingsInner) -> FrozenMappings {
         let boxed = Arc::new(FrozenMappingsBox {
             primary, inverted: AtomicLazyCell::NONE
         });
         FrozenMappings(ArcRef::new(boxed).map(|boxed| &boxed.primary))
     }
     /// Chain the specified mappings onto this one,
-    /// using the renamed result of each mapping as the original for the next
+    /// using the renamed result of each mapping as the original for the next.
+    ///
+    /// Comments from both mappings are carried along with their entries,
+    /// preferring this mapping's comment if both have one.
+    /// Package mappings aren't kept.
     pub fn chain<T: for<'a> IterableMappings<'a> >(&self, mapping: T) -> FrozenMappings {
         let mut classes = FnvIndexMap::default();
         let mut fields = FnvIndexMap::default();
@@ -172,7 +326,596 @@ impl FrozenMappings {
             let renamed = mapping.remap_method(renamed);
             methods.insert(original.clone(), renamed);
         }
-        FrozenMappings::new_raw(classes, fields, methods)
+        // We can only carry over our own parameters, since mappings can't iterate over theirs
+        let parameters = self.parameters()
+            .map(|(original, renamed)| {
+                let renamed = mapping.get_remapped_parameter(renamed)
+                    .map(Cow::into_owned)
+                    .unwrap_or_else(|| renamed.transform_class(&mapping));
+                (original.clone(), renamed)
+            })
+            .collect();
+        // Comments are keyed by the oldest original, with ours taking precedence
+        let mut comments = self.0.comments.clone();
+        for (original, _) in mapping.classes() {
+            if let Some(comment) = mapping.class_comment(original) {
+                let original = inverted.get_remapped_class(original).unwrap_or(original);
+                comments.classes.entry(original.clone()).or_insert_with(|| comment.into());
+            }
+        }
+        for (original, _) in mapping.fields() {
+            if let Some(comment) = mapping.field_comment(original) {
+                comments.fields.entry(inverted.remap_field(original)).or_insert_with(|| comment.into());
+            }
+        }
+        for (original, _) in mapping.methods() {
+            if let Some(comment) = mapping.method_comment(original) {
+                comments.methods.entry(inverted.remap_method(original)).or_insert_with(|| comment.into());
+            }
+        }
+        FrozenMappings::new_raw_full(classes, fields, methods, parameters, comments)
+    }
+    /// Chain the specified mappings onto this one, failing if a class would be renamed back to its original name.
+    ///
+    /// Renaming `A` to `B` and then back to `A` is almost always a mistake,
+    /// so this reports the members of the cycle instead of silently giving an identity mapping.
+    /// Classes which neither mapping renames aren't cycles.
+    pub fn chain_checked<T: for<'a> IterableMappings<'a>>(&self, mapping: T) -> Result<FrozenMappings, ChainError> {
+        super::chain::check_cycles(self, &mapping)?;
+        Ok(self.chain(mapping))
+    }
+    /// Check that the specified stages can be safely chained together, in order.
+    ///
+    /// Each stage must start from the names produced by the previous stage,
+    /// and must not map two classes to the same name (which would break inverting the chain).
+    /// Returns an error describing the first problematic stage.
+    pub fn validate_chain(stages: &[&FrozenMappings]) -> Result<(), ChainValidationError> {
+        super::chain::validate_chain(stages)
+    }
+    /// Compare the method signatures of this mapping with another version of it.
+    ///
+    /// Methods are matched by their original declaring type and name,
+    /// reporting any whose signature differs between the two mappings.
+    /// Overloads with identical signatures in both are ignored,
+    /// and if more than one overload changed we report them as ambiguous.
+    pub fn diff_methods_by_name(&self, other: &FrozenMappings) -> Vec<MethodDrift> {
+        super::diff::diff_methods_by_name(self, other)
+    }
+    /// Combine the entries of both mappings, which must share the same originals.
+    ///
+    /// Unlike chaining, this takes the union of the two mappings,
+    /// failing if they rename the same original entry differently.
+    /// Comments from this mapping take precedence over the other's,
+    /// and neither mapping's package mappings are kept.
+    pub fn merge(&self, other: &FrozenMappings) -> Result<FrozenMappings, MergeConflict> {
+        super::merge::merge(self, other)
+    }
+    /// Compute the entries which were added, removed or changed going from this mapping to the other.
+    ///
+    /// Entries are matched by their originals, so renaming an entry shows up as a change.
+    /// Added entries are given in the order of the other mapping, and the rest in the order of this one.
+    pub fn diff(&self, other: &FrozenMappings) -> MappingsDiff {
+        super::diff::diff(self, other)
+    }
+    /// Keep only the entries that both mappings rename the same way.
+    ///
+    /// Unlike `merge`, entries that differ between the mappings are dropped instead of being an error.
+    /// Members are compared by their renamed data, including their declaring types and signatures.
+    /// Comments are kept from this mapping, but its package mappings aren't.
+    pub fn intersect(&self, other: &FrozenMappings) -> FrozenMappings {
+        let classes: FnvIndexMap<ReferenceType, ReferenceType> = self.classes()
+            .filter(|&(original, renamed)| other.get_remapped_class(original) == Some(renamed))
+            .map(|(original, renamed)| (original.clone(), renamed.clone()))
+            .collect();
+        let fields: FnvIndexMap<FieldData, FieldData> = self.fields()
+            .filter(|&(original, renamed)| other.0.fields.get(original) == Some(renamed))
+            .map(|(original, renamed)| (original.clone(), renamed.clone()))
+            .collect();
+        let methods: FnvIndexMap<MethodData, MethodData> = self.methods()
+            .filter(|&(original, renamed)| other.0.methods.get(original) == Some(renamed))
+            .map(|(original, renamed)| (original.clone(), renamed.clone()))
+            .collect();
+        let comments = EntryComments {
+            classes: self.0.comments.classes.iter()
+                .filter(|&(original, _)| classes.contains_key(original))
+                .map(|(original, comment)| (original.clone(), comment.clone()))
+                .collect(),
+            fields: self.0.comments.fields.iter()
+                .filter(|&(original, _)| fields.contains_key(original))
+                .map(|(original, comment)| (original.clone(), comment.clone()))
+                .collect(),
+            methods: self.0.comments.methods.iter()
+                .filter(|&(original, _)| methods.contains_key(original))
+                .map(|(original, comment)| (original.clone(), comment.clone()))
+                .collect()
+        };
+        let parameters = self.parameters()
+            .filter(|&(original, renamed)| {
+                matches!(other.0.parameters.get(original), Some(other) if other.name == renamed.name)
+            })
+            .map(|(original, renamed)| (original.clone(), renamed.clone()))
+            .collect();
+        // The renamed members are kept as-is, since their classes may not be in the intersection
+        FrozenMappings::new_raw_full(classes, fields, methods, parameters, comments)
+    }
+    /// Get the original class that was renamed to the specified class.
+    ///
+    /// This uses the same cached inverse as `inverted`,
+    /// so only the first reverse lookup needs to build it.
+    #[inline]
+    pub fn get_original_class(&self, renamed: &ReferenceType) -> Option<&ReferenceType> {
+        self.inverse().classes.get(renamed)
+    }
+    /// Get the original field that was renamed to the specified field
+    #[inline]
+    pub fn get_original_field(&self, renamed: &FieldData) -> Option<&FieldData> {
+        self.inverse().fields.get(renamed)
+    }
+    /// Get the original method that was renamed to the specified method
+    #[inline]
+    pub fn get_original_method(&self, renamed: &MethodData) -> Option<&MethodData> {
+        self.inverse().methods.get(renamed)
+    }
+    /// Borrow the map from renamed classes back to their originals.
+    ///
+    /// Like `get_original_class`, this uses the cached inverse instead of cloning it.
+    #[inline]
+    pub fn inverted_classes(&self) -> &FnvIndexMap<ReferenceType, ReferenceType> {
+        &self.inverse().classes
+    }
+    /// Borrow the map from renamed fields back to their originals
+    #[inline]
+    pub fn inverted_fields(&self) -> &FnvIndexMap<FieldData, FieldData> {
+        &self.inverse().fields
+    }
+    /// Borrow the map from renamed methods back to their originals
+    #[inline]
+    pub fn inverted_methods(&self) -> &FnvIndexMap<MethodData, MethodData> {
+        &self.inverse().methods
+    }
+    /// Visit each of the classes, then the fields and the methods, in the order they were inserted.
+    pub fn accept<V: MappingsVisitor>(&self, visitor: &mut V) {
+        for (original, renamed) in self.classes() {
+            visitor.visit_class(original, renamed);
+        }
+        for (original, renamed) in self.fields() {
+            visitor.visit_field(original, renamed);
+        }
+        for (original, renamed) in self.methods() {
+            visitor.visit_method(original, renamed);
+        }
+    }
+    /// Look up inherited methods using the specified class hierarchy,
+    /// which gives the direct superclass and interfaces of each class.
+    ///
+    /// Remapping a method that isn't mapped on its own declaring type
+    /// then uses the mapping of a parent's method with exactly the same name and signature.
+    pub fn with_inheritance(&self, parents: &HashMap<ReferenceType, Vec<ReferenceType>>) -> InheritanceMappings {
+        InheritanceMappings::new(self.clone(), parents.clone())
+    }
+    /// Remap a member reference given by its owner's internal name, its name and its descriptor,
+    /// like a `Fieldref` or `Methodref` in a class's constant pool.
+    ///
+    /// Methods are distinguished from fields by their descriptor starting with `(`.
+    /// Members without a mapping keep their name, but their owner and descriptor are still remapped.
+    /// Invalid method descriptors are passed through unchanged, along with the method's name.
+    pub fn remap_member_ref(&self, owner: &str, name: &str, desc: &str) -> (ReferenceType, String, String) {
+        let owner = ReferenceType::from_internal_name(owner);
+        if desc.starts_with('(') {
+            match MethodSignature::parse_descriptor(desc) {
+                Some(signature) => {
+                    let remapped = self.remap_method(&MethodData::new(name.into(), owner, signature));
+                    let descriptor = remapped.signature().descriptor().into();
+                    (remapped.declaring_type().clone(), remapped.name, descriptor)
+                },
+                None => (self.remap_class(&owner), name.into(), desc.into())
+            }
+        } else {
+            let mut original = FieldData::new(name.into(), owner);
+            original.set_descriptor(TypeDescriptor::parse_descriptor(desc));
+            let remapped = self.remap_field(&original);
+            (remapped.declaring_type().clone(), remapped.name, self.remap_descriptor_str(desc))
+        }
+    }
+    /// Check if the specified original class is mapped
+    #[inline]
+    pub fn contains_class(&self, original: &ReferenceType) -> bool {
+        self.0.classes.contains_key(original)
+    }
+    /// Check if the specified original field is mapped
+    #[inline]
+    pub fn contains_field(&self, original: &FieldData) -> bool {
+        self.0.find_field(original).is_some()
+    }
+    /// Check if the specified original method is mapped
+    #[inline]
+    pub fn contains_method(&self, original: &MethodData) -> bool {
+        self.0.methods.contains_key(original)
+    }
+    /// Check if some class was renamed to the specified class
+    #[inline]
+    pub fn contains_original_class(&self, renamed: &ReferenceType) -> bool {
+        self.inverse().classes.contains_key(renamed)
+    }
+    /// Check if some field was renamed to the specified field
+    #[inline]
+    pub fn contains_original_field(&self, renamed: &FieldData) -> bool {
+        self.inverse().find_field(renamed).is_some()
+    }
+    /// Check if some method was renamed to the specified method
+    #[inline]
+    pub fn contains_original_method(&self, renamed: &MethodData) -> bool {
+        self.inverse().methods.contains_key(renamed)
+    }
+    /// The number of mapped classes
+    #[inline]
+    pub fn class_count(&self) -> usize {
+        self.0.classes.len()
+    }
+    /// The number of mapped fields
+    #[inline]
+    pub fn field_count(&self) -> usize {
+        self.0.fields.len()
+    }
+    /// The number of mapped methods
+    #[inline]
+    pub fn method_count(&self) -> usize {
+        self.0.methods.len()
+    }
+    /// The total number of mapped classes, fields and methods
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.class_count() + self.field_count() + self.method_count()
+    }
+    /// Check if these mappings don't have any classes, fields or methods
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Cache up to the specified number of remapped method signatures,
+    /// which helps when remapping many methods that share the same signatures.
+    ///
+    /// The cache is shared by every clone of these mappings, but not by the inverted mappings.
+    /// Enabling the cache again clears it.
+    pub fn enable_signature_cache(&self, capacity: usize) {
+        *self.0.signature_cache.lock() = Some(FnvLruCache::new(capacity));
+    }
+    /// Create a copy of these mappings that records the specified package renames,
+    /// so they can be written back out along with the classes.
+    ///
+    /// The renames are only informational, since the classes should already be in their renamed packages.
+    /// This can add back the package mappings dropped by `chain` or `merge`,
+    /// and giving an empty map removes them.
+    pub fn with_package_mappings(&self, packages: FnvIndexMap<String, String>) -> FrozenMappings {
+        let primary = FrozenMappingsInner {
+            classes: self.0.classes.clone(),
+            fields: self.0.fields.clone(),
+            untyped_fields: self.0.untyped_fields.clone(),
+            methods: self.0.methods.clone(),
+            parameters: self.0.parameters.clone(),
+            comments: self.0.comments.clone(),
+            packages,
+            signature_cache: Mutex::new(None),
+            #[cfg(feature = "bloom")]
+            class_filter: LazyClassFilter::default()
+        };
+        FrozenMappings::from_inner(primary)
+    }
+    /// Iterate over the original parameters along with their renamed data.
+    ///
+    /// These aren't part of `IterableMappings`, since only a few formats give parameter names.
+    #[inline]
+    pub fn parameters(&self) -> map::Iter<'_, ParameterData, ParameterData> {
+        self.0.parameters.iter()
+    }
+    /// Consume these mappings, giving each original class along with its renamed class.
+    ///
+    /// This avoids cloning the entries if nothing else shares these mappings,
+    /// including clones and the inverted mappings.
+    pub fn into_classes(self) -> map::IntoIter<ReferenceType, ReferenceType> {
+        match self.try_into_inner() {
+            Ok(inner) => inner.classes.into_iter(),
+            Err(shared) => shared.0.classes.clone().into_iter()
+        }
+    }
+    /// Consume these mappings, giving each original field along with its renamed data.
+    ///
+    /// Like `into_classes`, this only clones the entries if the mappings are shared.
+    pub fn into_fields(self) -> map::IntoIter<FieldData, FieldData> {
+        match self.try_into_inner() {
+            Ok(inner) => inner.fields.into_iter(),
+            Err(shared) => shared.0.fields.clone().into_iter()
+        }
+    }
+    /// Consume these mappings, giving each original method along with its renamed data.
+    ///
+    /// Like `into_classes`, this only clones the entries if the mappings are shared.
+    pub fn into_methods(self) -> map::IntoIter<MethodData, MethodData> {
+        match self.try_into_inner() {
+            Ok(inner) => inner.methods.into_iter(),
+            Err(shared) => shared.0.methods.clone().into_iter()
+        }
+    }
+    /// Take the data we reference if nothing else shares it, or give back the mappings otherwise
+    fn try_into_inner(self) -> Result<FrozenMappingsInner, FrozenMappings> {
+        let is_primary = ptr::eq(&self.0.as_owner().primary, self.0.as_ref());
+        match Arc::try_unwrap(self.0.into_owner()) {
+            Ok(boxed) if is_primary => Ok(boxed.primary),
+            // If we referenced the inverted data, it must've been computed already
+            Ok(boxed) => Ok(boxed.inverted.into_inner().unwrap()),
+            Err(owner) => Err(FrozenMappings(ArcRef::new(owner).map(|boxed| {
+                if is_primary { &boxed.primary } else { boxed.inverted() }
+            })))
+        }
+    }
+    /// Get the inverse of the data we reference, computing it if needed
+    fn inverse(&self) -> &FrozenMappingsInner {
+        let owner = self.0.as_owner();
+        if ptr::eq(&owner.primary, self.0.as_ref()) {
+            owner.inverted()
+        } else {
+            &owner.primary
+        }
+    }
+    /// Find all the other classes referenced by the signatures of the specified class's mapped members.
+    ///
+    /// Array types are reduced to their element type, and the class itself isn't included.
+    pub fn referenced_classes_of(&self, class: &ReferenceType) -> Vec<ReferenceType> {
+        let mut result = FnvIndexSet::default();
+        for method in self.original_methods().filter(|data| data.declaring_type() == class) {
+            let signature = method.signature();
+            let referenced = signature.parameter_types().iter()
+                .chain(Some(signature.return_type()))
+                .filter_map(TypeDescriptor::referenced_class)
+                .filter(|&referenced| referenced != class);
+            result.extend(referenced.cloned());
+        }
+        result.into_iter().collect()
+    }
+    /// Find every class referenced anywhere in these mappings.
+    ///
+    /// This includes both the original and renamed classes, the declaring types of every member,
+    /// and every class used by field types and method signatures (reducing arrays to their element type).
+    pub fn referenced_classes(&self) -> HashSet<ReferenceType> {
+        let mut result = HashSet::new();
+        for (original, renamed) in self.classes() {
+            result.insert(original.clone());
+            result.insert(renamed.clone());
+        }
+        for (original, renamed) in self.fields() {
+            for field in &[original, renamed] {
+                result.insert(field.declaring_type().clone());
+                result.extend(field.descriptor()
+                    .and_then(TypeDescriptor::referenced_class)
+                    .cloned());
+            }
+        }
+        for (original, renamed) in self.methods() {
+            for method in &[original, renamed] {
+                result.insert(method.declaring_type().clone());
+                let signature = method.signature();
+                result.extend(signature.parameter_types().iter()
+                    .chain(Some(signature.return_type()))
+                    .filter_map(TypeDescriptor::referenced_class)
+                    .cloned());
+            }
+        }
+        result
+    }
+    /// Check that no remapped method signature still references an obfuscated class,
+    /// using the specified predicate to decide which classes are obfuscated.
+    ///
+    /// On failure, this returns the remapped data of every offending method.
+    pub fn assert_fully_remapped<F: Fn(&ReferenceType) -> bool>(&self, is_obfuscated: F) -> Result<(), Vec<MethodData>> {
+        let failures: Vec<MethodData> = self.methods()
+            .map(|(_, renamed)| renamed)
+            .filter(|renamed| {
+                let signature = renamed.signature();
+                signature.parameter_types().iter()
+                    .chain(Some(signature.return_type()))
+                    .filter_map(TypeDescriptor::referenced_class)
+                    .any(&is_obfuscated)
+            })
+            .cloned()
+            .collect();
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+    /// Rename this mapping's members using the human names from another mapping,
+    /// matching members by the searge ID of their renamed names.
+    ///
+    /// The other mapping is expected to go from searge names like `func_12345_a`
+    /// to human names like `tick`, regardless of the declaring types it uses.
+    /// Members without a searge ID, or whose ID isn't given a name, keep their current name.
+    pub fn apply_names_by_searge_id(&self, names: &FrozenMappings) -> FrozenMappings {
+        let field_names: FnvIndexMap<u64, &str> = names.fields()
+            .filter_map(|(original, renamed)| original.searge_id().map(|id| (id, &*renamed.name)))
+            .collect();
+        let method_names: FnvIndexMap<u64, &str> = names.methods()
+            .filter_map(|(original, renamed)| original.searge_id().map(|id| (id, &*renamed.name)))
+            .collect();
+        self.transform_fields(|field| {
+            field.searge_id().and_then(|id| field_names.get(&id)).map(|&name| name.into())
+        }).transform_methods(|method| {
+            method.searge_id().and_then(|id| method_names.get(&id)).map(|&name| name.into())
+        })
+    }
+    /// Split this mapping into two stages through a new intermediate layer,
+    /// returning the mappings from the originals to the intermediates
+    /// and from the intermediates to the renamed names.
+    ///
+    /// The generator is called with each original class to give its intermediate name,
+    /// and must give a unique name for each class.
+    /// Members keep their original names in the intermediate layer.
+    /// Chaining the two resulting mappings gives back this mapping.
+    pub fn regenerate_intermediate<F>(&self, mut gen: F) -> (FrozenMappings, FrozenMappings)
+        where F: FnMut(&ReferenceType) -> ReferenceType {
+        let intermediates: FnvIndexMap<ReferenceType, ReferenceType> = self.classes()
+            .map(|(original, _)| (original.clone(), gen(original)))
+            .collect();
+        let first = FrozenMappings::new(
+            intermediates.iter().map(|(original, intermediate)| (original.clone(), intermediate.clone())),
+            self.fields().map(|(original, _)| (original.clone(), original.name.clone())),
+            self.methods().map(|(original, _)| (original.clone(), original.name.clone()))
+        );
+        let second = FrozenMappings::new(
+            self.classes().map(|(original, renamed)| (intermediates[original].clone(), renamed.clone())),
+            self.fields().map(|(original, renamed)| {
+                (original.transform_class(&intermediates), renamed.name.clone())
+            }),
+            self.methods().map(|(original, renamed)| {
+                (original.transform_class(&intermediates), renamed.name.clone())
+            })
+        );
+        (first, second)
+    }
+    /// Add mappings for the inner classes of renamed classes, keeping their `$Inner` suffix.
+    ///
+    /// Inner classes are found from the declaring types of the original members,
+    /// and follow the rename of their closest mapped outer class.
+    /// The outer classes in between are mapped too, so `obf4$a` becomes `Player$a` along with `obf4$a$b`.
+    /// Inner classes which already have an explicit mapping are left alone.
+    pub fn expand_inner_classes(&self) -> FrozenMappings {
+        let mut result = self.rebuild();
+        let declaring_types = self.original_fields().map(FieldData::declaring_type)
+            .chain(self.original_methods().map(MethodData::declaring_type));
+        let mut missing = Vec::new();
+        for original in declaring_types {
+            if result.classes.contains_key(original) { continue }
+            missing.clear();
+            missing.push(original.clone());
+            let mut outer = original.outer_class();
+            while let Some(current) = outer {
+                if let Some(renamed) = self.get_remapped_class(&current) {
+                    for inner in missing.drain(..) {
+                        let suffix = &inner.internal_name()[current.internal_name().len()..];
+                        let renamed = format!("{}{}", renamed.internal_name(), suffix);
+                        result.classes.entry(inner)
+                            .or_insert_with(|| ReferenceType::from_internal_name(&renamed));
+                    }
+                    break
+                }
+                outer = current.outer_class();
+                missing.push(current);
+            }
+        }
+        result.frozen()
+    }
+    /// Count how many classes in each original package are renamed by this mapping,
+    /// keyed by the internal name of the package.
+    ///
+    /// Classes are counted if they're mapped or declare a mapped member,
+    /// and are considered unmapped if they keep their original name.
+    pub fn coverage_by_package(&self) -> FnvIndexMap<String, PackageCoverage> {
+        super::coverage::coverage_by_package(self)
+    }
+    /// Build a table of lookups from deobfuscated names to obfuscated names,
+    /// assuming this mapping goes from obfuscated to deobfuscated names.
+    #[inline]
+    pub fn build_reflection_table(&self) -> ReflectionTable {
+        ReflectionTable::new(self)
+    }
+    /// Create a copy of these mappings with the entries sorted by their original names,
+    /// so writing them gives deterministic output regardless of the order they were inserted in.
+    ///
+    /// Members are sorted by their declaring class, then their name,
+    /// and methods with the same name are sorted by descriptor.
+    pub fn sorted(&self) -> FrozenMappings {
+        let mut classes = self.0.classes.clone();
+        classes.sort_by(|first, _, second, _| first.internal_name().cmp(second.internal_name()));
+        let mut fields = self.0.fields.clone();
+        fields.sort_by(|first, _, second, _| field_key(first).cmp(&field_key(second)));
+        let mut methods = self.0.methods.clone();
+        methods.sort_by(|first, _, second, _| method_key(first).cmp(&method_key(second)));
+        let mut parameters = self.0.parameters.clone();
+        parameters.sort_by(|first, _, second, _| {
+            (method_key(first.method()), first.index()).cmp(&(method_key(second.method()), second.index()))
+        });
+        let sorted = FrozenMappings::new_raw_full(classes, fields, methods, parameters, self.0.comments.clone());
+        if self.0.packages.is_empty() {
+            sorted
+        } else {
+            sorted.with_package_mappings(self.0.packages.clone())
+        }
+    }
+    /// Create a copy of these mappings without the types of their fields,
+    /// like they'd have if they came from a format that doesn't give field types.
+    ///
+    /// This is needed to compare or merge with mappings from those formats.
+    /// If several fields only differed by their types, the first of them is kept.
+    pub fn without_field_types(&self) -> FrozenMappings {
+        let mut fields = FnvIndexMap::default();
+        for (original, renamed) in self.fields() {
+            fields.entry(original.without_descriptor()).or_insert_with(|| renamed.without_descriptor());
+        }
+        let mut comments = self.0.comments.clone();
+        comments.fields = self.0.comments.fields.iter()
+            .map(|(original, comment)| (original.without_descriptor(), comment.clone()))
+            .collect();
+        let result = FrozenMappings::new_raw_full(
+            self.0.classes.clone(), fields, self.0.methods.clone(),
+            self.0.parameters.clone(), comments
+        );
+        if self.0.packages.is_empty() {
+            result
+        } else {
+            result.with_package_mappings(self.0.packages.clone())
+        }
+    }
+    /// Create a copy of these mappings that only renames classes,
+    /// dropping every field, method and parameter entry.
+    ///
+    /// Class comments and package renames are kept.
+    pub fn rename_classes_only(&self) -> FrozenMappings {
+        let comments = EntryComments {
+            classes: self.0.comments.classes.clone(),
+            ..EntryComments::default()
+        };
+        let result = FrozenMappings::new_raw_full(
+            self.0.classes.clone(), FnvIndexMap::default(), FnvIndexMap::default(),
+            FnvIndexMap::default(), comments
+        );
+        if self.0.packages.is_empty() {
+            result
+        } else {
+            result.with_package_mappings(self.0.packages.clone())
+        }
+    }
+    /// Check if this mapping has exactly the same entries as the other one,
+    /// regardless of the order they were inserted in.
+    ///
+    /// This is the same as `==`, which already ignores the order of the entries.
+    #[inline]
+    pub fn content_eq(&self, other: &FrozenMappings) -> bool {
+        self == other
+    }
+    /// Compute a stable hash of this mapping's entries,
+    /// which doesn't depend on the order they were inserted in.
+    ///
+    /// Mappings which are `content_eq` are guaranteed to have the same hash,
+    /// making this suitable as a key for content-addressed caches.
+    pub fn content_hash(&self) -> u64 {
+        fn entry_hash<K: Hash, V: Hash>(kind: u8, original: &K, renamed: &V) -> u64 {
+            let mut hasher = FnvHasher::default();
+            kind.hash(&mut hasher);
+            original.hash(&mut hasher);
+            renamed.hash(&mut hasher);
+            hasher.finish()
+        }
+        let mut result = 0;
+        for (original, renamed) in self.classes() {
+            result ^= entry_hash(0, original, renamed);
+        }
+        for (original, renamed) in self.fields() {
+            result ^= entry_hash(1, original, renamed);
+        }
+        for (original, renamed) in self.methods() {
+            result ^= entry_hash(2, original, renamed);
+        }
+        result
     }
     #[doc(hidden)]
     pub fn srg_difference(&self, other: &FrozenMappings) -> Changeset {
@@ -200,19 +943,94 @@ impl FrozenMappings {
                 .collect(),
             method_names: self.methods()
                 .map(|(first, second)| (first.clone(), second.name.clone()))
-                .collect()
+                .collect(),
+            parameter_names: self.parameters()
+                .map(|(first, second)| (first.clone(), second.name.clone()))
+                .collect(),
+            class_comments: self.0.comments.classes.clone(),
+            field_comments: self.0.comments.fields.clone(),
+            method_comments: self.0.comments.methods.clone()
         }
     }
 }
+#[inline]
+fn field_key(field: &FieldData) -> (&str, &str, &str) {
+    (field.declaring_type().internal_name(), &field.name, field.descriptor().map_or("", TypeDescriptor::descriptor))
+}
+#[inline]
+fn method_key(method: &MethodData) -> (&str, &str, &str) {
+    (method.declaring_type().internal_name(), &method.name, method.signature().descriptor())
+}
+/// A fluent builder for `FrozenMappings`, mostly useful for constructing small mappings in code.
+///
+/// ```
+/// # use srglib::prelude::*;
+/// let mappings = FrozenMappings::builder()
+///     .class_name("obf4", "net.techcable.Player")
+///     .field(FieldData::new("a".into(), ReferenceType::from_name("obf4")), "dead")
+///     .build();
+/// assert_eq!(mappings.remap_class_name("obf4").internal_name(), "net/techcable/Player");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FrozenMappingsBuilder {
+    classes: Vec<(ReferenceType, ReferenceType)>,
+    fields: Vec<(FieldData, String)>,
+    methods: Vec<(MethodData, String)>
+}
+impl FrozenMappingsBuilder {
+    #[inline]
+    pub fn class(&mut self, original: ReferenceType, renamed: ReferenceType) -> &mut Self {
+        self.classes.push((original, renamed));
+        self
+    }
+    /// Rename the class with the specified java name, like `org.spigotmc.XRay`
+    #[inline]
+    pub fn class_name(&mut self, original: &str, renamed: &str) -> &mut Self {
+        self.class(ReferenceType::from_name(original), ReferenceType::from_name(renamed))
+    }
+    #[inline]
+    pub fn field(&mut self, original: FieldData, renamed: &str) -> &mut Self {
+        self.fields.push((original, renamed.into()));
+        self
+    }
+    #[inline]
+    pub fn method(&mut self, original: MethodData, renamed: &str) -> &mut Self {
+        self.methods.push((original, renamed.into()));
+        self
+    }
+    /// Build the mappings, remapping the members' declaring types and signatures
+    /// just like `FrozenMappings::new`.
+    pub fn build(&self) -> FrozenMappings {
+        FrozenMappings::new(
+            self.classes.iter().cloned(),
+            self.fields.iter().cloned(),
+            self.methods.iter().cloned()
+        )
+    }
+}
 impl Mappings for FrozenMappings {
     #[inline]
     fn get_remapped_class(&self, original: &ReferenceType) -> Option<&ReferenceType> {
+        #[cfg(feature = "bloom")]
+        {
+            if !self.0.class_filter.get(&self.0.classes).may_contain(original) {
+                return None
+            }
+        }
         self.0.classes.get(original)
     }
 
+    /// Get the remapped field, falling back to a field with the same name if the types don't match
     #[inline]
     fn get_remapped_field(&self, original: &FieldData) -> Option<Cow<FieldData>> {
-        self.0.fields.get(original).map(Cow::Borrowed)
+        let (key, renamed) = self.0.find_field(original)?;
+        if key.descriptor() == original.descriptor() {
+            Some(Cow::Borrowed(renamed))
+        } else {
+            let mut data = original.transform_class(self);
+            data.name = renamed.name.clone();
+            Some(Cow::Owned(data))
+        }
     }
 
     #[inline]
@@ -220,6 +1038,32 @@ impl Mappings for FrozenMappings {
         self.0.methods.get(original).map(Cow::Borrowed)
     }
 
+    #[inline]
+    fn get_remapped_parameter(&self, original: &ParameterData) -> Option<Cow<'_, ParameterData>> {
+        self.0.parameters.get(original).map(Cow::Borrowed)
+    }
+
+    #[inline]
+    fn class_comment(&self, original: &ReferenceType) -> Option<&str> {
+        self.0.comments.classes.get(original).map(String::as_str)
+    }
+
+    #[inline]
+    fn field_comment(&self, original: &FieldData) -> Option<&str> {
+        self.0.comments.fields.get(original)
+            .or_else(|| self.0.comments.fields.get(self.0.find_field(original)?.0))
+            .map(String::as_str)
+    }
+
+    #[inline]
+    fn method_comment(&self, original: &MethodData) -> Option<&str> {
+        self.0.comments.methods.get(original).map(String::as_str)
+    }
+    #[inline]
+    fn package_mappings(&self) -> Option<&FnvIndexMap<String, String>> {
+        if self.0.packages.is_empty() { None } else { Some(&self.0.packages) }
+    }
+
     #[inline]
     fn frozen(&self) -> FrozenMappings {
         self.clone()
@@ -246,6 +1090,22 @@ impl TypeTransformer for FrozenMappings {
     fn maybe_remap_class(&self, original: &ReferenceType) -> Option<ReferenceType> {
         self.get_remapped_class(original).cloned()
     }
+    fn remap_signature(&self, original: &MethodSignature) -> MethodSignature {
+        // Only hold the lock to access the cache, so other threads can use it while we remap
+        let cached = self.0.signature_cache.lock().as_mut()
+            .map(|cache| cache.get(original.descriptor()).cloned());
+        match cached {
+            Some(Some(remapped)) => remapped,
+            Some(None) => {
+                let remapped = original.raw_transform_class(self);
+                if let Some(ref mut cache) = *self.0.signature_cache.lock() {
+                    cache.insert(original.descriptor().into(), remapped.clone());
+                }
+                remapped
+            },
+            None => original.raw_transform_class(self)
+        }
+    }
 }
 impl<'a> IterableMappings<'a> for FrozenMappings {
     type FieldValue = &'a FieldData;
@@ -0,0 +1,80 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::prelude::*;
+use crate::utils::FnvIndexSet;
+
+/// Mappings which look up inherited methods using a known class hierarchy.
+///
+/// Mappings usually only list a method on the class that declares it,
+/// so a call through a subclass wouldn't otherwise be renamed.
+/// Created by `FrozenMappings::with_inheritance`.
+#[derive(Debug, Default)]
+pub struct InheritanceMappings {
+    mappings: FrozenMappings,
+    /// The direct superclass and interfaces of each class
+    parents: HashMap<ReferenceType, Vec<ReferenceType>>
+}
+impl InheritanceMappings {
+    pub(super) fn new(mappings: FrozenMappings, parents: HashMap<ReferenceType, Vec<ReferenceType>>) -> Self {
+        InheritanceMappings { mappings, parents }
+    }
+    /// Find the renamed name of an inherited method with the same name and signature,
+    /// searching depth-first through each class's parents in the order they were given.
+    ///
+    /// Since the superclass is given first, it's searched before any interfaces.
+    fn find_inherited(&self, original: &MethodData) -> Option<String> {
+        let mut visited = FnvIndexSet::default();
+        let mut remaining = vec![original.declaring_type()];
+        while let Some(class) = remaining.pop() {
+            // Interfaces can be inherited more than once
+            if !visited.insert(class) { continue }
+            let inherited = MethodData::new(original.name.clone(), class.clone(), original.signature().clone());
+            if let Some(renamed) = self.mappings.get_remapped_method(&inherited) {
+                return Some(renamed.name.clone())
+            }
+            if let Some(parents) = self.parents.get(class) {
+                remaining.extend(parents.iter().rev());
+            }
+        }
+        None
+    }
+}
+impl Mappings for InheritanceMappings {
+    #[inline]
+    fn get_remapped_class(&self, original: &ReferenceType) -> Option<&ReferenceType> {
+        self.mappings.get_remapped_class(original)
+    }
+
+    #[inline]
+    fn get_remapped_field(&self, original: &FieldData) -> Option<Cow<'_, FieldData>> {
+        self.mappings.get_remapped_field(original)
+    }
+
+    /// Get the remapped method, falling back to the superclasses and interfaces that have it.
+    ///
+    /// The inherited method must have exactly the same name and signature.
+    fn get_remapped_method(&self, original: &MethodData) -> Option<Cow<'_, MethodData>> {
+        self.find_inherited(original).map(|renamed| {
+            let mut data = original.transform_class(&self.mappings);
+            data.name = renamed;
+            Cow::Owned(data)
+        })
+    }
+
+    /// Freeze the underlying mappings, which no longer know about the class hierarchy
+    #[inline]
+    fn frozen(&self) -> FrozenMappings {
+        self.mappings.clone()
+    }
+}
+impl TypeTransformer for InheritanceMappings {
+    #[inline]
+    fn maybe_remap_class(&self, original: &ReferenceType) -> Option<ReferenceType> {
+        self.mappings.maybe_remap_class(original)
+    }
+    #[inline]
+    fn remap_signature(&self, original: &MethodSignature) -> MethodSignature {
+        self.mappings.remap_signature(original)
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt;
+
+use failure_derive::Fail;
+
+use crate::prelude::*;
+
+/// An entry that was renamed differently by each of the merged mappings.
+#[derive(Debug, Fail)]
+pub enum MergeConflict {
+    /// The class is renamed differently, to `first` by the first mapping and `second` by the other
+    Class {
+        original: ReferenceType,
+        first: ReferenceType,
+        second: ReferenceType
+    },
+    /// The field is given a different name by each mapping
+    Field {
+        original: FieldData,
+        first: String,
+        second: String
+    },
+    /// The method is given a different name by each mapping
+    Method {
+        original: MethodData,
+        first: String,
+        second: String
+    }
+}
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MergeConflict::Class { ref original, ref first, ref second } => write!(
+                f, "Class {} is renamed to both {} and {}",
+                original.internal_name(), first.internal_name(), second.internal_name()
+            ),
+            MergeConflict::Field { ref original, ref first, ref second } => write!(
+                f, "Field {} is renamed to both {} and {}",
+                original.internal_name(), first, second
+            ),
+            MergeConflict::Method { ref original, ref first, ref second } => write!(
+                f, "Method {} {} is renamed to both {} and {}",
+                original.internal_name(), original.signature().descriptor(), first, second
+            )
+        }
+    }
+}
+
+pub(super) fn merge(first: &FrozenMappings, second: &FrozenMappings) -> Result<FrozenMappings, MergeConflict> {
+    let mut result = first.rebuild();
+    for (original, renamed) in second.classes() {
+        match result.classes.get(original) {
+            Some(existing) if existing != renamed => return Err(MergeConflict::Class {
+                original: original.clone(),
+                first: existing.clone(),
+                second: renamed.clone()
+            }),
+            Some(_) => {},
+            None => { result.classes.insert(original.clone(), renamed.clone()); }
+        }
+        if let Some(comment) = second.class_comment(original) {
+            result.class_comments.entry(original.clone()).or_insert_with(|| comment.into());
+        }
+    }
+    for (original, renamed) in second.fields() {
+        match result.field_names.get(original) {
+            Some(existing) if *existing != renamed.name => return Err(MergeConflict::Field {
+                original: original.clone(),
+                first: existing.clone(),
+                second: renamed.name.clone()
+            }),
+            Some(_) => {},
+            None => { result.field_names.insert(original.clone(), renamed.name.clone()); }
+        }
+        if let Some(comment) = second.field_comment(original) {
+            result.field_comments.entry(original.clone()).or_insert_with(|| comment.into());
+        }
+    }
+    for (original, renamed) in second.methods() {
+        match result.method_names.get(original) {
+            Some(existing) if *existing != renamed.name => return Err(MergeConflict::Method {
+                original: original.clone(),
+                first: existing.clone(),
+                second: renamed.name.clone()
+            }),
+            Some(_) => {},
+            None => { result.method_names.insert(original.clone(), renamed.name.clone()); }
+        }
+        if let Some(comment) = second.method_comment(original) {
+            result.method_comments.entry(original.clone()).or_insert_with(|| comment.into());
+        }
+    }
+    for (original, renamed) in second.parameters() {
+        result.parameter_names.entry(original.clone()).or_insert_with(|| renamed.name.clone());
+    }
+    Ok(result.frozen())
+}
@@ -1,13 +1,38 @@
 use std::borrow::{Borrow, Cow};
+use std::iter;
+
+use fnv::{FnvHashMap, FnvHashSet};
 
 use super::prelude::*;
+use super::utils::FnvIndexMap;
 
 pub mod simple;
 pub mod frozen;
+pub mod diff;
+pub mod chain;
+pub mod merge;
+pub mod borrowed;
+pub mod reflection;
+pub mod coverage;
+pub mod validate;
+pub mod inheritance;
+pub mod visitor;
+#[cfg(feature = "bloom")]
+mod bloom;
+mod signature_remapper;
 pub(crate) mod transformer;
 
 pub use self::simple::SimpleMappings;
-pub use self::frozen::FrozenMappings;
+pub use self::frozen::{FrozenMappings, FrozenMappingsBuilder};
+pub use self::borrowed::BorrowedMappings;
+pub use self::diff::{MethodDrift, MappingsDiff, EntryDiff, MappingEntry, ChangedEntry};
+pub use self::chain::{ChainValidationError, ChainError};
+pub use self::merge::MergeConflict;
+pub use self::reflection::ReflectionTable;
+pub use self::coverage::PackageCoverage;
+pub use self::validate::MappingsValidationError;
+pub use self::inheritance::InheritanceMappings;
+pub use self::visitor::MappingsVisitor;
 
 /// Chain all the specified mappings together,
 /// using the renamed result of each mapping as the original for the next
@@ -30,6 +55,51 @@ pub trait Mappings: Default + ::std::fmt::Debug + transformer::TypeTransformer {
     fn remap_type(&self, original: &TypeDescriptor) -> TypeDescriptor {
         original.transform_class(self)
     }
+    /// Remap all of the specified types, reusing the result for repeated types.
+    fn remap_types<'a, I: IntoIterator<Item=&'a TypeDescriptor>>(&self, types: I) -> Vec<TypeDescriptor> {
+        let mut cache = FnvHashMap::<&'a TypeDescriptor, TypeDescriptor>::default();
+        types.into_iter().map(|original| {
+            cache.entry(original)
+                .or_insert_with(|| self.remap_type(original))
+                .clone()
+        }).collect()
+    }
+    /// Remap a raw field or method descriptor, like `Lobf4;` or `(Lobf4;ID)Z`.
+    ///
+    /// Method descriptors are detected by their leading `(`,
+    /// and invalid descriptors are passed through unchanged.
+    fn remap_descriptor_str(&self, raw: &str) -> String {
+        if raw.starts_with('(') {
+            self.remap_method_descriptor(raw)
+        } else {
+            match TypeDescriptor::parse_descriptor(raw) {
+                Some(original) => self.remap_type(&original).descriptor().into(),
+                None => raw.into()
+            }
+        }
+    }
+    /// Remap a raw method descriptor like `(Lobf4;ID)Z`,
+    /// remapping the classes of its parameters and return type.
+    ///
+    /// Invalid descriptors are passed through unchanged.
+    fn remap_method_descriptor(&self, descriptor: &str) -> String {
+        match MethodSignature::parse_descriptor(descriptor) {
+            Some(signature) => self.remap_signature(&signature).descriptor().into(),
+            None => descriptor.into()
+        }
+    }
+    /// Remap the class names in a generic signature from a `Signature` attribute,
+    /// like `<T:Lobf4;>(TT;Ljava/util/List<+Lobfs;>;)V`.
+    ///
+    /// Class, method and field signatures are all supported, including type variables and wildcards.
+    /// Inner classes of parameterized types like `Lobf4<TT;>.a;` are remapped using their full name.
+    /// Invalid signatures are passed through unchanged.
+    fn remap_signature_string(&self, generic_signature: &str) -> String {
+        match signature_remapper::remap_signature(self, generic_signature) {
+            Ok(remapped) => remapped,
+            Err(_) => generic_signature.into()
+        }
+    }
     #[inline]
     fn remap_class(&self, original: &ReferenceType) -> ReferenceType {
         self.get_remapped_class(original).unwrap_or(original).clone()
@@ -38,7 +108,21 @@ pub trait Mappings: Default + ::std::fmt::Debug + transformer::TypeTransformer {
     fn remap_class_name(&self, original: &str) -> ReferenceType {
         self.remap_class(&ReferenceType::from_name(original))
     }
+    /// Get the simple name of the remapped class, without its package
+    #[inline]
+    fn remapped_simple_name(&self, original: &ReferenceType) -> String {
+        self.remap_class(original).simple_name().into()
+    }
+    /// Get the internal name of the remapped class's package, like `net/minecraft/server`
+    #[inline]
+    fn remapped_package(&self, original: &ReferenceType) -> String {
+        self.remap_class(original).package_name().into()
+    }
     /// Get the remapped field data, or `None` if the field doesn't exist
+    ///
+    /// If there's no exact match, this falls back to a field with the same name,
+    /// since the field's type could be missing from either the original or the mappings.
+    /// A field mapped without its type is preferred, then the first field mapped with one.
     fn get_remapped_field(&self, original: &FieldData) -> Option<Cow<FieldData>>;
     /// Get the remapped field data.
     ///
@@ -62,6 +146,174 @@ pub trait Mappings: Default + ::std::fmt::Debug + transformer::TypeTransformer {
             original.transform_class(self)
         })
     }
+    /// Get the remapped parameter data, or `None` if the mappings don't rename the parameter.
+    ///
+    /// Most formats don't include parameters, so this is usually `None`.
+    #[inline]
+    fn get_remapped_parameter(&self, _original: &ParameterData) -> Option<Cow<'_, ParameterData>> {
+        None
+    }
+    /// Get the user-supplied comment attached to the original class, if any
+    #[inline]
+    fn class_comment(&self, _original: &ReferenceType) -> Option<&str> {
+        None
+    }
+    /// Get the user-supplied comment attached to the original field, if any
+    #[inline]
+    fn field_comment(&self, _original: &FieldData) -> Option<&str> {
+        None
+    }
+    /// Get the user-supplied comment attached to the original method, if any
+    #[inline]
+    fn method_comment(&self, _original: &MethodData) -> Option<&str> {
+        None
+    }
+    /// Get the package mappings these mappings were parsed from,
+    /// with the default package given as the empty string.
+    ///
+    /// These are only kept if the parser was asked to keep them,
+    /// since the renames are already applied to the classes.
+    /// Returns `None` if there aren't any package mappings.
+    ///
+    /// Sorting and inverting the mappings keeps them,
+    /// but they're dropped when chaining, merging, intersecting or transforming the mappings.
+    #[inline]
+    fn package_mappings(&self) -> Option<&FnvIndexMap<String, String>> {
+        None
+    }
+    /// Remap a verification type from a `StackMapTable`-style type list.
+    ///
+    /// Object types are given as bare internal names (`java/lang/String`),
+    /// while arrays are given as descriptors (`[Ljava/lang/String;`).
+    /// Special tokens like `Top`, `Null` and `UninitializedThis` pass through unchanged,
+    /// as does anything we can't parse.
+    fn remap_verification_type(&self, s: &str) -> String {
+        match s {
+            "Top" | "Integer" | "Float" | "Long" | "Double" | "Null" | "UninitializedThis" => s.into(),
+            _ if s.starts_with('[') => match ArrayType::parse_descriptor(s) {
+                Some(array) => self.remap_type(&array.into_type_descriptor()).descriptor().into(),
+                None => s.into()
+            },
+            _ if s.is_empty() || s.contains('.') || s.starts_with("Uninitialized(") => s.into(),
+            _ => self.remap_class(&ReferenceType::from_internal_name(s)).internal_name().into()
+        }
+    }
+    /// Remap the components of an `EnclosingMethod` attribute,
+    /// giving the remapped class, method name and method descriptor.
+    ///
+    /// The method is absent for classes enclosed directly by another class.
+    /// The method's name can only be remapped if we also know its descriptor,
+    /// and invalid descriptors are passed through unchanged.
+    fn remap_enclosing_method(
+        &self,
+        class: &ReferenceType,
+        method_name: Option<&str>,
+        descriptor: Option<&str>
+    ) -> (ReferenceType, Option<String>, Option<String>) {
+        let remapped_class = self.remap_class(class);
+        let signature = match descriptor {
+            Some(descriptor) => match MethodSignature::parse_descriptor(descriptor) {
+                Some(signature) => signature,
+                None => return (remapped_class, method_name.map(String::from), Some(descriptor.into()))
+            },
+            None => return (remapped_class, method_name.map(String::from), None)
+        };
+        match method_name {
+            Some(name) => {
+                let remapped = self.remap_method(&MethodData::new(name.into(), class.clone(), signature));
+                (remapped_class, Some(remapped.name.clone()), Some(remapped.signature().descriptor().into()))
+            },
+            None => {
+                let remapped = signature.transform_class(self);
+                (remapped_class, None, Some(remapped.descriptor().into()))
+            }
+        }
+    }
+    /// Remap a component of a record's `Record` attribute, giving the remapped name and descriptor.
+    ///
+    /// Each record component is backed by a field of the same name,
+    /// so the component is renamed along with the field.
+    /// Invalid descriptors are passed through unchanged.
+    fn remap_record_component(&self, owner: &ReferenceType, name: &str, descriptor: &str) -> (String, String) {
+        let mut field = FieldData::new(name.into(), owner.clone());
+        let component_type = TypeDescriptor::parse_descriptor(descriptor);
+        field.set_descriptor(component_type.clone());
+        let remapped_name = self.remap_field(&field).name;
+        let remapped_descriptor = match component_type {
+            Some(component_type) => component_type.transform_class(self).descriptor().into(),
+            None => descriptor.into()
+        };
+        (remapped_name, remapped_descriptor)
+    }
+    /// Remap an entry of an `InnerClasses` attribute, giving the remapped inner class,
+    /// outer class and simple name of the inner class.
+    ///
+    /// The simple name is absent for anonymous classes, and is kept if the inner class isn't renamed.
+    /// Otherwise it's derived from the remapped inner class, ignoring the numeric prefix of local classes.
+    fn remap_inner_class_entry(
+        &self,
+        inner: &ReferenceType,
+        outer: Option<&ReferenceType>,
+        inner_simple_name: Option<&str>
+    ) -> (ReferenceType, Option<ReferenceType>, Option<String>) {
+        let remapped_inner = self.remap_class(inner);
+        let remapped_outer = outer.map(|outer| self.remap_class(outer));
+        let remapped_name = match inner_simple_name {
+            Some(name) if remapped_inner == *inner => Some(name.into()),
+            Some(_) => {
+                let name = remapped_inner.simple_inner_name();
+                Some(name.trim_start_matches(|c: char| c.is_ascii_digit()).into())
+            },
+            None => None
+        };
+        (remapped_inner, remapped_outer, remapped_name)
+    }
+    /// Remap the classes listed by a sealed class's `PermittedSubclasses` attribute.
+    fn remap_permitted_subclasses<'a, I: IntoIterator<Item=&'a ReferenceType>>(&self, subclasses: I) -> Vec<ReferenceType> {
+        subclasses.into_iter().map(|subclass| self.remap_class(subclass)).collect()
+    }
+    /// Remap a value from a config file if it's a known class name like `org.spigotmc.XRay`.
+    ///
+    /// Inner classes like `org.spigotmc.XRay$Manager` are remapped directly if they're known,
+    /// and otherwise by remapping their outer class and keeping the inner suffix.
+    /// Values that aren't the name of a mapped class are returned unchanged.
+    fn remap_config_value(&self, value: &str) -> String {
+        let is_class_name = !value.is_empty()
+            && !value.starts_with('.') && !value.ends_with('.')
+            && value.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$' || c == '.');
+        if !is_class_name { return value.into() }
+        if let Some(renamed) = self.get_remapped_class(&ReferenceType::from_name(value)) {
+            return renamed.name()
+        }
+        // Try the enclosing classes, starting with the innermost
+        for (separator, _) in value.rmatch_indices('$') {
+            let outer = ReferenceType::from_name(&value[..separator]);
+            if let Some(renamed) = self.get_remapped_class(&outer) {
+                let mut result = renamed.name();
+                result.push_str(&value[separator..]);
+                return result
+            }
+        }
+        value.into()
+    }
+    /// Remap a package referenced by a module's `module-info`, given as an internal name like `java/lang`.
+    ///
+    /// The package's new name comes from the kept package mappings if they have it,
+    /// and is otherwise inferred from where its classes were moved.
+    /// If none of its classes are mapped the package is returned unchanged.
+    #[inline]
+    fn remap_module_package(&self, internal_package: &str) -> String {
+        self.remap_module_packages(iter::once(internal_package)).remove(0)
+    }
+    /// Remap the package list of a module's `exports` or `opens` directive.
+    ///
+    /// The package renames are only inferred once, instead of once for each package.
+    /// By default this freezes the mappings to find their classes,
+    /// so implementations that can iterate over their classes should override it.
+    fn remap_module_packages<'a, I: IntoIterator<Item=&'a str>>(&self, packages: I) -> Vec<String> {
+        let frozen = self.frozen();
+        remap_packages(frozen.package_mappings(), frozen.classes(), packages)
+    }
     fn frozen(&self) -> FrozenMappings;
     fn inverted(&self) -> FrozenMappings {
         self.frozen().inverted()
@@ -77,6 +329,20 @@ pub trait MutableMappings: Mappings {
     fn clear_classes(&mut self);
     fn clear_fields(&mut self);
     fn clear_methods(&mut self);
+    /// Insert every class, field and method renamed by the other mappings.
+    ///
+    /// Like `IndexMap::insert`, the other mappings win if they rename an entry we already have.
+    fn merge_from<'a, T: IterableMappings<'a>>(&mut self, other: &'a T) {
+        for (original, renamed) in other.classes() {
+            self.set_remapped_class(original.clone(), renamed.clone());
+        }
+        for (original, renamed) in other.fields() {
+            self.set_field_name(original.clone(), renamed.into().name);
+        }
+        for (original, renamed) in other.methods() {
+            self.set_method_name(original.clone(), renamed.into().name);
+        }
+    }
 }
 pub trait IterableMappings<'a>: Mappings {
     type FieldValue: Borrow<FieldData> + Into<FieldData>;
@@ -95,10 +361,42 @@ pub trait IterableMappings<'a>: Mappings {
     fn fields(&'a self) -> Self::Fields;
     fn methods(&'a self) -> Self::Methods;
 
+    /// Iterate over the distinct packages of the original classes, in the order they're first seen.
+    ///
+    /// Classes in the default package give the empty package `""`.
+    #[inline]
+    fn packages(&'a self) -> Packages<'a, Self::OriginalClasses> {
+        Packages { iter: self.original_classes(), seen: FnvHashSet::default() }
+    }
+    /// Iterate over the distinct packages of the renamed classes, in the order they're first seen.
+    #[inline]
+    fn renamed_packages(&'a self) -> Packages<'a, RenamedClasses<'a, Self::Classes>> {
+        let renamed: fn((&'a ReferenceType, &'a ReferenceType)) -> &'a ReferenceType = |(_, renamed)| renamed;
+        Packages { iter: self.classes().map(renamed), seen: FnvHashSet::default() }
+    }
+    /// Iterate over the original fields,
+    /// pairing each with the result of remapping it using the specified transformer.
+    ///
+    /// This ignores this mapping's own renamed data,
+    /// and lazily remaps each field without building a new mapping.
+    #[inline]
+    fn remapped_fields_with<T: TypeTransformer>(&'a self, transformer: &'a T) -> RemappedFields<'a, Self::OriginalFields, T> {
+        RemappedFields { iter: self.original_fields(), transformer }
+    }
+    /// Iterate over the original methods,
+    /// pairing each with the result of remapping it using the specified transformer.
+    ///
+    /// This ignores this mapping's own renamed data,
+    /// and lazily remaps each method without building a new mapping.
+    #[inline]
+    fn remapped_methods_with<T: TypeTransformer>(&'a self, transformer: &'a T) -> RemappedMethods<'a, Self::OriginalMethods, T> {
+        RemappedMethods { iter: self.original_methods(), transformer }
+    }
+
     /// Transform all of this mapping's data using the specified mappings.
     ///
     /// The returned mapping data is guaranteed to have the same originals
-    /// as the data of the old mapping data
+    /// as the data of the old mapping data, but without any package mappings.
     #[inline]
     fn transform<T: Mappings>(&'a self, transformer: T) -> FrozenMappings  {
         self::transformer::transform(self, transformer)
@@ -107,16 +405,50 @@ pub trait IterableMappings<'a>: Mappings {
         where F: Fn(&str) -> Option<String> {
         self.transform_classes(|t| {
             let (package_name, simple_name) = t.split_name();
-            match func(package_name) {
-                Some(updated_package) => {
-                    let mut result: String = updated_package.clone();
-                    if !result.is_empty() {
-                        result.push('/');
-                    }
-                    result.push_str(simple_name);
-                    Some(ReferenceType::from_internal_name(&result))
-                },
-                None => None
+            func(package_name).map(|updated_package| join_class_name(&updated_package, simple_name))
+        })
+    }
+    /// Move individual renamed classes to a new package, keeping their simple names.
+    ///
+    /// Unlike `transform_packages`, the closure is given each class
+    /// so classes that share a package can be moved separately.
+    fn transform_class_packages<F>(&'a self, func: F) -> FrozenMappings
+        where F: Fn(&ReferenceType) -> Option<String> {
+        self.transform_classes(|t| {
+            func(t).map(|updated_package| join_class_name(&updated_package, t.simple_name()))
+        })
+    }
+    /// Transform the simple names of the renamed classes,
+    /// giving the closure each class's package and simple name.
+    ///
+    /// The closure returns the new simple name, and the class stays in the same package.
+    fn transform_class_names<F>(&'a self, func: F) -> FrozenMappings
+        where F: Fn(&str, &str) -> Option<String> {
+        self.transform_classes(|t| {
+            let (package_name, simple_name) = t.split_name();
+            func(package_name, simple_name).map(|updated_name| join_class_name(package_name, &updated_name))
+        })
+    }
+    /// Lowercase the packages of all the renamed classes,
+    /// so mixed-case packages can't collide on case-insensitive filesystems.
+    fn normalize_package_case(&'a self) -> FrozenMappings {
+        self.transform_packages(|package| {
+            if package.chars().any(char::is_uppercase) {
+                Some(package.to_lowercase())
+            } else {
+                None
+            }
+        })
+    }
+    /// Remove the specified prefix from the internal names of the renamed classes,
+    /// like the `v1_16/` left over from namespacing them.
+    ///
+    /// Classes that don't start with the prefix are left alone, as are classes that are nothing but the prefix.
+    fn strip_renamed_prefix(&'a self, prefix: &str) -> FrozenMappings {
+        self.transform_classes(|t| {
+            match t.internal_name().strip_prefix(prefix) {
+                Some(stripped) if !stripped.is_empty() => Some(ReferenceType::from_internal_name(stripped)),
+                _ => None
             }
         })
     }
@@ -144,4 +476,120 @@ pub trait IterableMappings<'a>: Mappings {
             self::transformer::MethodRenamer(func)
         )
     }
-}
\ No newline at end of file
+}
+/// Guess what each package was renamed to, based on where its classes were moved.
+///
+/// If a package's classes were split between several packages,
+/// this picks the most common one, preferring the first seen on ties.
+fn infer_package_renames<'a, I>(classes: I) -> FnvHashMap<&'a str, &'a str>
+    where I: IntoIterator<Item=(&'a ReferenceType, &'a ReferenceType)> {
+    let mut counts: FnvIndexMap<(&str, &str), usize> = FnvIndexMap::default();
+    for (original, renamed) in classes {
+        *counts.entry((original.package_name(), renamed.package_name())).or_insert(0) += 1;
+    }
+    let mut best: FnvHashMap<&str, (&str, usize)> = FnvHashMap::default();
+    for ((original, renamed), count) in counts {
+        match best.get(original) {
+            Some(&(_, best_count)) if best_count >= count => {},
+            _ => {
+                best.insert(original, (renamed, count));
+            }
+        }
+    }
+    best.into_iter().map(|(original, (renamed, _))| (original, renamed)).collect()
+}
+/// Remap each package using the package mappings if they have it,
+/// and otherwise using the renames inferred from the classes.
+pub(crate) fn remap_packages<'a, 'b, C, I>(
+    package_mappings: Option<&FnvIndexMap<String, String>>,
+    classes: C,
+    packages: I
+) -> Vec<String>
+    where C: IntoIterator<Item=(&'a ReferenceType, &'a ReferenceType)>, I: IntoIterator<Item=&'b str> {
+    let inferred = infer_package_renames(classes);
+    packages.into_iter()
+        .map(|package| {
+            package_mappings.and_then(|mappings| mappings.get(package)).map(String::as_str)
+                .or_else(|| inferred.get(package).cloned())
+                .unwrap_or(package).into()
+        })
+        .collect()
+}
+/// Join a package's internal name with a class's simple name
+fn join_class_name(package_name: &str, simple_name: &str) -> ReferenceType {
+    let mut result = String::from(package_name);
+    if !result.is_empty() {
+        result.push('/');
+    }
+    result.push_str(simple_name);
+    ReferenceType::from_internal_name(&result)
+}
+pub type RenamedClasses<'a, I> = iter::Map<I, fn((&'a ReferenceType, &'a ReferenceType)) -> &'a ReferenceType>;
+pub struct Packages<'a, I: Iterator<Item=&'a ReferenceType>> {
+    iter: I,
+    seen: FnvHashSet<&'a str>
+}
+impl<'a, I: Iterator<Item=&'a ReferenceType>> Iterator for Packages<'a, I> {
+    type Item = &'a str;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a str> {
+        let seen = &mut self.seen;
+        self.iter.by_ref()
+            .map(ReferenceType::package_name)
+            .find(|package| seen.insert(package))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+impl<'a, I> iter::FusedIterator for Packages<'a, I>
+    where I: iter::FusedIterator<Item=&'a ReferenceType> {}
+
+pub struct RemappedFields<'a, I: Iterator<Item=&'a FieldData>, T: TypeTransformer> {
+    iter: I,
+    transformer: &'a T
+}
+impl<'a, I: Iterator<Item=&'a FieldData>, T: TypeTransformer> Iterator for RemappedFields<'a, I, T> {
+    type Item = (&'a FieldData, FieldData);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let transformer = self.transformer;
+        self.iter.next().map(|original| (original, original.transform_class(transformer)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+impl<'a, I, T> iter::ExactSizeIterator for RemappedFields<'a, I, T>
+    where I: iter::ExactSizeIterator<Item=&'a FieldData>, T: TypeTransformer {}
+impl<'a, I, T> iter::FusedIterator for RemappedFields<'a, I, T>
+    where I: iter::FusedIterator<Item=&'a FieldData>, T: TypeTransformer {}
+
+pub struct RemappedMethods<'a, I: Iterator<Item=&'a MethodData>, T: TypeTransformer> {
+    iter: I,
+    transformer: &'a T
+}
+impl<'a, I: Iterator<Item=&'a MethodData>, T: TypeTransformer> Iterator for RemappedMethods<'a, I, T> {
+    type Item = (&'a MethodData, MethodData);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let transformer = self.transformer;
+        self.iter.next().map(|original| (original, original.transform_class(transformer)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+impl<'a, I, T> iter::ExactSizeIterator for RemappedMethods<'a, I, T>
+    where I: iter::ExactSizeIterator<Item=&'a MethodData>, T: TypeTransformer {}
+impl<'a, I, T> iter::FusedIterator for RemappedMethods<'a, I, T>
+    where I: iter::FusedIterator<Item=&'a MethodData>, T: TypeTransformer {}
@@ -0,0 +1,80 @@
+use indexmap::Equivalent;
+
+use crate::prelude::*;
+use crate::utils::FnvIndexMap;
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct MemberKey {
+    class: String,
+    name: String,
+    descriptor: String
+}
+/// A borrowed `MemberKey`, which must hash exactly the same way
+#[derive(Hash)]
+struct MemberRef<'a> {
+    class: &'a str,
+    name: &'a str,
+    descriptor: &'a str
+}
+impl<'a> Equivalent<MemberKey> for MemberRef<'a> {
+    #[inline]
+    fn equivalent(&self, key: &MemberKey) -> bool {
+        self.class == key.class && self.name == key.name && self.descriptor == key.descriptor
+    }
+}
+
+/// Precomputed lookups from deobfuscated names to obfuscated names,
+/// for code doing runtime reflection against obfuscated classes.
+///
+/// All class names are java names like `net.minecraft.server.World`,
+/// which is what reflection expects.
+/// Method descriptors are given using the deobfuscated class names.
+#[derive(Debug, Default)]
+pub struct ReflectionTable {
+    classes: FnvIndexMap<String, String>,
+    // NOTE: Fields don't have a descriptor, so it's always empty
+    fields: FnvIndexMap<MemberKey, String>,
+    methods: FnvIndexMap<MemberKey, String>
+}
+impl ReflectionTable {
+    /// Build the table for the specified obfuscated to deobfuscated mappings
+    pub fn new(mappings: &FrozenMappings) -> ReflectionTable {
+        let inverted = mappings.inverted();
+        ReflectionTable {
+            classes: inverted.classes()
+                .map(|(deobf, obf)| (deobf.name(), obf.name()))
+                .collect(),
+            fields: inverted.fields()
+                .map(|(deobf, obf)| (MemberKey {
+                    class: deobf.declaring_type().name(),
+                    name: deobf.name.clone(),
+                    descriptor: String::new()
+                }, obf.name.clone()))
+                .collect(),
+            methods: inverted.methods()
+                .map(|(deobf, obf)| (MemberKey {
+                    class: deobf.declaring_type().name(),
+                    name: deobf.name.clone(),
+                    descriptor: deobf.signature().descriptor().into()
+                }, obf.name.clone()))
+                .collect()
+        }
+    }
+    /// Get the obfuscated name of the specified class
+    #[inline]
+    pub fn obf_class(&self, deobf: &str) -> Option<&str> {
+        self.classes.get(deobf).map(String::as_str)
+    }
+    /// Get the obfuscated name of the specified field
+    #[inline]
+    pub fn obf_field(&self, deobf_class: &str, deobf_name: &str) -> Option<&str> {
+        self.fields.get(&MemberRef { class: deobf_class, name: deobf_name, descriptor: "" })
+            .map(String::as_str)
+    }
+    /// Get the obfuscated name of the specified method
+    #[inline]
+    pub fn obf_method(&self, deobf_class: &str, deobf_name: &str, descriptor: &str) -> Option<&str> {
+        self.methods.get(&MemberRef { class: deobf_class, name: deobf_name, descriptor })
+            .map(String::as_str)
+    }
+}
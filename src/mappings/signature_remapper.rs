@@ -0,0 +1,197 @@
+//! Remapping the class names in Java generic signatures, as given by a class file's `Signature` attribute.
+//!
+//! The signature is copied as it's parsed, so only the class names are ever allocated.
+use crate::prelude::*;
+use crate::utils::*;
+
+/// Remap a class, method or field signature, or give an error if it's invalid.
+///
+/// A signature with a single class type like `Ljava/util/List<TT;>;` could be either a field or a class
+/// that only gives its superclass, but both are remapped the same way.
+pub(crate) fn remap_signature<M: Mappings>(mappings: &M, signature: &str) -> Result<String, SimpleParseError> {
+    let mut remapper = SignatureRemapper {
+        mappings,
+        parser: SimpleParser::new(signature),
+        result: String::with_capacity(signature.len())
+    };
+    remapper.signature()?;
+    remapper.parser.ensure_finished()?;
+    Ok(remapper.result)
+}
+
+/// The kind of type signature that was just remapped
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum SignatureKind {
+    Base,
+    Class,
+    TypeVariable,
+    Array
+}
+
+struct SignatureRemapper<'a, M: Mappings> {
+    mappings: &'a M,
+    parser: SimpleParser<'a>,
+    result: String
+}
+impl<'a, M: Mappings> SignatureRemapper<'a, M> {
+    fn signature(&mut self) -> Result<(), SimpleParseError> {
+        let has_type_parameters = self.type_parameters()?;
+        if self.parser.remaining().starts_with('(') {
+            return self.method_rest()
+        }
+        let first = self.type_signature()?;
+        if !has_type_parameters && self.parser.is_finished() && first != SignatureKind::Base {
+            // A field signature
+            return Ok(())
+        }
+        if first != SignatureKind::Class {
+            return Err(self.parser.error_with("Expected a superclass".into()))
+        }
+        // The interfaces of a class signature
+        while !self.parser.is_finished() {
+            self.class_type()?;
+        }
+        Ok(())
+    }
+    /// Remap the rest of a method signature, after its type parameters
+    fn method_rest(&mut self) -> Result<(), SimpleParseError> {
+        self.parser.expect('(')?;
+        self.result.push('(');
+        while !self.parser.remaining().starts_with(')') {
+            self.type_signature()?;
+        }
+        self.parser.expect(')')?;
+        self.result.push(')');
+        if self.parser.remaining().starts_with('V') {
+            self.parser.skip(1);
+            self.result.push('V');
+        } else {
+            self.type_signature()?;
+        }
+        while self.parser.remaining().starts_with('^') {
+            self.parser.skip(1);
+            self.result.push('^');
+            match self.type_signature()? {
+                SignatureKind::Array | SignatureKind::Base => {
+                    return Err(self.parser.error_with("Expected a thrown class".into()))
+                },
+                SignatureKind::Class | SignatureKind::TypeVariable => {}
+            }
+        }
+        Ok(())
+    }
+    /// Remap the formal type parameters like `<T:Ljava/lang/Object;>`, returning if there were any
+    fn type_parameters(&mut self) -> Result<bool, SimpleParseError> {
+        if !self.parser.remaining().starts_with('<') { return Ok(false) }
+        self.parser.skip(1);
+        self.result.push('<');
+        loop {
+            let name = self.parser.take_until(|c| c == ':' || c == '>');
+            if name.is_empty() {
+                return Err(self.parser.expected("a type parameter"))
+            }
+            self.parser.expect(':')?;
+            self.result.push_str(name);
+            self.result.push(':');
+            // The class bound is omitted if the parameter only has interface bounds
+            if !self.parser.remaining().starts_with(':') {
+                self.type_signature()?;
+            }
+            while self.parser.remaining().starts_with(':') {
+                self.parser.skip(1);
+                self.result.push(':');
+                self.type_signature()?;
+            }
+            if self.parser.remaining().starts_with('>') {
+                self.parser.skip(1);
+                self.result.push('>');
+                return Ok(true)
+            }
+        }
+    }
+    fn type_signature(&mut self) -> Result<SignatureKind, SimpleParseError> {
+        Ok(match self.parser.peek()? {
+            'L' => {
+                self.class_type()?;
+                SignatureKind::Class
+            },
+            'T' => {
+                self.parser.skip(1);
+                let name = self.parser.take_until(|c| c == ';');
+                if name.is_empty() {
+                    return Err(self.parser.expected("a type variable"))
+                }
+                self.parser.expect(';')?;
+                self.result.push('T');
+                self.result.push_str(name);
+                self.result.push(';');
+                SignatureKind::TypeVariable
+            },
+            '[' => {
+                self.parser.skip(1);
+                self.result.push('[');
+                self.type_signature()?;
+                SignatureKind::Array
+            },
+            'V' => return Err(self.parser.expected("a type signature")),
+            _ => {
+                let primitive = self.parser.parse::<PrimitiveType>()?;
+                self.result.push_str(primitive.descriptor());
+                SignatureKind::Base
+            }
+        })
+    }
+    /// Remap a possibly parameterized class like `Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;`,
+    /// where each inner class is remapped using its full name.
+    fn class_type(&mut self) -> Result<(), SimpleParseError> {
+        self.parser.expect('L')?;
+        let name = self.parser.take_until(|c| c == '<' || c == '.' || c == ';');
+        if name.is_empty() {
+            return Err(self.parser.expected("a class name"))
+        }
+        let mut original = ReferenceType::from_internal_name(name);
+        self.result.push('L');
+        self.result.push_str(self.mappings.remap_class(&original).internal_name());
+        self.type_arguments()?;
+        while self.parser.remaining().starts_with('.') {
+            self.parser.skip(1);
+            let name = self.parser.take_until(|c| c == '<' || c == '.' || c == ';');
+            if name.is_empty() {
+                return Err(self.parser.expected("an inner class name"))
+            }
+            original = ReferenceType::from_internal_name(&format!("{}${}", original.internal_name(), name));
+            self.result.push('.');
+            self.result.push_str(self.mappings.remap_class(&original).simple_inner_name());
+            self.type_arguments()?;
+        }
+        self.parser.expect(';')?;
+        self.result.push(';');
+        Ok(())
+    }
+    fn type_arguments(&mut self) -> Result<(), SimpleParseError> {
+        if !self.parser.remaining().starts_with('<') { return Ok(()) }
+        self.parser.skip(1);
+        self.result.push('<');
+        loop {
+            match self.parser.peek()? {
+                '*' => {
+                    self.parser.skip(1);
+                    self.result.push('*');
+                },
+                wildcard @ '+' | wildcard @ '-' => {
+                    self.parser.skip(1);
+                    self.result.push(wildcard);
+                    self.type_signature()?;
+                },
+                _ => {
+                    self.type_signature()?;
+                }
+            }
+            if self.parser.remaining().starts_with('>') {
+                self.parser.skip(1);
+                self.result.push('>');
+                return Ok(())
+            }
+        }
+    }
+}
@@ -1,16 +1,75 @@
 use std::borrow::Cow;
 use std::iter;
 
-use indexmap::{map};
+use indexmap::map::{self, MutableKeys};
 
 use crate::prelude::*;
 use crate::utils::FnvIndexMap;
+use super::frozen::EntryComments;
+use super::validate::MappingsValidationError;
 
 #[derive(Clone, Debug, Default)]
 pub struct SimpleMappings {
     pub(super) classes: FnvIndexMap<ReferenceType, ReferenceType>,
     pub(super) method_names: FnvIndexMap<MethodData, String>,
-    pub(super) field_names: FnvIndexMap<FieldData, String>
+    pub(super) field_names: FnvIndexMap<FieldData, String>,
+    pub(super) parameter_names: FnvIndexMap<ParameterData, String>,
+    pub(super) class_comments: FnvIndexMap<ReferenceType, String>,
+    pub(super) field_comments: FnvIndexMap<FieldData, String>,
+    pub(super) method_comments: FnvIndexMap<MethodData, String>
+}
+impl SimpleMappings {
+    /// Check for mistakes that would produce broken output, giving every problem we find.
+    ///
+    /// This detects classes that are renamed to the same name,
+    /// and members declared by the renamed name of a class instead of its original name.
+    pub fn validate(&self) -> Result<(), Vec<MappingsValidationError>> {
+        super::validate::validate(self)
+    }
+    /// Add all the entries of the other mappings,
+    /// replacing the renamed data and comments of any entries we already have.
+    ///
+    /// Existing entries keep their position, and new entries are added at the end.
+    pub fn extend(&mut self, other: SimpleMappings) {
+        self.classes.extend(other.classes);
+        self.method_names.extend(other.method_names);
+        self.field_names.extend(other.field_names);
+        self.parameter_names.extend(other.parameter_names);
+        self.class_comments.extend(other.class_comments);
+        self.field_comments.extend(other.field_comments);
+        self.method_comments.extend(other.method_comments);
+    }
+    /// Rename the original parameter, replacing any existing name
+    #[inline]
+    pub fn set_parameter_name(&mut self, original: ParameterData, renamed: String) {
+        self.parameter_names.insert(original, renamed);
+    }
+    /// Attach a comment to the original class, replacing any existing comment
+    #[inline]
+    pub fn set_class_comment(&mut self, original: ReferenceType, comment: String) {
+        self.class_comments.insert(original, comment);
+    }
+    /// Attach a comment to the original field, replacing any existing comment
+    #[inline]
+    pub fn set_field_comment(&mut self, original: FieldData, comment: String) {
+        self.field_comments.insert(original, comment);
+    }
+    /// Attach a comment to the original method, replacing any existing comment
+    #[inline]
+    pub fn set_method_comment(&mut self, original: MethodData, comment: String) {
+        self.method_comments.insert(original, comment);
+    }
+    /// Set the access flags of an original method that's already been renamed,
+    /// returning false if the method isn't mapped.
+    pub fn set_method_access(&mut self, original: &MethodData, access: Option<u16>) -> bool {
+        match self.method_names.get_full_mut2(original) {
+            Some((_, key, _)) => {
+                key.set_access(access);
+                true
+            },
+            None => false
+        }
+    }
 }
 impl Mappings for SimpleMappings {
     #[inline]
@@ -18,28 +77,83 @@ impl Mappings for SimpleMappings {
         self.classes.get(original)
     }
 
+    /// Get the remapped field, falling back to a field with the same name if the types don't match
+    ///
+    /// Finding the first field mapped with a type needs to search all the fields,
+    /// so freeze these mappings before doing lots of lookups.
     #[inline]
     fn get_remapped_field(&self, original: &FieldData) -> Option<Cow<FieldData>> {
-        self.field_names.get(original).map(|name| {
-            Cow::Owned(FieldData::new(
+        let name = self.field_names.get(original)
+            .or_else(|| {
+                original.descriptor()?;
+                self.field_names.get(&original.without_descriptor())
+            })
+            .or_else(|| {
+                self.field_names.iter()
+                    .find(|&(key, _)| {
+                        key.descriptor().is_some() && key.name == original.name
+                            && key.declaring_type() == original.declaring_type()
+                    })
+                    .map(|(_, name)| name)
+            })?;
+        let mut remapped = original.transform_class(self);
+        remapped.name.clone_from(name);
+        Some(Cow::Owned(remapped))
+    }
+
+    #[inline]
+    fn get_remapped_method(&self, original: &MethodData) -> Option<Cow<MethodData>> {
+        // Use our own key, which may know the method's access flags
+        self.method_names.get_key_value(original).map(|(original, name)| {
+            let mut remapped = original.transform_class(&*self);
+            remapped.name.clone_from(name);
+            Cow::Owned(remapped)
+        })
+    }
+
+    #[inline]
+    fn get_remapped_parameter(&self, original: &ParameterData) -> Option<Cow<'_, ParameterData>> {
+        self.parameter_names.get(original).map(|name| {
+            Cow::Owned(ParameterData::new(
                 name.clone(),
-                self.remap_class(original.declaring_type())
+                self.remap_method(original.method()),
+                original.index()
             ))
         })
     }
 
     #[inline]
-    fn get_remapped_method(&self, original: &MethodData) -> Option<Cow<MethodData>> {
-        self.method_names.get(original).map(|name| {
-            let mut data = original
-                .transform_class(&*self);
-            data.name = name.clone();
-            Cow::Owned(data)
-        })
+    fn class_comment(&self, original: &ReferenceType) -> Option<&str> {
+        self.class_comments.get(original).map(String::as_str)
+    }
+
+    #[inline]
+    fn field_comment(&self, original: &FieldData) -> Option<&str> {
+        self.field_comments.get(original).map(String::as_str)
+    }
+
+    #[inline]
+    fn method_comment(&self, original: &MethodData) -> Option<&str> {
+        self.method_comments.get(original).map(String::as_str)
+    }
+
+    /// Remap the packages referenced by a module's `module-info`, without needing to freeze these mappings
+    fn remap_module_packages<'a, I: IntoIterator<Item=&'a str>>(&self, packages: I) -> Vec<String> {
+        super::remap_packages(self.package_mappings(), &self.classes, packages)
     }
 
     fn frozen(&self) -> FrozenMappings {
-        FrozenMappings::new_ref(&self.classes, &self.field_names, &self.method_names)
+        FrozenMappings::new_full(
+            self.classes.iter().map(|(original, renamed)| (original.clone(), renamed.clone())),
+            self.field_names.iter().map(|(original, renamed)| (original.clone(), renamed.clone())),
+            self.method_names.iter().map(|(original, renamed)| (original.clone(), renamed.clone())),
+            self.parameter_names.clone(),
+            EntryComments {
+                classes: self.class_comments.clone(),
+                fields: self.field_comments.clone(),
+                methods: self.method_comments.clone()
+            }
+        )
     }
 }
 impl MutableMappings for SimpleMappings {
@@ -150,9 +264,9 @@ impl<'a> Iterator for Fields<'a> {
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
         Iterator::next(&mut self.iter).map(|(original, renamed)| {
-            let mut data = original.transform_class(self.mappings);
-            data.name = renamed.clone();
-            (original, data)
+            let mut remapped = original.transform_class(self.mappings);
+            remapped.name.clone_from(renamed);
+            (original, remapped)
         })
     }
 
@@ -175,10 +289,9 @@ impl<'a> Iterator for Methods<'a> {
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
         Iterator::next(&mut self.iter).map(|(original, renamed)| {
-            let mut data = original
-                .transform_class(self.mappings);
-            data.name = renamed.clone();
-            (original, data)
+            let mut remapped = original.transform_class(self.mappings);
+            remapped.name.clone_from(renamed);
+            (original, remapped)
         })
     }
 
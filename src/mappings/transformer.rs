@@ -70,10 +70,9 @@ pub trait MappingsTransformer {
         self.rename_field(original).map_or_else(
             || original.map_class(|t| self.transform_class(t)),
             |renamed| {
-                let mut data = original
-                    .map_class(|t| self.transform_class(t));
-                data.name = renamed;
-                data
+                let mut remapped = original.map_class(|t| self.transform_class(t));
+                remapped.name = renamed;
+                remapped
             }
         )
     }
@@ -81,11 +80,11 @@ pub trait MappingsTransformer {
         self.rename_method(original).map_or_else(
             || original.map_class(|t| self.transform_class(t)),
             |renamed| {
-            let mut data = original
-                .map_class(|t| self.transform_class(t));
-            data.name = renamed;
-            data
-        })
+                let mut remapped = original.map_class(|t| self.transform_class(t));
+                remapped.name = renamed;
+                remapped
+            }
+        )
     }
     fn rename_field(&self, original: &FieldData) -> Option<String>;
     fn rename_method(&self, original: &MethodData) -> Option<String>;
@@ -0,0 +1,85 @@
+use std::fmt;
+
+use failure_derive::Fail;
+
+use crate::prelude::*;
+use crate::utils::FnvIndexMap;
+
+/// A mistake in a mapping which would produce broken output when applied.
+#[derive(Debug, Fail)]
+pub enum MappingsValidationError {
+    /// Two different classes are renamed to the same class
+    ClassCollision {
+        renamed: ReferenceType,
+        first: ReferenceType,
+        second: ReferenceType
+    },
+    /// The field's declaring type isn't mapped, but it's the renamed name of another class,
+    /// so the field was probably given using the renamed names.
+    DanglingField {
+        original: FieldData,
+        renamed_from: ReferenceType
+    },
+    /// The method's declaring type isn't mapped, but it's the renamed name of another class,
+    /// so the method was probably given using the renamed names.
+    DanglingMethod {
+        original: MethodData,
+        renamed_from: ReferenceType
+    }
+}
+impl fmt::Display for MappingsValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MappingsValidationError::ClassCollision { ref renamed, ref first, ref second } => write!(
+                f, "Classes {} and {} are both renamed to {}",
+                first.internal_name(), second.internal_name(), renamed.internal_name()
+            ),
+            MappingsValidationError::DanglingField { ref original, ref renamed_from } => write!(
+                f, "Field {} is declared by the renamed name of {}",
+                original.internal_name(), renamed_from.internal_name()
+            ),
+            MappingsValidationError::DanglingMethod { ref original, ref renamed_from } => write!(
+                f, "Method {} {} is declared by the renamed name of {}",
+                original.internal_name(), original.signature().descriptor(), renamed_from.internal_name()
+            )
+        }
+    }
+}
+
+pub(super) fn validate(mappings: &SimpleMappings) -> Result<(), Vec<MappingsValidationError>> {
+    let mut errors = Vec::new();
+    let mut originals: FnvIndexMap<&ReferenceType, &ReferenceType> = FnvIndexMap::default();
+    for (original, renamed) in &mappings.classes {
+        match originals.get(renamed) {
+            Some(&first) => errors.push(MappingsValidationError::ClassCollision {
+                renamed: renamed.clone(),
+                first: first.clone(),
+                second: original.clone()
+            }),
+            None => { originals.insert(renamed, original); }
+        }
+    }
+    // Classes renamed to themselves are consistent either way
+    let renamed_from = |declaring_type: &ReferenceType| if mappings.classes.contains_key(declaring_type) {
+        None
+    } else {
+        originals.get(declaring_type).cloned()
+    };
+    for original in mappings.field_names.keys() {
+        if let Some(renamed_from) = renamed_from(original.declaring_type()) {
+            errors.push(MappingsValidationError::DanglingField {
+                original: original.clone(),
+                renamed_from: renamed_from.clone()
+            });
+        }
+    }
+    for original in mappings.method_names.keys() {
+        if let Some(renamed_from) = renamed_from(original.declaring_type()) {
+            errors.push(MappingsValidationError::DanglingMethod {
+                original: original.clone(),
+                renamed_from: renamed_from.clone()
+            });
+        }
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
@@ -0,0 +1,47 @@
+use crate::prelude::*;
+
+/// Receives the entries of a mapping one at a time, like ASM's `ClassVisitor`.
+///
+/// This lets a transform or writer stream the entries
+/// without building an intermediate mapping.
+/// Every callback does nothing by default, so visitors only need to implement the entries they care about.
+pub trait MappingsVisitor {
+    /// Visit a class along with its renamed class
+    #[inline]
+    fn visit_class(&mut self, _original: &ReferenceType, _renamed: &ReferenceType) {}
+    /// Visit a field along with its renamed data
+    #[inline]
+    fn visit_field(&mut self, _original: &FieldData, _renamed: &FieldData) {}
+    /// Visit a method along with its renamed data
+    #[inline]
+    fn visit_method(&mut self, _original: &MethodData, _renamed: &MethodData) {}
+}
+/// Collect the visited entries into the mappings
+impl MappingsVisitor for SimpleMappings {
+    #[inline]
+    fn visit_class(&mut self, original: &ReferenceType, renamed: &ReferenceType) {
+        self.set_remapped_class(original.clone(), renamed.clone());
+    }
+    #[inline]
+    fn visit_field(&mut self, original: &FieldData, renamed: &FieldData) {
+        self.set_field_name(original.clone(), renamed.name.clone());
+    }
+    #[inline]
+    fn visit_method(&mut self, original: &MethodData, renamed: &MethodData) {
+        self.set_method_name(original.clone(), renamed.name.clone());
+    }
+}
+impl<V: MappingsVisitor + ?Sized> MappingsVisitor for &mut V {
+    #[inline]
+    fn visit_class(&mut self, original: &ReferenceType, renamed: &ReferenceType) {
+        (**self).visit_class(original, renamed)
+    }
+    #[inline]
+    fn visit_field(&mut self, original: &FieldData, renamed: &FieldData) {
+        (**self).visit_field(original, renamed)
+    }
+    #[inline]
+    fn visit_method(&mut self, original: &MethodData, renamed: &MethodData) {
+        (**self).visit_method(original, renamed)
+    }
+}
@@ -1,11 +1,15 @@
 pub use crate::types::{TypeDescriptor, JavaType, ReferenceType, ArrayType, PrimitiveType};
-pub use crate::descriptor::{MethodSignature, MethodData, FieldData};
-pub use crate::mappings::{Mappings, IterableMappings, MutableMappings, FrozenMappings, SimpleMappings};
+pub use crate::descriptor::{MethodSignature, MethodData, FieldData, ParameterData};
+pub use crate::mappings::{Mappings, IterableMappings, MutableMappings, FrozenMappings, SimpleMappings, BorrowedMappings};
 pub use crate::mappings::transformer::{TypeTransformer, MapClass};
 pub use crate::format::{
-    MappingsFormat, MappingsParseError,
+    MappingsFormat, MappingsParseError, ParseOptions,
     csrg::CompactSrgMappingsFormat,
-    srg::SrgMappingsFormat,
-    tsrg::TabSrgMappingsFormat
+    srg::{SrgMappingsFormat, ColonSrgMappingsFormat},
+    tsrg::{TabSrgMappingsFormat, TabSrg2MappingsFormat},
+    tiny::{TinyV1MappingsFormat, TinyV2MappingsFormat},
+    proguard::ProguardMappingsFormat,
+    enigma::EnigmaMappingsFormat,
+    json::JsonMappingsFormat
 };
 pub use crate::chain;
\ No newline at end of file
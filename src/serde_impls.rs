@@ -0,0 +1,223 @@
+//! Implementations of serde's `Serialize` and `Deserialize`, behind the `serde` feature.
+//!
+//! Types and signatures are given by their descriptors (or internal names for classes),
+//! so the representation stays compact and matches the mapping formats.
+use std::fmt;
+
+use serde::ser::{Serialize, Serializer, SerializeStruct};
+use serde::de::{self, Deserialize, Deserializer, Visitor, MapAccess, SeqAccess, Unexpected};
+
+use crate::prelude::*;
+
+impl Serialize for ReferenceType {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.internal_name())
+    }
+}
+impl<'de> Deserialize<'de> for ReferenceType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        if name.is_empty() || name.contains(['.', ';', '[']) {
+            return Err(de::Error::invalid_value(Unexpected::Str(&name), &"an internal name"))
+        }
+        Ok(ReferenceType::from_internal_name(&name))
+    }
+}
+impl Serialize for TypeDescriptor {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.descriptor())
+    }
+}
+impl<'de> Deserialize<'de> for TypeDescriptor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let descriptor = String::deserialize(deserializer)?;
+        TypeDescriptor::parse_descriptor(&descriptor)
+            .ok_or_else(|| de::Error::invalid_value(Unexpected::Str(&descriptor), &"a type descriptor"))
+    }
+}
+impl Serialize for MethodSignature {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.descriptor())
+    }
+}
+impl<'de> Deserialize<'de> for MethodSignature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let descriptor = String::deserialize(deserializer)?;
+        MethodSignature::parse_descriptor(&descriptor)
+            .ok_or_else(|| de::Error::invalid_value(Unexpected::Str(&descriptor), &"a method descriptor"))
+    }
+}
+const FIELD_DATA_FIELDS: &[&str] = &["declaring_type", "name", "descriptor"];
+/// Fields are given as a struct with their declaring type and name,
+/// along with their descriptor if we know their type
+impl Serialize for FieldData {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("FieldData", FIELD_DATA_FIELDS.len())?;
+        state.serialize_field("declaring_type", self.declaring_type())?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("descriptor", &self.descriptor())?;
+        state.end()
+    }
+}
+impl<'de> Deserialize<'de> for FieldData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("FieldData", FIELD_DATA_FIELDS, FieldDataVisitor)
+    }
+}
+struct FieldDataVisitor;
+impl<'de> Visitor<'de> for FieldDataVisitor {
+    type Value = FieldData;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a field with a declaring type, name and optional descriptor")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<FieldData, A::Error> {
+        let declaring_type: ReferenceType = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let name: String = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let descriptor: Option<TypeDescriptor> = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+        let mut field = FieldData::new(name, declaring_type);
+        field.set_descriptor(descriptor);
+        Ok(field)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FieldData, A::Error> {
+        let mut declaring_type: Option<ReferenceType> = None;
+        let mut name: Option<String> = None;
+        let mut descriptor: Option<Option<TypeDescriptor>> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match &*key {
+                "declaring_type" if declaring_type.is_none() => declaring_type = Some(map.next_value()?),
+                "name" if name.is_none() => name = Some(map.next_value()?),
+                "descriptor" if descriptor.is_none() => descriptor = Some(map.next_value()?),
+                "declaring_type" | "name" | "descriptor" => return Err(de::Error::custom(format!("duplicate field `{}`", key))),
+                _ => return Err(de::Error::unknown_field(&key, FIELD_DATA_FIELDS))
+            }
+        }
+        let mut field = FieldData::new(
+            name.ok_or_else(|| de::Error::missing_field("name"))?,
+            declaring_type.ok_or_else(|| de::Error::missing_field("declaring_type"))?
+        );
+        // Like an `Option` field, a missing descriptor means we don't know the type
+        field.set_descriptor(descriptor.flatten());
+        Ok(field)
+    }
+}
+/// Methods are given as a `(declaring_type, name, signature)` tuple
+impl Serialize for MethodData {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.declaring_type(), &self.name, self.signature()).serialize(serializer)
+    }
+}
+impl<'de> Deserialize<'de> for MethodData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (declaring_type, name, signature) = <(ReferenceType, String, MethodSignature)>::deserialize(deserializer)?;
+        Ok(MethodData::new(name, declaring_type, signature))
+    }
+}
+
+const MAPPINGS_FIELDS: &[&str] = &["classes", "fields", "methods"];
+/// Mappings are given as a map of the renamed classes,
+/// along with the renamed member names paired with each original member.
+///
+/// Since most formats only support string keys, the members are given as sequences of pairs.
+/// The renamed member data is implied by the classes, so it's rebuilt by `FrozenMappings::new`.
+impl Serialize for FrozenMappings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fields: Vec<(&FieldData, &str)> = self.fields()
+            .map(|(original, renamed)| (original, &*renamed.name))
+            .collect();
+        let methods: Vec<(&MethodData, &str)> = self.methods()
+            .map(|(original, renamed)| (original, &*renamed.name))
+            .collect();
+        let mut state = serializer.serialize_struct("FrozenMappings", MAPPINGS_FIELDS.len())?;
+        state.serialize_field("classes", &ClassesRef(self))?;
+        state.serialize_field("fields", &fields)?;
+        state.serialize_field("methods", &methods)?;
+        state.end()
+    }
+}
+struct ClassesRef<'a>(&'a FrozenMappings);
+impl<'a> Serialize for ClassesRef<'a> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.classes())
+    }
+}
+/// The classes, deserialized from a map while preserving their order
+struct ClassEntries(Vec<(ReferenceType, ReferenceType)>);
+impl<'de> Deserialize<'de> for ClassEntries {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(ClassEntriesVisitor)
+    }
+}
+struct ClassEntriesVisitor;
+impl<'de> Visitor<'de> for ClassEntriesVisitor {
+    type Value = ClassEntries;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of original classes to renamed classes")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<ClassEntries, A::Error> {
+        let mut result = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(entry) = map.next_entry()? {
+            result.push(entry);
+        }
+        Ok(ClassEntries(result))
+    }
+}
+type FieldEntries = Vec<(FieldData, String)>;
+type MethodEntries = Vec<(MethodData, String)>;
+impl<'de> Deserialize<'de> for FrozenMappings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("FrozenMappings", MAPPINGS_FIELDS, MappingsVisitor)
+    }
+}
+struct MappingsVisitor;
+impl<'de> Visitor<'de> for MappingsVisitor {
+    type Value = FrozenMappings;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("mappings with classes, fields and methods")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<FrozenMappings, A::Error> {
+        let classes: ClassEntries = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let fields: FieldEntries = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let methods: MethodEntries = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+        Ok(FrozenMappings::new(classes.0, fields, methods))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FrozenMappings, A::Error> {
+        let mut classes: Option<ClassEntries> = None;
+        let mut fields: Option<FieldEntries> = None;
+        let mut methods: Option<MethodEntries> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match &*key {
+                "classes" if classes.is_none() => classes = Some(map.next_value()?),
+                "fields" if fields.is_none() => fields = Some(map.next_value()?),
+                "methods" if methods.is_none() => methods = Some(map.next_value()?),
+                "classes" | "fields" | "methods" => return Err(de::Error::custom(format!("duplicate field `{}`", key))),
+                _ => return Err(de::Error::unknown_field(&key, MAPPINGS_FIELDS))
+            }
+        }
+        Ok(FrozenMappings::new(
+            classes.ok_or_else(|| de::Error::missing_field("classes"))?.0,
+            fields.ok_or_else(|| de::Error::missing_field("fields"))?,
+            methods.ok_or_else(|| de::Error::missing_field("methods"))?
+        ))
+    }
+}
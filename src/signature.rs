@@ -0,0 +1,561 @@
+//! Java generic signatures, as given by a class file's `Signature` attribute.
+//!
+//! Unlike descriptors, these include type parameters like `<T:Ljava/lang/Object;>`,
+//! type variables like `TT;` and parameterized types like `Ljava/util/List<+TT;>;`.
+use crate::prelude::*;
+use crate::utils::*;
+
+/// The generic signature of a class, method or field
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GenericSignature {
+    Class(ClassSignature),
+    Method(GenericMethodSignature),
+    Field(TypeSignature)
+}
+impl GenericSignature {
+    /// Give the signature string, exactly like the one we were parsed from
+    pub fn signature(&self) -> String {
+        let mut result = String::new();
+        match *self {
+            GenericSignature::Class(ref signature) => signature.write_signature(&mut result),
+            GenericSignature::Method(ref signature) => signature.write_signature(&mut result),
+            GenericSignature::Field(ref signature) => signature.write_signature(&mut result),
+        }
+        result
+    }
+    /// Give every class referenced by the signature, including the classes of type arguments and bounds.
+    ///
+    /// Inner classes of parameterized types are given by their full name, like `java/util/Map$Entry`.
+    pub fn referenced_classes(&self) -> Vec<ReferenceType> {
+        let mut result = Vec::new();
+        match *self {
+            GenericSignature::Class(ref signature) => {
+                visit_parameters(&signature.type_parameters, &mut result);
+                signature.superclass.visit_classes(&mut result);
+                for interface in &signature.interfaces {
+                    interface.visit_classes(&mut result);
+                }
+            },
+            GenericSignature::Method(ref signature) => {
+                visit_parameters(&signature.type_parameters, &mut result);
+                let types = signature.parameters.iter()
+                    .chain(&signature.return_type)
+                    .chain(&signature.throws);
+                for signature in types {
+                    signature.visit_classes(&mut result);
+                }
+            },
+            GenericSignature::Field(ref signature) => signature.visit_classes(&mut result),
+        }
+        result
+    }
+}
+/// Parse a class, method or field signature.
+///
+/// A signature with a single class type like `Ljava/util/List<TT;>;` could be either a field or a class
+/// that only gives its superclass, so it's parsed as a field.
+impl SimpleParse for GenericSignature {
+    fn parse(parser: &mut SimpleParser) -> Result<Self, SimpleParseError> {
+        let type_parameters = parse_type_parameters(parser)?;
+        if parser.remaining().starts_with('(') {
+            return Ok(GenericSignature::Method(GenericMethodSignature::parse_rest(parser, type_parameters)?))
+        }
+        let first = parser.parse::<TypeSignature>()?;
+        if type_parameters.is_empty() && parser.is_finished() && !matches!(first, TypeSignature::Base(_)) {
+            return Ok(GenericSignature::Field(first))
+        }
+        let superclass = match first {
+            TypeSignature::Class(superclass) => superclass,
+            _ => return Err(parser.error_with("Expected a superclass".into()))
+        };
+        let interfaces = parse_interfaces(parser)?;
+        Ok(GenericSignature::Class(ClassSignature { type_parameters, superclass, interfaces }))
+    }
+}
+impl MapClass for GenericSignature {
+    fn maybe_transform_class<T: TypeTransformer>(&self, transformer: T) -> Option<Self> {
+        let transformer: &dyn TypeTransformer = &transformer;
+        Some(match *self {
+            GenericSignature::Class(ref signature) => GenericSignature::Class(signature.transform_class(transformer)),
+            GenericSignature::Method(ref signature) => GenericSignature::Method(signature.transform_class(transformer)),
+            GenericSignature::Field(ref signature) => GenericSignature::Field(signature.transform_class(transformer)),
+        })
+    }
+}
+
+/// The signature of a generic class, or a class with a generic supertype
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub superclass: ClassTypeSignature,
+    pub interfaces: Vec<ClassTypeSignature>
+}
+impl ClassSignature {
+    fn write_signature(&self, result: &mut String) {
+        write_parameters(&self.type_parameters, result);
+        self.superclass.write_signature(result);
+        for interface in &self.interfaces {
+            interface.write_signature(result);
+        }
+    }
+}
+impl SimpleParse for ClassSignature {
+    fn parse(parser: &mut SimpleParser) -> Result<Self, SimpleParseError> {
+        let type_parameters = parse_type_parameters(parser)?;
+        let superclass = parser.parse()?;
+        let interfaces = parse_interfaces(parser)?;
+        Ok(ClassSignature { type_parameters, superclass, interfaces })
+    }
+}
+impl MapClass for ClassSignature {
+    fn maybe_transform_class<T: TypeTransformer>(&self, transformer: T) -> Option<Self> {
+        let transformer: &dyn TypeTransformer = &transformer;
+        Some(ClassSignature {
+            type_parameters: self.type_parameters.iter().map(|parameter| parameter.transform_class(transformer)).collect(),
+            superclass: self.superclass.transform_class(transformer),
+            interfaces: self.interfaces.iter().map(|interface| interface.transform_class(transformer)).collect()
+        })
+    }
+}
+
+/// The signature of a generic method, or a method with generic parameters
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GenericMethodSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub parameters: Vec<TypeSignature>,
+    /// The return type, or `None` if the method returns `void`
+    pub return_type: Option<TypeSignature>,
+    /// The exceptions thrown by the method, which are either classes or type variables
+    pub throws: Vec<TypeSignature>
+}
+impl GenericMethodSignature {
+    fn parse_rest(parser: &mut SimpleParser, type_parameters: Vec<TypeParameter>) -> Result<Self, SimpleParseError> {
+        parser.expect('(')?;
+        let mut parameters = Vec::new();
+        while !parser.remaining().starts_with(')') {
+            parameters.push(parser.parse()?);
+        }
+        parser.expect(')')?;
+        let return_type = if parser.remaining().starts_with('V') {
+            parser.skip(1);
+            None
+        } else {
+            Some(parser.parse()?)
+        };
+        let mut throws = Vec::new();
+        while parser.remaining().starts_with('^') {
+            parser.skip(1);
+            match parser.parse()? {
+                TypeSignature::Array(_) | TypeSignature::Base(_) => return Err(parser.error_with("Expected a thrown class".into())),
+                thrown => throws.push(thrown)
+            }
+        }
+        Ok(GenericMethodSignature { type_parameters, parameters, return_type, throws })
+    }
+    fn write_signature(&self, result: &mut String) {
+        write_parameters(&self.type_parameters, result);
+        result.push('(');
+        for parameter in &self.parameters {
+            parameter.write_signature(result);
+        }
+        result.push(')');
+        match self.return_type {
+            Some(ref return_type) => return_type.write_signature(result),
+            None => result.push('V')
+        }
+        for thrown in &self.throws {
+            result.push('^');
+            thrown.write_signature(result);
+        }
+    }
+}
+impl SimpleParse for GenericMethodSignature {
+    fn parse(parser: &mut SimpleParser) -> Result<Self, SimpleParseError> {
+        let type_parameters = parse_type_parameters(parser)?;
+        Self::parse_rest(parser, type_parameters)
+    }
+}
+impl MapClass for GenericMethodSignature {
+    fn maybe_transform_class<T: TypeTransformer>(&self, transformer: T) -> Option<Self> {
+        let transformer: &dyn TypeTransformer = &transformer;
+        let transform = |signature: &TypeSignature| signature.transform_class(transformer);
+        Some(GenericMethodSignature {
+            type_parameters: self.type_parameters.iter().map(|parameter| parameter.transform_class(transformer)).collect(),
+            parameters: self.parameters.iter().map(transform).collect(),
+            return_type: self.return_type.as_ref().map(transform),
+            throws: self.throws.iter().map(transform).collect()
+        })
+    }
+}
+
+/// A formal type parameter like `T:Ljava/lang/Object;`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeParameter {
+    pub name: String,
+    /// The class the parameter must extend, which is omitted if the parameter only has interface bounds
+    pub class_bound: Option<TypeSignature>,
+    pub interface_bounds: Vec<TypeSignature>
+}
+impl SimpleParse for TypeParameter {
+    fn parse(parser: &mut SimpleParser) -> Result<Self, SimpleParseError> {
+        let name = parser.take_until(|c| c == ':' || c == '>');
+        if name.is_empty() {
+            return Err(parser.expected("a type parameter"))
+        }
+        parser.expect(':')?;
+        let class_bound = if parser.remaining().starts_with(':') {
+            None
+        } else {
+            Some(parser.parse()?)
+        };
+        let mut interface_bounds = Vec::new();
+        while parser.remaining().starts_with(':') {
+            parser.skip(1);
+            interface_bounds.push(parser.parse()?);
+        }
+        Ok(TypeParameter { name: name.into(), class_bound, interface_bounds })
+    }
+}
+impl MapClass for TypeParameter {
+    fn maybe_transform_class<T: TypeTransformer>(&self, transformer: T) -> Option<Self> {
+        let transformer: &dyn TypeTransformer = &transformer;
+        Some(TypeParameter {
+            name: self.name.clone(),
+            class_bound: self.class_bound.as_ref().map(|bound| bound.transform_class(transformer)),
+            interface_bounds: self.interface_bounds.iter().map(|bound| bound.transform_class(transformer)).collect()
+        })
+    }
+}
+
+/// A type in a generic signature
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeSignature {
+    /// A primitive type, which is never `void`
+    Base(PrimitiveType),
+    Class(ClassTypeSignature),
+    /// A type variable like `TT;`, given by its name
+    TypeVariable(String),
+    Array(Box<TypeSignature>)
+}
+impl TypeSignature {
+    fn write_signature(&self, result: &mut String) {
+        match *self {
+            TypeSignature::Base(primitive) => result.push_str(primitive.descriptor()),
+            TypeSignature::Class(ref class) => class.write_signature(result),
+            TypeSignature::TypeVariable(ref name) => {
+                result.push('T');
+                result.push_str(name);
+                result.push(';');
+            },
+            TypeSignature::Array(ref element) => {
+                result.push('[');
+                element.write_signature(result);
+            }
+        }
+    }
+    fn visit_classes(&self, result: &mut Vec<ReferenceType>) {
+        match *self {
+            TypeSignature::Class(ref class) => class.visit_classes(result),
+            TypeSignature::Array(ref element) => element.visit_classes(result),
+            TypeSignature::Base(_) | TypeSignature::TypeVariable(_) => {}
+        }
+    }
+}
+impl SimpleParse for TypeSignature {
+    fn parse(parser: &mut SimpleParser) -> Result<Self, SimpleParseError> {
+        Ok(match parser.peek()? {
+            'L' => TypeSignature::Class(parser.parse()?),
+            'T' => {
+                parser.skip(1);
+                let name = parser.take_until(|c| c == ';');
+                if name.is_empty() {
+                    return Err(parser.expected("a type variable"))
+                }
+                parser.expect(';')?;
+                TypeSignature::TypeVariable(name.into())
+            },
+            '[' => {
+                parser.skip(1);
+                TypeSignature::Array(Box::new(parser.parse()?))
+            },
+            'V' => return Err(parser.expected("a type signature")),
+            _ => TypeSignature::Base(parser.parse()?)
+        })
+    }
+}
+impl MapClass for TypeSignature {
+    fn maybe_transform_class<T: TypeTransformer>(&self, transformer: T) -> Option<Self> {
+        let transformer: &dyn TypeTransformer = &transformer;
+        match *self {
+            TypeSignature::Class(ref class) => Some(TypeSignature::Class(class.transform_class(transformer))),
+            TypeSignature::Array(ref element) => Some(TypeSignature::Array(Box::new(element.transform_class(transformer)))),
+            TypeSignature::Base(_) | TypeSignature::TypeVariable(_) => None
+        }
+    }
+}
+
+/// A possibly parameterized class like `Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassTypeSignature {
+    /// The outermost class
+    pub class: ReferenceType,
+    pub type_arguments: Vec<TypeArgument>,
+    /// The inner classes of a parameterized outer class, which are given by their simple names
+    pub inner_classes: Vec<InnerClassSignature>
+}
+impl ClassTypeSignature {
+    /// Give the full name of the innermost class, like `java/util/Map$Entry`
+    pub fn full_class(&self) -> ReferenceType {
+        self.inner_classes.iter().fold(self.class.clone(), |outer, inner| inner.full_class(&outer))
+    }
+    fn write_signature(&self, result: &mut String) {
+        result.push('L');
+        result.push_str(self.class.internal_name());
+        write_arguments(&self.type_arguments, result);
+        for inner in &self.inner_classes {
+            result.push('.');
+            result.push_str(&inner.name);
+            write_arguments(&inner.type_arguments, result);
+        }
+        result.push(';');
+    }
+    fn visit_classes(&self, result: &mut Vec<ReferenceType>) {
+        let mut class = self.class.clone();
+        visit_arguments(&self.type_arguments, result);
+        for inner in &self.inner_classes {
+            let inner_class = inner.full_class(&class);
+            result.push(class);
+            class = inner_class;
+            visit_arguments(&inner.type_arguments, result);
+        }
+        result.push(class);
+    }
+}
+impl SimpleParse for ClassTypeSignature {
+    fn parse(parser: &mut SimpleParser) -> Result<Self, SimpleParseError> {
+        parser.expect('L')?;
+        let name = parser.take_until(|c| c == '<' || c == '.' || c == ';');
+        if name.is_empty() {
+            return Err(parser.expected("a class name"))
+        }
+        let class = ReferenceType::from_internal_name(name);
+        let type_arguments = parse_type_arguments(parser)?;
+        let mut inner_classes = Vec::new();
+        while parser.remaining().starts_with('.') {
+            parser.skip(1);
+            let name = parser.take_until(|c| c == '<' || c == '.' || c == ';');
+            if name.is_empty() {
+                return Err(parser.expected("an inner class name"))
+            }
+            let type_arguments = parse_type_arguments(parser)?;
+            inner_classes.push(InnerClassSignature { name: name.into(), type_arguments });
+        }
+        parser.expect(';')?;
+        Ok(ClassTypeSignature { class, type_arguments, inner_classes })
+    }
+}
+impl MapClass for ClassTypeSignature {
+    /// Remap the outer class and each of its inner classes,
+    /// which are renamed using their full name
+    fn maybe_transform_class<T: TypeTransformer>(&self, transformer: T) -> Option<Self> {
+        let transformer: &dyn TypeTransformer = &transformer;
+        let transform = |argument: &TypeArgument| argument.transform_class(transformer);
+        let mut original = self.class.clone();
+        let mut inner_classes = Vec::with_capacity(self.inner_classes.len());
+        for inner in &self.inner_classes {
+            original = inner.full_class(&original);
+            let remapped = original.transform_class(transformer);
+            inner_classes.push(InnerClassSignature {
+                name: remapped.simple_inner_name().into(),
+                type_arguments: inner.type_arguments.iter().map(transform).collect()
+            });
+        }
+        Some(ClassTypeSignature {
+            class: self.class.transform_class(transformer),
+            type_arguments: self.type_arguments.iter().map(transform).collect(),
+            inner_classes
+        })
+    }
+}
+
+/// An inner class of a parameterized class, like the `.Entry<TK;TV;>` in `Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InnerClassSignature {
+    /// The simple name of the inner class, relative to its outer class
+    pub name: String,
+    pub type_arguments: Vec<TypeArgument>
+}
+impl InnerClassSignature {
+    #[inline]
+    fn full_class(&self, outer: &ReferenceType) -> ReferenceType {
+        ReferenceType::from_internal_name(&format!("{}${}", outer.internal_name(), self.name))
+    }
+}
+
+/// A type argument of a parameterized class
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeArgument {
+    /// The unbounded wildcard `*`
+    Wildcard,
+    /// An upper bounded wildcard like `+TT;`
+    Extends(TypeSignature),
+    /// A lower bounded wildcard like `-TT;`
+    Super(TypeSignature),
+    Exact(TypeSignature)
+}
+impl SimpleParse for TypeArgument {
+    fn parse(parser: &mut SimpleParser) -> Result<Self, SimpleParseError> {
+        Ok(match parser.peek()? {
+            '*' => {
+                parser.skip(1);
+                TypeArgument::Wildcard
+            },
+            '+' => {
+                parser.skip(1);
+                TypeArgument::Extends(parser.parse()?)
+            },
+            '-' => {
+                parser.skip(1);
+                TypeArgument::Super(parser.parse()?)
+            },
+            _ => TypeArgument::Exact(parser.parse()?)
+        })
+    }
+}
+impl MapClass for TypeArgument {
+    fn maybe_transform_class<T: TypeTransformer>(&self, transformer: T) -> Option<Self> {
+        let transformer: &dyn TypeTransformer = &transformer;
+        Some(match *self {
+            TypeArgument::Wildcard => return None,
+            TypeArgument::Extends(ref bound) => TypeArgument::Extends(bound.maybe_transform_class(transformer)?),
+            TypeArgument::Super(ref bound) => TypeArgument::Super(bound.maybe_transform_class(transformer)?),
+            TypeArgument::Exact(ref argument) => TypeArgument::Exact(argument.maybe_transform_class(transformer)?),
+        })
+    }
+}
+
+fn parse_type_parameters(parser: &mut SimpleParser) -> Result<Vec<TypeParameter>, SimpleParseError> {
+    let mut result = Vec::new();
+    if !parser.remaining().starts_with('<') { return Ok(result) }
+    parser.skip(1);
+    loop {
+        result.push(parser.parse()?);
+        if parser.remaining().starts_with('>') {
+            parser.skip(1);
+            return Ok(result)
+        }
+    }
+}
+fn parse_type_arguments(parser: &mut SimpleParser) -> Result<Vec<TypeArgument>, SimpleParseError> {
+    let mut result = Vec::new();
+    if !parser.remaining().starts_with('<') { return Ok(result) }
+    parser.skip(1);
+    loop {
+        result.push(parser.parse()?);
+        if parser.remaining().starts_with('>') {
+            parser.skip(1);
+            return Ok(result)
+        }
+    }
+}
+fn parse_interfaces(parser: &mut SimpleParser) -> Result<Vec<ClassTypeSignature>, SimpleParseError> {
+    let mut result = Vec::new();
+    while !parser.is_finished() {
+        result.push(parser.parse()?);
+    }
+    Ok(result)
+}
+fn write_parameters(parameters: &[TypeParameter], result: &mut String) {
+    if parameters.is_empty() { return }
+    result.push('<');
+    for parameter in parameters {
+        result.push_str(&parameter.name);
+        result.push(':');
+        if let Some(ref bound) = parameter.class_bound {
+            bound.write_signature(result);
+        }
+        for bound in &parameter.interface_bounds {
+            result.push(':');
+            bound.write_signature(result);
+        }
+    }
+    result.push('>');
+}
+fn write_arguments(arguments: &[TypeArgument], result: &mut String) {
+    if arguments.is_empty() { return }
+    result.push('<');
+    for argument in arguments {
+        match *argument {
+            TypeArgument::Wildcard => result.push('*'),
+            TypeArgument::Extends(ref bound) => {
+                result.push('+');
+                bound.write_signature(result);
+            },
+            TypeArgument::Super(ref bound) => {
+                result.push('-');
+                bound.write_signature(result);
+            },
+            TypeArgument::Exact(ref argument) => argument.write_signature(result)
+        }
+    }
+    result.push('>');
+}
+fn visit_parameters(parameters: &[TypeParameter], result: &mut Vec<ReferenceType>) {
+    for parameter in parameters {
+        for bound in parameter.class_bound.iter().chain(&parameter.interface_bounds) {
+            bound.visit_classes(result);
+        }
+    }
+}
+fn visit_arguments(arguments: &[TypeArgument], result: &mut Vec<ReferenceType>) {
+    for argument in arguments {
+        match *argument {
+            TypeArgument::Wildcard => {},
+            TypeArgument::Extends(ref bound) | TypeArgument::Super(ref bound) | TypeArgument::Exact(ref bound) => {
+                bound.visit_classes(result)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn parse(signature: &str) -> GenericSignature {
+        GenericSignature::parse_text(signature)
+            .unwrap_or_else(|e| panic!("Invalid signature {:?} at {}", signature, e.index))
+    }
+    #[test]
+    fn round_trip() {
+        for signature in &[
+            "Ljava/util/List<Ljava/lang/String;>;",
+            "TT;",
+            "[[I",
+            "Ljava/util/Map<*+TK;>.Entry<-TV;[Ljava/lang/Object;>;",
+            "<T:Ljava/lang/Object;>(TT;Ljava/util/List<+TT;>;I)TT;^Ljava/io/IOException;^TE;",
+            "()V",
+            "<LT::Ljava/lang/Comparable<TLT;>;:Ljava/io/Serializable;>Ljava/lang/Object;Ljava/lang/Runnable;",
+        ] {
+            let parsed = parse(signature);
+            assert_eq!(parsed.signature(), *signature);
+        }
+        for invalid in &["", "Ljava/util/List<>;", "(I", "TT", "V", "()V^[I", "<T>V"] {
+            assert!(GenericSignature::parse_text(invalid).is_err(), "Parsed {:?}", invalid);
+        }
+    }
+    #[test]
+    fn referenced_classes() {
+        let signature = parse("<T:Lobf4;>(Ljava/util/Map<TT;Lobfs;>.Entry<Lobfs$a;>;)TT;");
+        let classes: Vec<_> = signature.referenced_classes().iter()
+            .map(|class| class.internal_name().to_string())
+            .collect();
+        assert_eq!(classes, vec!["obf4", "obfs", "java/util/Map", "obfs$a", "java/util/Map$Entry"]);
+        match signature {
+            GenericSignature::Method(ref method) => match method.parameters[0] {
+                TypeSignature::Class(ref class) => assert_eq!(class.full_class().internal_name(), "java/util/Map$Entry"),
+                ref other => panic!("Unexpected parameter: {:?}", other)
+            },
+            ref other => panic!("Unexpected signature: {:?}", other)
+        }
+    }
+}
@@ -4,6 +4,8 @@ use std::hash::{Hash, Hasher};
 
 use indexmap::Equivalent;
 use lazy_static::lazy_static;
+use fnv::FnvHashSet;
+use parking_lot::Mutex;
 
 use crate::prelude::{TypeTransformer, MapClass};
 use crate::utils::*;
@@ -144,7 +146,7 @@ impl SimpleParse for PrimitiveType {
             'C' => PrimitiveType::Char,
             'Z' => PrimitiveType::Boolean,
             'V' => PrimitiveType::Void,
-            _ => return Err(parser.error())
+            _ => return Err(parser.expected("a type descriptor"))
         };
         parser.skip(1);
         Ok(primitive_type)
@@ -175,6 +177,83 @@ impl MapClass for TypeDescriptor {
         })
     }
 }
+impl TypeDescriptor {
+    /// Parse a single type from the start of the string, giving it along with the rest of the string.
+    ///
+    /// For example, `ILjava/lang/String;` gives `I` and `Ljava/lang/String;`.
+    pub fn parse_prefix(s: &str) -> Option<(TypeDescriptor, &str)> {
+        let mut parser = SimpleParser::new(s);
+        let result = parser.parse::<TypeDescriptor>().ok()?;
+        Some((result, parser.remaining()))
+    }
+    #[inline]
+    pub fn is_primitive(&self) -> bool {
+        matches!(*self, TypeDescriptor::Primitive(_))
+    }
+    #[inline]
+    pub fn is_reference(&self) -> bool {
+        matches!(*self, TypeDescriptor::Reference(_))
+    }
+    #[inline]
+    pub fn is_array(&self) -> bool {
+        matches!(*self, TypeDescriptor::Array(_))
+    }
+    /// Give the class this type is, or `None` if it's a primitive or an array
+    #[inline]
+    pub fn as_reference(&self) -> Option<&ReferenceType> {
+        match *self {
+            TypeDescriptor::Reference(ref obj) => Some(obj),
+            _ => None
+        }
+    }
+    #[inline]
+    pub fn as_array(&self) -> Option<&ArrayType> {
+        match *self {
+            TypeDescriptor::Array(ref array) => Some(array),
+            _ => None
+        }
+    }
+    /// Give an array of this type with the specified number of extra dimensions,
+    /// adding to the dimensions of an existing array instead of nesting it.
+    ///
+    /// Without any extra dimensions this gives back the same type, even for `void`.
+    /// Returns `None` for an array of `void`, since there aren't any.
+    pub fn array(self, extra_dims: usize) -> Option<TypeDescriptor> {
+        if extra_dims == 0 { return Some(self) }
+        Some(match self {
+            TypeDescriptor::Primitive(PrimitiveType::Void) => return None,
+            TypeDescriptor::Array(array) => ArrayType::new(array.dimensions() + extra_dims, array.element_type()),
+            element_type => ArrayType::new(extra_dims, element_type)
+        }.into_type_descriptor())
+    }
+    /// The class this type refers to, including the element type of arrays.
+    pub(crate) fn referenced_class(&self) -> Option<&ReferenceType> {
+        match self {
+            TypeDescriptor::Primitive(_) => None,
+            TypeDescriptor::Reference(obj) => Some(obj),
+            TypeDescriptor::Array(array) => match array.0.element_type {
+                ElementType::Reference(ref obj) => Some(obj),
+                ElementType::Primitive(_) => None
+            }
+        }
+    }
+    /// Append the readable name of this type (like `java.lang.String[]`) to the buffer.
+    pub(crate) fn push_name(&self, buffer: &mut String) {
+        let (element_type, dimensions) = match self {
+            TypeDescriptor::Primitive(prim) => (prim.internal_name(), 0),
+            TypeDescriptor::Reference(obj) => (obj.internal_name(), 0),
+            TypeDescriptor::Array(array) => (array.0.element_type.internal_name(), array.0.dimensions)
+        };
+        push_dotted(buffer, element_type);
+        for _ in 0..dimensions {
+            buffer.push_str("[]");
+        }
+    }
+}
+/// Append the internal name to the buffer, replacing slashes with dots
+fn push_dotted(buffer: &mut String, internal_name: &str) {
+    buffer.extend(internal_name.chars().map(|c| if c == '/' { '.' } else { c }));
+}
 // NOTE: Must use descriptor_hash so Borrow and hashmap will work correctly
 descriptor_hash!(TypeDescriptor, equals = false);
 impl<'a> JavaType<'a> for TypeDescriptor {
@@ -245,6 +324,20 @@ impl ArrayType {
         descriptor.push_str(element_type.descriptor());
         ArrayType(Arc::new(ArrayTypeInner { descriptor, dimensions, element_type }))
     }
+    /// The number of dimensions of this array, which is always at least one
+    #[inline]
+    pub fn dimensions(&self) -> usize {
+        self.0.dimensions
+    }
+    /// The type of this array's elements, which is never itself an array
+    /// since nested arrays are flattened into dimensions.
+    #[inline]
+    pub fn element_type(&self) -> TypeDescriptor {
+        match self.0.element_type {
+            ElementType::Primitive(prim) => TypeDescriptor::Primitive(prim),
+            ElementType::Reference(ref reference) => TypeDescriptor::Reference(reference.clone())
+        }
+    }
 }
 
 impl Equivalent<TypeDescriptor> for ArrayType {
@@ -356,6 +449,9 @@ impl ElementType {
     }
 }
 
+lazy_static! {
+    static ref INTERNED_TYPES: Mutex<FnvHashSet<Arc<str>>> = Mutex::new(FnvHashSet::default());
+}
 #[derive(Clone, Debug)]
 pub struct ReferenceType {
     // NOTE: Using an Arc makes this cheep to clone
@@ -366,12 +462,39 @@ impl ReferenceType {
         Self::from_internal_name(&name.replace('.', "/"))
     }
     pub fn from_internal_name(name: &str) -> ReferenceType {
+        ReferenceType { descriptor: Self::descriptor_for(name).into() }
+    }
+    /// Create a class from its descriptor, like `Lcom/foo/Bar;`
+    ///
+    /// Returns `None` if the descriptor isn't a class, including for arrays and primitives.
+    pub fn from_descriptor(descriptor: &str) -> Option<ReferenceType> {
+        let name = descriptor.strip_prefix('L')?.strip_suffix(';')?;
+        if name.is_empty() || name.contains(['.', ';', '[']) {
+            return None
+        }
+        Some(ReferenceType { descriptor: descriptor.into() })
+    }
+    /// Give the shared instance of the type with the specified internal name,
+    /// so identical types share their memory and usually compare by pointer.
+    ///
+    /// Interned types are never freed, so this is meant for names that are used over and over.
+    pub fn interned(name: &str) -> ReferenceType {
+        let descriptor = Self::descriptor_for(name);
+        let mut interned = INTERNED_TYPES.lock();
+        if let Some(existing) = interned.get(&*descriptor) {
+            return ReferenceType { descriptor: existing.clone() }
+        }
+        let descriptor: Arc<str> = descriptor.into();
+        interned.insert(descriptor.clone());
+        ReferenceType { descriptor }
+    }
+    fn descriptor_for(name: &str) -> String {
         assert!(!name.contains('.'));
         let mut descriptor = String::with_capacity(name.len() + 2);
         descriptor.push('L');
         descriptor.push_str(name);
         descriptor.push(';');
-        ReferenceType { descriptor: descriptor.into() }
+        descriptor
     }
     /// Give this package name as it's 'internal name'.
     ///
@@ -391,10 +514,52 @@ impl ReferenceType {
             ("", internal_name)
         }
     }
+    /// Append the dotted name of this class to the buffer.
+    #[inline]
+    pub(crate) fn push_name(&self, buffer: &mut String) {
+        push_dotted(buffer, self.internal_name())
+    }
     #[inline]
     pub fn simple_name(&self) -> &str {
         self.split_name().1
     }
+    /// Give the simple name of this class without the names of its outer classes.
+    ///
+    /// For example `org/spigotmc/XRay$Manager` will give `Manager`,
+    /// while classes that aren't inner classes just give their simple name.
+    #[inline]
+    pub fn simple_inner_name(&self) -> &str {
+        match self.inner_separator() {
+            Some(index) => &self.internal_name()[index + 1..],
+            None => self.simple_name()
+        }
+    }
+    /// Give the class this one is nested in, based on its name.
+    ///
+    /// For example `org/spigotmc/XRay$Manager$Entry` will give `org/spigotmc/XRay$Manager`.
+    #[inline]
+    pub fn outer_class(&self) -> Option<ReferenceType> {
+        self.inner_separator()
+            .map(|index| ReferenceType::from_internal_name(&self.internal_name()[..index]))
+    }
+    /// Check if this is an inner class, based on whether its simple name contains a `$`
+    #[inline]
+    pub fn is_inner(&self) -> bool {
+        self.inner_separator().is_some()
+    }
+    /// Find the index of the last `$` in the internal name, which separates the inner name from its outer class.
+    ///
+    /// We only split within the simple name, and names like `$Proxy` with nothing on one side aren't inner classes.
+    fn inner_separator(&self) -> Option<usize> {
+        let internal_name = self.internal_name();
+        let simple_start = internal_name.len() - self.simple_name().len();
+        let index = self.simple_name().rfind('$')?;
+        if index == 0 || index + 1 == self.simple_name().len() {
+            None
+        } else {
+            Some(simple_start + index)
+        }
+    }
 }
 impl MapClass for ReferenceType {
     #[inline]
@@ -423,7 +588,15 @@ impl Equivalent<TypeDescriptor> for ReferenceType {
         }
     }
 }
-descriptor_hash!(ReferenceType);
+impl PartialEq for ReferenceType {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        // Interned types can skip comparing their names
+        Arc::ptr_eq(&self.descriptor, &other.descriptor) || self.descriptor == other.descriptor
+    }
+}
+impl Eq for ReferenceType {}
+descriptor_hash!(ReferenceType, equals = false);
 impl<'a> JavaType<'a> for ReferenceType {
     type Name = String;
     type InternalName = &'a str;
@@ -473,4 +646,90 @@ mod test {
             "org/spigotmc/XRay[][]"
         );
     }
+    #[test]
+    fn test_inner_classes() {
+        let entry = ReferenceType::from_internal_name("org/spigotmc/XRay$Manager$Entry");
+        assert!(entry.is_inner());
+        assert_eq!(entry.simple_inner_name(), "Entry");
+        let manager = entry.outer_class().unwrap();
+        assert_eq!(manager.internal_name(), "org/spigotmc/XRay$Manager");
+        assert_eq!(manager.outer_class().unwrap().internal_name(), "org/spigotmc/XRay");
+        // A `$` in the package doesn't make a class inner
+        let outer = ReferenceType::from_internal_name("net/bad$package/Outer");
+        assert!(!outer.is_inner());
+        assert_eq!(outer.outer_class(), None);
+        assert_eq!(outer.simple_inner_name(), "Outer");
+        assert!(!ReferenceType::from_internal_name("$Proxy1").is_inner());
+    }
+    #[test]
+    fn test_type_predicates() {
+        let int = TypeDescriptor::parse_descriptor("I").unwrap();
+        let string = TypeDescriptor::parse_descriptor("Ljava/lang/String;").unwrap();
+        let strings = TypeDescriptor::parse_descriptor("[Ljava/lang/String;").unwrap();
+        assert!(int.is_primitive() && !int.is_reference() && !int.is_array());
+        assert!(string.is_reference() && !string.is_primitive() && !string.is_array());
+        assert!(strings.is_array() && !strings.is_reference() && !strings.is_primitive());
+        assert_eq!(string.as_reference().unwrap().internal_name(), "java/lang/String");
+        assert_eq!(int.as_reference(), None);
+        assert_eq!(strings.as_reference(), None);
+        assert_eq!(strings.as_array().unwrap().dimensions(), 1);
+        assert!(string.as_array().is_none());
+    }
+    #[test]
+    fn test_parse_prefix() {
+        let (first, rest) = TypeDescriptor::parse_prefix("ILjava/lang/String;[[D").unwrap();
+        assert_eq!(first, TypeDescriptor::parse_descriptor("I").unwrap());
+        let (second, rest) = TypeDescriptor::parse_prefix(rest).unwrap();
+        assert_eq!(second.descriptor(), "Ljava/lang/String;");
+        assert_eq!(rest, "[[D");
+        let (third, rest) = TypeDescriptor::parse_prefix(rest).unwrap();
+        assert_eq!((third.descriptor(), rest), ("[[D", ""));
+        for invalid in &["", "Q", "Ljava/lang/String", "[", ")V"] {
+            assert_eq!(TypeDescriptor::parse_prefix(invalid), None, "Parsed {:?}", invalid);
+        }
+    }
+    #[test]
+    fn test_array() {
+        let int = TypeDescriptor::parse_descriptor("I").unwrap();
+        assert_eq!(int.clone().array(0), Some(int.clone()));
+        assert_eq!(int.array(2).unwrap().descriptor(), "[[I");
+        let strings = TypeDescriptor::parse_descriptor("[Ljava/lang/String;").unwrap();
+        let nested = strings.array(2).unwrap();
+        assert_eq!(nested.descriptor(), "[[[Ljava/lang/String;");
+        assert_eq!(nested.as_array().unwrap().dimensions(), 3);
+        assert_eq!(nested, TypeDescriptor::parse_descriptor("[[[Ljava/lang/String;").unwrap());
+    }
+    #[test]
+    fn test_void_array() {
+        let void = TypeDescriptor::parse_descriptor("V").unwrap();
+        assert_eq!(void.clone().array(1), None);
+        assert_eq!(void.clone().array(0), Some(void));
+    }
+    #[test]
+    fn test_from_descriptor() {
+        let bar = ReferenceType::from_descriptor("Lcom/foo/Bar;").unwrap();
+        assert_eq!(bar, ReferenceType::from_internal_name("com/foo/Bar"));
+        assert_eq!(bar.descriptor(), "Lcom/foo/Bar;");
+        for invalid in &["", "L;", "Lcom/foo/Bar", "com/foo/Bar;", "[Lcom/foo/Bar;", "I", "Lcom.foo.Bar;", "LFoo;LBar;"] {
+            assert_eq!(ReferenceType::from_descriptor(invalid), None, "Parsed {:?}", invalid);
+        }
+    }
+    #[test]
+    fn test_interned() {
+        let first = ReferenceType::interned("org/spigotmc/XRay");
+        let second = ReferenceType::interned("org/spigotmc/XRay");
+        assert!(Arc::ptr_eq(&first.descriptor, &second.descriptor));
+        assert_eq!(first, ReferenceType::from_internal_name("org/spigotmc/XRay"));
+        assert_eq!(first.descriptor(), "Lorg/spigotmc/XRay;");
+        assert_ne!(first, ReferenceType::interned("org/spigotmc/XRay$Manager"));
+    }
+    #[test]
+    fn test_array_elements() {
+        let objects = ArrayType::parse_descriptor("[[Ljava/lang/Object;").unwrap();
+        assert_eq!(objects.dimensions(), 2);
+        assert_eq!(objects.element_type(), ReferenceType::from_name("java.lang.Object").into_type_descriptor());
+        let ints = ArrayType::new(1, PrimitiveType::Int);
+        assert_eq!(ints.dimensions(), 1);
+        assert_eq!(ints.element_type(), TypeDescriptor::Primitive(PrimitiveType::Int));
+    }
 }
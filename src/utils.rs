@@ -2,48 +2,92 @@ use std::hash::{Hash, BuildHasher};
 use std::collections::hash_map::RandomState;
 use std::ops::Deref;
 
-use indexmap::{IndexMap, map::Entry};
+use indexmap::{IndexMap, IndexSet, Equivalent, map::Entry};
 
 pub type FnvIndexMap<K, V> = IndexMap<K, V, ::fnv::FnvBuildHasher>;
+pub type FnvIndexSet<T> = IndexSet<T, ::fnv::FnvBuildHasher>;
 pub type FnvLruCache<K, V> = LruCache<K, V, ::fnv::FnvBuildHasher>;
 
+/// A cache which evicts its least recently used entries once it's full.
+///
+/// Entries are kept in order of use, from least to most recent.
+/// Only `get`, `insert` and `get_or_insert_with` count as a use,
+/// while reading the underlying map directly doesn't affect the order.
 #[derive(Clone, Debug)]
 pub struct LruCache<K: Eq + Hash, V, S: BuildHasher = RandomState> {
     capacity: usize,
     map: IndexMap<K, V, S>
 }
 impl<K: Eq + Hash, V, S: BuildHasher + Default> LruCache<K, V, S> {
+    /// Create a cache which holds at most the specified number of entries.
+    ///
+    /// A capacity of zero is treated as one,
+    /// since `get_or_insert_with` needs to keep the value it gives back.
     #[inline]
     pub fn new(capacity: usize) -> LruCache<K, V, S> {
+        let capacity = capacity.max(1);
         LruCache { capacity, map: IndexMap::with_capacity_and_hasher(capacity, Default::default()) }
     }
-    fn cleanup(&mut self) {
-        assert!(self.map.len() >= self.capacity);
+    /// Evict the oldest entries until we're within our capacity, returning how many were removed
+    fn cleanup(&mut self) -> usize {
+        if self.map.len() <= self.capacity { return 0 }
         let needed_removed = self.map.len() - self.capacity;
         let mut index = 0;
         self.map.retain(|_, _| {
             let should_remove = index < needed_removed;
             index += 1;
-            should_remove
+            !should_remove
         });
-        assert!(self.map.len() <= self.capacity);
+        debug_assert_eq!(self.map.len(), self.capacity);
+        needed_removed
+    }
+    /// Mark the entry at the specified index as the most recently used, giving its new index
+    fn touch(&mut self, index: usize) -> usize {
+        let last = self.map.len() - 1;
+        self.map.move_index(index, last);
+        last
+    }
+    /// Get the value of the specified key, marking it as the most recently used
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&V> {
+        let index = self.map.get_index_of(key)?;
+        let index = self.touch(index);
+        self.map.get_index(index).map(|(_, value)| value)
     }
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
-        let (old, cleanup) = match self.map.entry(key) {
-            Entry::Occupied(mut entry) => (Some(entry.insert(value)), false),
+        let old = match self.map.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let index = entry.index();
+                let old = entry.insert(value);
+                self.touch(index);
+                Some(old)
+            },
             Entry::Vacant(entry) => {
                 entry.insert(value);
-                (None, true)
+                None
             }
         };
-        if cleanup && self.map.len() >= self.capacity {
-            self.cleanup()
+        if old.is_none() {
+            self.cleanup();
         }
         old
     }
-    #[inline]
     pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, func: F) -> &V {
-        self.map.entry(key).or_insert_with(func)
+        let (mut index, inserted) = match self.map.entry(key) {
+            Entry::Occupied(entry) => {
+                let index = entry.index();
+                (self.touch(index), false)
+            },
+            Entry::Vacant(entry) => {
+                let index = entry.index();
+                entry.insert(func());
+                (index, true)
+            }
+        };
+        if inserted {
+            // The new entry is the newest, so it's never evicted
+            index -= self.cleanup();
+        }
+        self.map.get_index(index).unwrap().1
     }
 }
 impl<K: Eq + Hash, V, S: BuildHasher> Deref for LruCache<K, V, S> {
@@ -65,13 +109,49 @@ impl<'a> SimpleParser<'a> {
     pub fn new(text: &'a str) -> SimpleParser<'a> {
         SimpleParser { text, remaining: text }
     }
+    /// Create a parser for a single line of a file,
+    /// ignoring any trailing spaces or carriage return left over from a `\r\n` line ending.
+    #[inline]
+    pub fn for_line(line: &'a str) -> SimpleParser<'a> {
+        SimpleParser::new(line.trim_end_matches(&[' ', '\r'][..]))
+    }
     #[inline]
     pub fn peek(&mut self) -> Result<char, SimpleParseError> {
         self.remaining.chars().next().ok_or_else(|| self.error())
     }
     #[inline]
     pub fn peek_str(&mut self, size: usize) -> Result<&'a str, SimpleParseError> {
-        self.remaining.get(..size).ok_or_else(|| self.error())
+        self.remaining.get(..size).ok_or_else(|| self.expected(&format!("{} more characters", size)))
+    }
+    /// Peek at the next `n` characters, or `None` if there aren't that many left.
+    ///
+    /// Unlike `peek_str`, this counts characters instead of bytes,
+    /// so it never splits a multibyte character.
+    #[inline]
+    pub fn peek_chars(&self, n: usize) -> Option<&'a str> {
+        match self.remaining.char_indices().nth(n) {
+            Some((end, _)) => Some(&self.remaining[..end]),
+            None if self.remaining.chars().count() == n => Some(self.remaining),
+            None => None
+        }
+    }
+    /// Strip a trailing comment from the rest of the line, along with any whitespace before it.
+    ///
+    /// A comment is started by a `#` at the beginning of a token,
+    /// so a `#` in the middle of a name isn't treated as a comment.
+    pub fn strip_comment(&mut self) {
+        let mut starts_token = true;
+        let comment_start = self.remaining.char_indices().find(|&(_, c)| {
+            let is_comment = c == '#' && starts_token;
+            starts_token = c.is_whitespace();
+            is_comment
+        }).map(|(index, _)| index);
+        if let Some(index) = comment_start {
+            let stripped = self.remaining[..index].trim_end().len();
+            let removed = self.remaining.len() - stripped;
+            self.text = &self.text[..(self.text.len() - removed)];
+            self.remaining = &self.remaining[..stripped];
+        }
     }
     #[inline]
     pub fn take_until<F: FnMut(char) -> bool>(&mut self, func: F) -> &'a str {
@@ -89,15 +169,11 @@ impl<'a> SimpleParser<'a> {
         taken
     }
     pub fn expect(&mut self, expected: char) -> Result<(), SimpleParseError> {
-        let actual = self.peek().ok();
-        if actual == Some(expected) {
+        if self.remaining.starts_with(expected) {
             self.skip(expected.len_utf8());
             Ok(())
         } else {
-            Err(SimpleParseError {
-                index: self.current_index(),
-                reason: Some(format!("Expected {:?}, but got {:?}", expected, actual))
-            })
+            Err(self.expected(&format!("{:?}", expected)))
         }
     }
     pub fn expect_str(&mut self, s: &str) -> Result<(), SimpleParseError> {
@@ -105,7 +181,7 @@ impl<'a> SimpleParser<'a> {
             self.skip(s.len());
             Ok(())
         } else {
-            Err(self.error())
+            Err(self.expected(&format!("{:?}", s)))
         }
     }
     #[inline]
@@ -122,9 +198,33 @@ impl<'a> SimpleParser<'a> {
             Ok(s)
         }
     }
-    #[inline]
+    /// An error at the current position, describing the unexpected text found there
     pub fn error(&self) -> SimpleParseError {
-        SimpleParseError { index: self.current_index(), reason: None }
+        let reason = match self.next_token() {
+            Some(token) => format!("Unexpected {:?}", token),
+            None => "Unexpected end of line".into()
+        };
+        self.error_with(reason)
+    }
+    /// An error at the current position, describing what we expected to find instead
+    pub fn expected(&self, expected: &str) -> SimpleParseError {
+        let reason = match self.next_token() {
+            Some(token) => format!("Expected {}, but got {:?}", expected, token),
+            None => format!("Expected {}, but got the end of the line", expected)
+        };
+        self.error_with(reason)
+    }
+    /// An error for a line with the wrong number of columns, including the rest of the line
+    pub fn column_count_error(&self, expected: &str, actual: usize) -> SimpleParseError {
+        self.error_with(format!("Expected {} columns, but got {}: {:?}", expected, actual, self.remaining))
+    }
+    #[inline]
+    pub fn error_with(&self, reason: String) -> SimpleParseError {
+        SimpleParseError { index: self.current_index(), reason: Some(reason) }
+    }
+    /// The whitespace-separated token at the current position, if any
+    fn next_token(&self) -> Option<&'a str> {
+        self.remaining.split_whitespace().next()
     }
     #[inline]
     pub fn current_index(&self) -> usize {
@@ -144,7 +244,7 @@ impl<'a> SimpleParser<'a> {
         if self.remaining.is_empty() {
             Ok(())
         } else {
-            Err(self.error())
+            Err(self.error_with(format!("Unexpected trailing text: {:?}", self.remaining)))
         }
     }
 }
@@ -166,3 +266,48 @@ pub struct SimpleParseError {
     pub index: usize,
     pub reason: Option<String>
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn lru_capacity() {
+        let mut cache: LruCache<u32, u32> = LruCache::new(3);
+        for i in 0..10 {
+            cache.insert(i, i * 2);
+            assert!(cache.len() <= 3);
+        }
+        assert_eq!(cache.keys().cloned().collect::<Vec<_>>(), vec![7, 8, 9]);
+        let mut cache: LruCache<u32, u32> = LruCache::new(3);
+        for i in 0..10 {
+            assert_eq!(*cache.get_or_insert_with(i, || i * 2), i * 2);
+            assert!(cache.len() <= 3);
+        }
+        assert_eq!(*cache.get_or_insert_with(9, || unreachable!()), 18);
+        assert_eq!(cache.keys().cloned().collect::<Vec<_>>(), vec![7, 8, 9]);
+    }
+    #[test]
+    fn lru_recency() {
+        let mut cache: LruCache<&str, u32> = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        // Using the oldest entry keeps it from being evicted
+        assert_eq!(cache.get("a"), Some(&1));
+        cache.insert("c", 3);
+        assert_eq!(cache.keys().cloned().collect::<Vec<_>>(), vec!["a", "c"]);
+        assert_eq!(*cache.get_or_insert_with("a", || unreachable!()), 1);
+        cache.insert("d", 4);
+        assert_eq!(cache.keys().cloned().collect::<Vec<_>>(), vec!["a", "d"]);
+        assert_eq!(cache.insert("a", 5), Some(1));
+        cache.insert("e", 6);
+        assert_eq!(cache.keys().cloned().collect::<Vec<_>>(), vec!["a", "e"]);
+        assert_eq!(cache.get("b"), None);
+    }
+    #[test]
+    fn lru_zero_capacity() {
+        let mut cache: LruCache<u32, u32> = LruCache::new(0);
+        assert_eq!(cache.insert(1, 2), None);
+        assert_eq!(*cache.get_or_insert_with(2, || 4), 4);
+        assert_eq!(cache.len(), 1);
+    }
+}
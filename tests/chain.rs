@@ -1,6 +1,7 @@
 extern crate srglib;
 
 pub use srglib::prelude::*;
+use srglib::mappings::{ChainValidationError, ChainError};
 
 #[test]
 fn chain_simple() {
@@ -91,3 +92,155 @@ fn chain_complex() {
     ]).unwrap();
     expected.assert_equal(&actual)
 }
+
+#[test]
+fn validate_chain() {
+    let first = SrgMappingsFormat::parse_lines(&[
+        "CL: aa Entity",
+        "CL: ab Cow",
+    ]).unwrap();
+    let second = SrgMappingsFormat::parse_lines(&[
+        "CL: Entity net/minecraft/server/Entity",
+        "CL: Cow net/minecraft/server/Cow",
+    ]).unwrap();
+    let mismatched = SrgMappingsFormat::parse_lines(&[
+        "CL: aa net/minecraft/server/Entity",
+    ]).unwrap();
+    let collision = SrgMappingsFormat::parse_lines(&[
+        "CL: Entity Animal",
+        "CL: Cow Animal",
+    ]).unwrap();
+    FrozenMappings::validate_chain(&[&first, &second]).unwrap();
+    match FrozenMappings::validate_chain(&[&first, &mismatched]) {
+        Err(ChainValidationError::NamespaceMismatch { stage: 1, previous: 0 }) => {},
+        other => panic!("Unexpected result: {:?}", other)
+    }
+    match FrozenMappings::validate_chain(&[&first, &collision]) {
+        Err(ChainValidationError::NonInjective { stage: 1, ref renamed, .. }) if renamed == "Animal" => {},
+        other => panic!("Unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn chain_checked() {
+    let first = SrgMappingsFormat::parse_lines(&[
+        "CL: aa Entity",
+        "CL: ab ab",
+    ]).unwrap();
+    let second = SrgMappingsFormat::parse_lines(&[
+        "CL: Entity net/minecraft/server/Entity",
+        "CL: ab ab",
+    ]).unwrap();
+    let cycle = SrgMappingsFormat::parse_lines(&[
+        "CL: Entity aa",
+    ]).unwrap();
+    // Identity mappings aren't cycles
+    first.chain_checked(second.clone()).unwrap().assert_equal(&first.chain(second));
+    match first.chain_checked(cycle.clone()) {
+        Err(ChainError::Cycle { ref members }) if members == &["aa", "Entity"] => {},
+        other => panic!("Unexpected result: {:?}", other)
+    }
+    assert_eq!(
+        first.chain_checked(cycle.clone()).unwrap_err().to_string(),
+        "Rename cycle: aa -> Entity -> aa"
+    );
+}
+
+#[test]
+fn transform_class_names() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: a net/Minecraft/Server/Entity",
+        "CL: b net/minecraft/server/Cow",
+        "CL: c Root",
+        "MD: b/a (La;)V net/minecraft/server/Cow/love (Lnet/Minecraft/Server/Entity;)V"
+    ]).unwrap();
+    let actual = mappings.transform_class_names(|package, simple_name| {
+        if package.is_empty() {
+            None
+        } else {
+            Some(format!("Mc{}", simple_name))
+        }
+    });
+    let expected = SrgMappingsFormat::parse_lines(&[
+        "CL: a net/Minecraft/Server/McEntity",
+        "CL: b net/minecraft/server/McCow",
+        "CL: c Root",
+        "MD: b/a (La;)V net/minecraft/server/McCow/love (Lnet/Minecraft/Server/McEntity;)V"
+    ]).unwrap();
+    expected.assert_equal(&actual);
+    let expected = SrgMappingsFormat::parse_lines(&[
+        "CL: a net/minecraft/server/Entity",
+        "CL: b net/minecraft/server/Cow",
+        "CL: c Root",
+        "MD: b/a (La;)V net/minecraft/server/Cow/love (Lnet/minecraft/server/Entity;)V"
+    ]).unwrap();
+    expected.assert_equal(&mappings.normalize_package_case());
+}
+
+#[test]
+fn transform_class_packages() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: a net/minecraft/server/Entity",
+        "CL: b net/minecraft/server/Cow",
+        "CL: c Root",
+        "MD: b/a (La;)V net/minecraft/server/Cow/love (Lnet/minecraft/server/Entity;)V"
+    ]).unwrap();
+    let actual = mappings.transform_class_packages(|class| match class.internal_name() {
+        "net/minecraft/server/Entity" => Some("net/minecraft/entity".into()),
+        "Root" => Some("net/minecraft".into()),
+        _ => None
+    });
+    let expected = SrgMappingsFormat::parse_lines(&[
+        "CL: a net/minecraft/entity/Entity",
+        "CL: b net/minecraft/server/Cow",
+        "CL: c net/minecraft/Root",
+        "MD: b/a (La;)V net/minecraft/server/Cow/love (Lnet/minecraft/entity/Entity;)V"
+    ]).unwrap();
+    expected.assert_equal(&actual);
+}
+
+#[test]
+fn strip_renamed_prefix() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: a v1_16/net/minecraft/server/Entity",
+        "CL: b v1_16/net/minecraft/server/Cow",
+        "CL: c net/minecraft/Root",
+        "CL: d v1_16/",
+        "FD: a/a v1_16/net/minecraft/server/Entity/dead",
+        "MD: b/a (La;)V v1_16/net/minecraft/server/Cow/love (Lv1_16/net/minecraft/server/Entity;)V"
+    ]).unwrap();
+    let expected = SrgMappingsFormat::parse_lines(&[
+        "CL: a net/minecraft/server/Entity",
+        "CL: b net/minecraft/server/Cow",
+        "CL: c net/minecraft/Root",
+        "CL: d v1_16/",
+        "FD: a/a net/minecraft/server/Entity/dead",
+        "MD: b/a (La;)V net/minecraft/server/Cow/love (Lnet/minecraft/server/Entity;)V"
+    ]).unwrap();
+    expected.assert_equal(&mappings.strip_renamed_prefix("v1_16/"));
+}
+
+#[test]
+fn identity_classes() {
+    let known = FrozenMappings::identity_classes(vec![
+        ReferenceType::from_internal_name("aa"),
+        ReferenceType::from_internal_name("Unmapped"),
+    ]);
+    known.assert_equal(&known.inverted());
+    let renaming = SrgMappingsFormat::parse_lines(&[
+        "CL: aa Entity",
+        "CL: ab Cow",
+        "MD: ab/a (Laa;)V Cow/love (LEntity;)V"
+    ]).unwrap();
+    let expected = SrgMappingsFormat::parse_lines(&[
+        "CL: aa Entity",
+        "CL: Unmapped Unmapped",
+        "CL: ab Cow",
+        "MD: ab/a (Laa;)V Cow/love (LEntity;)V"
+    ]).unwrap();
+    expected.assert_equal(&known.chain(renaming.clone()));
+    // Chaining onto empty mappings only lacks the extra known classes
+    let mut without_unmapped = known.chain(renaming.clone()).rebuild();
+    without_unmapped.retain_classes(|original, _| original.internal_name() != "Unmapped");
+    FrozenMappings::empty().chain(renaming).assert_equal(&without_unmapped.frozen());
+}
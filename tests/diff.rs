@@ -0,0 +1,124 @@
+extern crate srglib;
+
+use srglib::prelude::*;
+use srglib::mappings::{MethodDrift, MappingEntry, ChangedEntry};
+
+#[test]
+fn method_drift() {
+    let old = SrgMappingsFormat::parse_lines(&[
+        "MD: a/a (I)V Foo/tick (I)V",
+        "MD: a/b ()V Foo/stop ()V",
+        "MD: a/c (I)V Foo/overloaded (I)V",
+        "MD: a/c (J)V Foo/overloaded (J)V",
+        "MD: a/d ()V Foo/removed ()V",
+    ]).unwrap();
+    let new = SrgMappingsFormat::parse_lines(&[
+        "MD: a/a (D)V Foo/tick (D)V",
+        "MD: a/b ()V Foo/stop ()V",
+        "MD: a/c (F)V Foo/overloaded (F)V",
+        "MD: a/c (D)V Foo/overloaded (D)V",
+        "MD: a/e ()V Foo/added ()V",
+    ]).unwrap();
+    let a = ReferenceType::from_internal_name("a");
+    let method = |name: &str, descriptor: &str| {
+        MethodData::new(name.into(), a.clone(), MethodSignature::from_descriptor(descriptor))
+    };
+    assert_eq!(old.diff_methods_by_name(&new), vec![
+        MethodDrift::Changed {
+            original: method("a", "(I)V"),
+            revised: method("a", "(D)V")
+        },
+        MethodDrift::Ambiguous {
+            original: vec![method("c", "(I)V"), method("c", "(J)V")],
+            revised: vec![method("c", "(F)V"), method("c", "(D)V")]
+        }
+    ]);
+}
+
+#[test]
+fn content_hash() {
+    let first = SrgMappingsFormat::parse_lines(&[
+        "CL: a Entity",
+        "CL: b World",
+        "FD: a/a Entity/dead",
+        "MD: b/a ()V World/tick ()V",
+    ]).unwrap();
+    let reordered = SrgMappingsFormat::parse_lines(&[
+        "MD: b/a ()V World/tick ()V",
+        "FD: a/a Entity/dead",
+        "CL: b World",
+        "CL: a Entity",
+    ]).unwrap();
+    let different = SrgMappingsFormat::parse_lines(&[
+        "CL: a Entity",
+        "CL: b World",
+        "FD: a/a Entity/alive",
+        "MD: b/a ()V World/tick ()V",
+    ]).unwrap();
+    assert!(first.content_eq(&reordered));
+    assert_eq!(first, reordered);
+    first.assert_equal(&reordered);
+    assert_ne!(first, different);
+    assert_eq!(first.content_hash(), reordered.content_hash());
+    assert!(!first.content_eq(&different));
+    assert_ne!(first.content_hash(), different.content_hash());
+}
+
+#[test]
+fn structured_diff() {
+    let old = SrgMappingsFormat::parse_lines(&[
+        "CL: a Entity",
+        "CL: b World",
+        "FD: a/a Entity/dead",
+        "FD: a/b Entity/removed",
+        "MD: b/a ()V World/tick ()V",
+    ]).unwrap();
+    let new = SrgMappingsFormat::parse_lines(&[
+        "CL: a Entity",
+        "CL: b Level",
+        "CL: c Player",
+        "FD: a/a Entity/alive",
+        "MD: b/a ()V Level/tick ()V",
+    ]).unwrap();
+    let diff = old.diff(&new);
+    let class = ReferenceType::from_internal_name;
+    assert_eq!(diff.classes.added, vec![MappingEntry { original: class("c"), renamed: class("Player") }]);
+    assert!(diff.classes.removed.is_empty());
+    assert_eq!(diff.classes.changed, vec![ChangedEntry { original: class("b"), old: class("World"), new: class("Level") }]);
+    assert!(diff.fields.added.is_empty());
+    assert_eq!(diff.fields.removed, vec![MappingEntry {
+        original: FieldData::new("b".into(), class("a")),
+        renamed: FieldData::new("removed".into(), class("Entity"))
+    }]);
+    assert_eq!(diff.fields.changed, vec![ChangedEntry {
+        original: FieldData::new("a".into(), class("a")),
+        old: FieldData::new("dead".into(), class("Entity")),
+        new: FieldData::new("alive".into(), class("Entity"))
+    }]);
+    // Moving the declaring type counts as a change, even with the same name
+    assert_eq!(diff.methods.changed.len(), 1);
+    assert_eq!(diff.methods.changed[0].new.declaring_type(), &class("Level"));
+    assert!(old.diff(&old).is_empty());
+}
+
+#[test]
+fn sorted() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "MD: b/a (I)V World/tick (I)V",
+        "CL: b World",
+        "FD: b/b World/time",
+        "MD: b/a ()V World/tick ()V",
+        "FD: a/a Entity/dead",
+        "CL: a Entity",
+    ]).unwrap();
+    let sorted = mappings.sorted();
+    assert!(sorted.content_eq(&mappings));
+    assert_eq!(SrgMappingsFormat::write_line_array(&sorted), vec![
+        "CL: a Entity",
+        "CL: b World",
+        "FD: a/a Entity/dead",
+        "FD: b/b World/time",
+        "MD: b/a ()V World/tick ()V",
+        "MD: b/a (I)V World/tick (I)V",
+    ]);
+}
@@ -1,6 +1,8 @@
 extern crate srglib;
 
 use srglib::prelude::*;
+use srglib::format::{MappingsLineProcessor, MappingsFormatKind, parse_try_all, parse_try_each};
+use srglib::format::mcp::{apply_mcp_csv, export_mcp_csv};
 
 const TEST_LINES: &[&str] = &[
     "CL: org/spigotmc/XRay net/techcable/xray/XRay",
@@ -34,6 +36,42 @@ const COMPACT_TEST_LINES: &[&str] = &[
     "org/spigotmc/XRay deobfuscate ([BLjava/util/Set;)I doAFunkyDance",
     "org/spigotmc/XRay$Manager aquire ()Lorg/spigotmc/XRay; get"
 ];
+const TINY_TEST_LINES: &[&str] = &[
+    "v1\tofficial\tnamed",
+    "# Comments and blank lines are skipped",
+    "",
+    "CLASS\torg/spigotmc/XRay\tnet/techcable/xray/XRay",
+    "CLASS\torg/spigotmc/XRay$Manager\tnet/techcable/xray/XRayManager",
+    "CLASS\torg/spigotmc/XRay$Injector\tnet/techcable/xray/injector/Injector",
+    "CLASS\torg/spigotmc/XRay$Injector$Manager\tnet/techcable/xray/injector/InjectorManager",
+    "CLASS\tobfs\tnet/techcable/minecraft/NoHax",
+    "CLASS\tobf4\tnet/techcable/minecraft/Player",
+    "FIELD\tobf4\tZ\ta\tdead",
+    "FIELD\tobf4\tI\tb\tblood",
+    "FIELD\tobf4\tD\tc\thealth",
+    "FIELD\tobf4\tF\td\tspeed",
+    "FIELD\torg/spigotmc/XRay$Injector$Manager\t[Lobf4;\ttaco\tseriousVariableName",
+    "METHOD\tobfs\t(Lobf4;ID)Z\ta\tisHacking",
+    "METHOD\torg/spigotmc/XRay\t([BLjava/util/Set;)I\tdeobfuscate\tdoAFunkyDance",
+    "METHOD\torg/spigotmc/XRay$Manager\t()Lorg/spigotmc/XRay;\taquire\tget\textraNamespace"
+];
+const TINY_TEST_LINES_V2: &[&str] = &[
+    "tiny\t2\t0\tofficial\tnamed",
+    "c\torg/spigotmc/XRay\tnet/techcable/xray/XRay",
+    "\tm\t([BLjava/util/Set;)I\tdeobfuscate\tdoAFunkyDance",
+    "c\torg/spigotmc/XRay$Manager\tnet/techcable/xray/XRayManager",
+    "\tm\t()Lorg/spigotmc/XRay;\taquire\tget",
+    "c\torg/spigotmc/XRay$Injector\tnet/techcable/xray/injector/Injector",
+    "c\torg/spigotmc/XRay$Injector$Manager\tnet/techcable/xray/injector/InjectorManager",
+    "\tf\t[Lobf4;\ttaco\tseriousVariableName",
+    "c\tobfs\tnet/techcable/minecraft/NoHax",
+    "\tm\t(Lobf4;ID)Z\ta\tisHacking",
+    "c\tobf4\tnet/techcable/minecraft/Player",
+    "\tf\tZ\ta\tdead",
+    "\tf\tI\tb\tblood",
+    "\tf\tD\tc\thealth",
+    "\tf\tF\td\tspeed",
+];
 
 #[test]
 fn compact_srg() {
@@ -41,12 +79,669 @@ fn compact_srg() {
     test_serialize::<CompactSrgMappingsFormat>(COMPACT_TEST_LINES);
 }
 
+#[test]
+fn compact_srg_field_types() {
+    let lines = &[
+        "obf4 net/techcable/minecraft/Player",
+        "obf4 a Z dead",
+        "obf4 b blood",
+        "obf4 c [Lobf4; targets",
+        "obfs a (Lobf4;ID)Z isHacking",
+    ];
+    let mappings = CompactSrgMappingsFormat::parse_lines(lines).unwrap();
+    let player = ReferenceType::from_internal_name("obf4");
+    let dead = FieldData::new("a".into(), player.clone())
+        .with_descriptor(PrimitiveType::Boolean.into_type_descriptor());
+    assert!(mappings.contains_field(&dead));
+    assert_eq!(mappings.remap_field(&FieldData::new("b".into(), player.clone())).name, "blood");
+    assert_eq!(mappings.remap_field(&FieldData::new("c".into(), player)).name, "targets");
+    assert_eq!(mappings.method_count(), 1);
+    assert_eq!(CompactSrgMappingsFormat::write_line_array(&mappings), lines.to_vec());
+    test_serialize::<CompactSrgMappingsFormat>(lines);
+    let text = lines.join("\n");
+    let borrowed = BorrowedMappings::parse_compact_srg(&text).unwrap();
+    assert_eq!(borrowed.frozen(), mappings.without_field_types());
+    assert!(CompactSrgMappingsFormat::parse_lines(&["obf4 a Q dead"]).is_err());
+}
+
 #[test]
 fn srg() {
     test_parse::<SrgMappingsFormat>(TEST_LINES);
     test_serialize::<SrgMappingsFormat>(TEST_LINES);
 }
 
+#[test]
+fn colon_srg() {
+    let colon_lines: Vec<String> = TEST_LINES.iter().map(|line| {
+        let mut parts: Vec<String> = line.split(' ').map(String::from).collect();
+        if parts[0] != "CL:" {
+            for part in parts.iter_mut().skip(1).filter(|part| !part.starts_with('(')) {
+                let separator = part.rfind('/').unwrap();
+                part.replace_range(separator..=separator, ":");
+            }
+        }
+        parts.join(" ")
+    }).collect();
+    test_parse::<ColonSrgMappingsFormat>(&colon_lines.iter().map(String::as_str).collect::<Vec<_>>());
+    test_serialize::<ColonSrgMappingsFormat>(&colon_lines.iter().map(String::as_str).collect::<Vec<_>>());
+    assert_eq!(
+        ColonSrgMappingsFormat::parse_lines(&colon_lines).unwrap(),
+        SrgMappingsFormat::parse_lines(TEST_LINES).unwrap()
+    );
+}
+
+#[test]
+fn tiny_v1() {
+    test_parse::<TinyV1MappingsFormat>(TINY_TEST_LINES);
+    test_serialize::<TinyV1MappingsFormat>(TINY_TEST_LINES);
+    // Tiny gives the types of fields, unlike SRG
+    assert_eq!(
+        TinyV1MappingsFormat::parse_lines(TINY_TEST_LINES).unwrap().without_field_types(),
+        SrgMappingsFormat::parse_lines(TEST_LINES).unwrap()
+    );
+    // Tiny gives the types of fields, which get remapped along with them
+    let taco = FieldData::new("taco".into(), ReferenceType::from_internal_name("org/spigotmc/XRay$Injector$Manager"))
+        .with_descriptor(TypeDescriptor::parse_descriptor("[Lobf4;").unwrap());
+    let renamed = TinyV1MappingsFormat::parse_lines(TINY_TEST_LINES).unwrap().remap_field(&taco);
+    assert_eq!(renamed.descriptor().map(TypeDescriptor::descriptor), Some("[Lnet/techcable/minecraft/Player;"));
+    assert!(TinyV1MappingsFormat::parse_lines(&["CLASS\ta\tb"]).is_err());
+}
+
+#[test]
+fn field_types() {
+    let lines = &[
+        "v1\tofficial\tnamed",
+        "CLASS\tobf4\tnet/techcable/minecraft/Player",
+        "FIELD\tobf4\tZ\ta\tdead",
+        "FIELD\tobf4\tLobf4;\ta\ttarget",
+    ];
+    let mappings = TinyV1MappingsFormat::parse_lines(lines).unwrap();
+    let player = ReferenceType::from_internal_name("obf4");
+    let field = |descriptor: &str| {
+        FieldData::new("a".into(), player.clone()).with_descriptor(TypeDescriptor::parse_descriptor(descriptor).unwrap())
+    };
+    assert_eq!(mappings.field_count(), 2);
+    assert_eq!(mappings.remap_field(&field("Z")).name, "dead");
+    let target = mappings.remap_field(&field("Lobf4;"));
+    assert_eq!(target.name, "target");
+    assert_eq!(target.descriptor().unwrap().descriptor(), "Lnet/techcable/minecraft/Player;");
+    // Without a type we fall back to the first field with the name
+    assert_eq!(mappings.remap_field(&FieldData::new("a".into(), player.clone())).name, "dead");
+    assert_eq!(mappings.remap_field(&field("I")).name, "dead");
+    // Mutable mappings fall back the same way
+    let mut simple = SimpleMappings::default();
+    for (original, renamed) in mappings.fields() {
+        simple.set_field_name(original.clone(), renamed.name.clone());
+    }
+    for original in &[field("Z"), field("Lobf4;"), field("I"), FieldData::new("a".into(), player.clone())] {
+        assert_eq!(simple.remap_field(original).name, mappings.remap_field(original).name);
+    }
+    assert_ne!(field("Z"), field("I"));
+    assert_ne!(field("Z"), FieldData::new("a".into(), player));
+    test_serialize::<TinyV1MappingsFormat>(lines);
+    assert_eq!(TinyV2MappingsFormat::parse_lines(TinyV2MappingsFormat::write_line_array(&mappings)).unwrap(), mappings);
+    assert_eq!(TabSrg2MappingsFormat::parse_lines(TabSrg2MappingsFormat::write_line_array(&mappings)).unwrap(), mappings);
+    assert_eq!(JsonMappingsFormat::parse_text(&JsonMappingsFormat::write_string(&mappings)).unwrap(), mappings);
+    assert_eq!(mappings.without_field_types().field_count(), 1);
+}
+
+#[test]
+fn tiny_v2() {
+    let text = [
+        "tiny\t2\t0\tofficial\tintermediary\tnamed",
+        "\tescaped-names",
+        "c\ta\tclass_1\tnet/techcable/minecraft/Player",
+        "\tc\tA player, who might be hacking",
+        "\tf\tZ\ta\tfield_1\tdead",
+        "\tf\tI\tb\tfield_2\t",
+        "\tm\t(La;I)V\tc\tmethod_1\tattack",
+        "\t\tp\t1\t\t\ttarget",
+        "\t\tc\tAttack the specified player",
+        "\tm\t()Lb;\td\tmethod_2\tget\\tName",
+        "c\tb\tclass_2\t",
+    ].join("\n");
+    let player = |name: &str, descriptor: &str| MethodData::new(
+        name.into(),
+        ReferenceType::from_internal_name("net/techcable/minecraft/Player"),
+        MethodSignature::from_descriptor(descriptor)
+    );
+    let official = TinyV2MappingsFormat::parse_text(&text).unwrap();
+    assert_eq!(official.remap_class_name("a").internal_name(), "class_1");
+    let named = TinyV2MappingsFormat::parse_stream_namespaces(text.as_bytes(), "official", "named").unwrap();
+    assert_eq!(named.remap_class_name("b").internal_name(), "b");
+    assert_eq!(
+        named.remap_field(&FieldData::new("b".into(), ReferenceType::from_internal_name("a"))).name,
+        "b"
+    );
+    assert_eq!(
+        named.remap_method(&MethodData::new(
+            "c".into(),
+            ReferenceType::from_internal_name("a"),
+            MethodSignature::from_descriptor("(La;I)V")
+        )),
+        player("attack", "(Lnet/techcable/minecraft/Player;I)V")
+    );
+    // Descriptors are given in the first namespace, so they need to be remapped to the source namespace
+    let intermediary = TinyV2MappingsFormat::parse_stream_namespaces(text.as_bytes(), "intermediary", "named").unwrap();
+    assert_eq!(
+        intermediary.remap_method(&MethodData::new(
+            "method_2".into(),
+            ReferenceType::from_internal_name("class_1"),
+            MethodSignature::from_descriptor("()Lclass_2;")
+        )),
+        player("get\tName", "()Lb;")
+    );
+    assert!(TinyV2MappingsFormat::parse_stream_namespaces(text.as_bytes(), "official", "unknown").is_err());
+    test_serialize::<TinyV2MappingsFormat>(TINY_TEST_LINES_V2);
+    assert_eq!(
+        TinyV2MappingsFormat::parse_lines(TINY_TEST_LINES_V2).unwrap(),
+        TinyV1MappingsFormat::parse_lines(TINY_TEST_LINES).unwrap()
+    );
+}
+
+#[test]
+fn tsrg2() {
+    let lines = &[
+        "tsrg2 obf srg named",
+        "obfs net/minecraft/NoHax net/techcable/minecraft/NoHax",
+        "\ta (Lobf4;ID)Z func_1_a isHacking",
+        "\t\tstatic",
+        "\t\t0 o p_1_0 player",
+        "\tb ()V func_2_b reset static",
+        "obf4 net/minecraft/Player net/techcable/minecraft/Player",
+        "\ta field_1_a dead",
+        "\tb I field_2_b blood",
+    ];
+    let mappings = TabSrg2MappingsFormat::parse_lines(lines).unwrap();
+    let player = ReferenceType::from_internal_name("obf4");
+    assert_eq!(mappings.remap_class(&player).name(), "net.techcable.minecraft.Player");
+    assert_eq!(
+        mappings.remap_method(&MethodData::new(
+            "a".into(), ReferenceType::from_internal_name("obfs"),
+            MethodSignature::from_descriptor("(Lobf4;ID)Z")
+        )),
+        MethodData::new(
+            "isHacking".into(), ReferenceType::from_name("net.techcable.minecraft.NoHax"),
+            MethodSignature::from_descriptor("(Lnet/techcable/minecraft/Player;ID)Z")
+        )
+    );
+    assert_eq!(mappings.remap_field(&FieldData::new("a".into(), player.clone())).name, "dead");
+    assert_eq!(mappings.remap_field(&FieldData::new("b".into(), player)).name, "blood");
+    assert_eq!(
+        mappings.remap_method(&MethodData::new(
+            "b".into(), ReferenceType::from_internal_name("obfs"),
+            MethodSignature::from_descriptor("()V")
+        )).name,
+        "reset"
+    );
+    // Static markers on either line give the method's access flags
+    let is_static: Vec<bool> = mappings.original_methods().map(MethodData::is_static).collect();
+    assert_eq!(is_static, vec![true, true]);
+    assert!(mappings.methods().all(|(_, renamed)| renamed.is_static()));
+    let reparsed = TabSrg2MappingsFormat::parse_lines(TabSrg2MappingsFormat::write_line_array(&mappings)).unwrap();
+    assert!(reparsed.original_methods().all(MethodData::is_static));
+    let simple = mappings.rebuild();
+    let method = MethodData::from_internal("obfs/a", "(Lobf4;ID)Z");
+    assert!(!method.is_static());
+    assert!(simple.remap_method(&method).is_static());
+    test_serialize::<TabSrg2MappingsFormat>(lines);
+    assert!(TabSrg2MappingsFormat::parse_lines(&["obf4 Player"]).is_err());
+    assert!(TabSrg2MappingsFormat::parse_lines(&["tsrg2 obf srg", "obf4 a Player"]).is_err());
+    assert!(TabSrg2MappingsFormat::parse_lines(&["tsrg2 obf srg", "obf4 Player", "\tb [ c"]).is_err());
+}
+
+#[test]
+fn parameters() {
+    let attack = MethodData::new(
+        "c".into(),
+        ReferenceType::from_internal_name("a"),
+        MethodSignature::from_descriptor("(La;I)V")
+    );
+    let tiny = TinyV2MappingsFormat::parse_lines(&[
+        "tiny\t2\t0\tofficial\tnamed",
+        "c\ta\tnet/techcable/minecraft/Player",
+        "\tm\t(La;I)V\tc\tattack",
+        "\t\tp\t1\t\ttarget",
+        "\t\tp\t2\t\t",
+    ]).unwrap();
+    // The original name doesn't matter, since parameters are identified by their index
+    let renamed = tiny.get_remapped_parameter(&ParameterData::new("".into(), attack.clone(), 1)).unwrap();
+    assert_eq!(renamed.name(), "target");
+    assert_eq!(renamed.index(), 1);
+    // The parameter's method is remapped along with its declaring class
+    assert_eq!(renamed.method(), &tiny.remap_method(&attack));
+    assert_eq!(renamed.method().declaring_type().internal_name(), "net/techcable/minecraft/Player");
+    assert!(tiny.get_remapped_parameter(&ParameterData::new("".into(), attack.clone(), 2)).is_none());
+    let inverted = tiny.inverted();
+    assert_eq!(
+        inverted.get_remapped_parameter(&renamed).unwrap().method(),
+        &attack
+    );
+
+    let tsrg2 = TabSrg2MappingsFormat::parse_lines(&[
+        "tsrg2 obf srg",
+        "a net/techcable/minecraft/Player",
+        "\tc (La;I)V attack",
+        "\t\tstatic",
+        "\t\t1 o target",
+    ]).unwrap();
+    assert_eq!(tsrg2.parameters().count(), 1);
+    assert_eq!(
+        tsrg2.get_remapped_parameter(&ParameterData::new("o".into(), attack.clone(), 1)).unwrap().into_owned(),
+        renamed.into_owned()
+    );
+    assert!(TabSrg2MappingsFormat::parse_lines(&["tsrg2 obf srg", "a b", "\t\t1 o target"]).is_err());
+    // Formats without parameters leave them empty
+    assert_eq!(SrgMappingsFormat::parse_text(&SrgMappingsFormat::write_string(&tiny)).unwrap().parameters().count(), 0);
+}
+
+#[test]
+fn proguard() {
+    let lines = &[
+        "# compiler: R8",
+        "net.techcable.minecraft.NoHax -> obfs:",
+        "    12:34:boolean isHacking(net.techcable.minecraft.Player,int,double) -> a",
+        "    35:35:void net.techcable.minecraft.Player.kick():10:10 -> a",
+        "    void reset() -> b",
+        "net.techcable.minecraft.Player -> obf4:",
+        "    boolean dead -> a",
+        "    int[][] blood -> b",
+        "    java.lang.String[] getNames(byte[],java.util.List) -> c",
+    ];
+    let mappings = ProguardMappingsFormat::parse_lines(lines).unwrap();
+    let player = ReferenceType::from_name("net.techcable.minecraft.Player");
+    assert_eq!(mappings.remap_class(&player).internal_name(), "obf4");
+    assert_eq!(
+        mappings.remap_method(&MethodData::new(
+            "isHacking".into(),
+            ReferenceType::from_name("net.techcable.minecraft.NoHax"),
+            MethodSignature::from_descriptor("(Lnet/techcable/minecraft/Player;ID)Z")
+        )),
+        MethodData::new("a".into(), ReferenceType::from_name("obfs"), MethodSignature::from_descriptor("(Lobf4;ID)Z"))
+    );
+    assert_eq!(
+        mappings.remap_method(&MethodData::new(
+            "getNames".into(), player.clone(),
+            MethodSignature::from_descriptor("([BLjava/util/List;)[Ljava/lang/String;")
+        )).name,
+        "c"
+    );
+    let blood = FieldData::new("blood".into(), player.clone()).with_descriptor(TypeDescriptor::parse_descriptor("[[I").unwrap());
+    assert_eq!(mappings.remap_field(&blood).name, "b");
+    assert_eq!(mappings.remap_field(&FieldData::new("blood".into(), player)).name, "b");
+    // The inlined method doesn't belong to NoHax
+    assert_eq!(mappings.original_methods().count(), 3);
+    test_serialize::<ProguardMappingsFormat>(lines);
+    assert!(ProguardMappingsFormat::parse_lines(&["    int a -> b"]).is_err());
+    assert!(ProguardMappingsFormat::parse_lines(&["a -> b:", "    int[ a -> b"]).is_err());
+    assert!(ProguardMappingsFormat::parse_lines(&["a -> b:", "    void a(void) -> b"]).is_err());
+}
+
+#[test]
+fn proguard_writer() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: net/techcable/minecraft/Player obf4",
+        "FD: net/techcable/minecraft/Player/dead obf4/a",
+        "MD: net/techcable/minecraft/Player/getNames ([BLjava/util/List;I)[[Ljava/lang/String; obf4/b ([BLjava/util/List;I)[[Ljava/lang/String;",
+        "MD: net/techcable/minecraft/Player/kill ()V obf4/c ()V",
+    ]).unwrap();
+    assert_eq!(ProguardMappingsFormat::write_line_array(&mappings), vec![
+        "net.techcable.minecraft.Player -> obf4:",
+        "    java.lang.Object dead -> a",
+        "    java.lang.String[][] getNames(byte[],java.util.List,int) -> b",
+        "    void kill() -> c",
+    ]);
+    let serialized = ProguardMappingsFormat::write_string(&mappings);
+    // The placeholder type is read back as the field's type
+    assert_eq!(ProguardMappingsFormat::parse_text(&serialized).unwrap().without_field_types(), mappings);
+}
+
+#[test]
+fn placeholder_field_types() {
+    // Fields without a type are written with a placeholder, which is read back as their type
+    let mappings = SrgMappingsFormat::parse_lines(TEST_LINES).unwrap();
+    let enigma = EnigmaMappingsFormat::parse_text(&EnigmaMappingsFormat::write_string(&mappings)).unwrap();
+    assert!(enigma.original_fields().all(|field| field.descriptor().unwrap().descriptor() == "Ljava/lang/Object;"));
+    assert_eq!(enigma.without_field_types(), mappings);
+    assert_eq!(TinyV1MappingsFormat::parse_text(&TinyV1MappingsFormat::write_string(&mappings)).unwrap().without_field_types(), mappings);
+    assert_eq!(TinyV2MappingsFormat::parse_text(&TinyV2MappingsFormat::write_string(&mappings)).unwrap().without_field_types(), mappings);
+    let inverted = mappings.inverted();
+    assert_eq!(ProguardMappingsFormat::parse_text(&ProguardMappingsFormat::write_string(&inverted)).unwrap().without_field_types(), inverted);
+    // Fields which really are objects keep their type
+    let object = TypeDescriptor::parse_descriptor("Ljava/lang/Object;").unwrap();
+    let typed = FrozenMappings::builder()
+        .field(FieldData::new("a".into(), ReferenceType::from_internal_name("obf4")).with_descriptor(object), "target")
+        .build();
+    assert_eq!(EnigmaMappingsFormat::parse_text(&EnigmaMappingsFormat::write_string(&typed)).unwrap(), typed);
+}
+
+const ENIGMA_TEST_LINES: &[&str] = &[
+    "CLASS org/spigotmc/XRay net/techcable/xray/XRay",
+    "\tMETHOD deobfuscate doAFunkyDance ([BLjava/util/Set;)I",
+    "\t\tARG 1 data",
+    "\tCLASS Manager XRayManager",
+    "\t\tCOMMENT Manages the XRay",
+    "\t\tMETHOD aquire get ()Lorg/spigotmc/XRay;",
+    "\tCLASS Injector",
+    "\t\tCLASS Manager InjectorManager",
+    "\t\t\tFIELD taco seriousVariableName [Lobf4;",
+    "\t\t\tFIELD unchanged Z",
+    "CLASS obfs net/techcable/minecraft/NoHax",
+    "\tMETHOD a isHacking (Lobf4;ID)Z",
+    "CLASS obf4 net/techcable/minecraft/Player",
+    "\tFIELD a dead Z",
+];
+
+#[test]
+fn enigma() {
+    let mappings = EnigmaMappingsFormat::parse_lines(ENIGMA_TEST_LINES).unwrap();
+    let class = |name: &str| mappings.remap_class_name(name).internal_name().to_owned();
+    assert_eq!(class("org/spigotmc/XRay$Manager"), "net/techcable/xray/XRay$XRayManager");
+    assert_eq!(class("org/spigotmc/XRay$Injector"), "net/techcable/xray/XRay$Injector");
+    assert_eq!(class("org/spigotmc/XRay$Injector$Manager"), "net/techcable/xray/XRay$Injector$InjectorManager");
+    assert_eq!(
+        mappings.remap_field(&FieldData::new(
+            "taco".into(),
+            ReferenceType::from_internal_name("org/spigotmc/XRay$Injector$Manager")
+        )).name,
+        "seriousVariableName"
+    );
+    assert_eq!(
+        mappings.remap_method(&MethodData::new(
+            "aquire".into(),
+            ReferenceType::from_internal_name("org/spigotmc/XRay$Manager"),
+            MethodSignature::from_descriptor("()Lorg/spigotmc/XRay;")
+        )).name,
+        "get"
+    );
+    assert_eq!(
+        mappings.remap_method(&MethodData::new(
+            "a".into(),
+            ReferenceType::from_internal_name("obfs"),
+            MethodSignature::from_descriptor("(Lobf4;ID)Z")
+        )).name,
+        "isHacking"
+    );
+    test_serialize::<EnigmaMappingsFormat>(ENIGMA_TEST_LINES);
+    assert!(EnigmaMappingsFormat::parse_lines(&["\tFIELD a b Z"]).is_err());
+    assert!(EnigmaMappingsFormat::parse_lines(&["CLASS a", "\t\tCLASS b"]).is_err());
+    assert!(EnigmaMappingsFormat::parse_lines(&["CLASS a", "\tMETHOD a b invalid"]).is_err());
+}
+
+#[test]
+fn enigma_directory() {
+    let directory = std::env::temp_dir().join(format!("srglib-enigma-{}", std::process::id()));
+    let package = directory.join("net/techcable/minecraft");
+    std::fs::create_dir_all(&package).unwrap();
+    std::fs::write(
+        package.join("Player.mapping"),
+        "CLASS obf4 net/techcable/minecraft/Player\n\tFIELD a dead Z\n"
+    ).unwrap();
+    std::fs::write(
+        package.join("NoHax.mapping"),
+        "CLASS obfs net/techcable/minecraft/NoHax\n\tMETHOD a isHacking (Lobf4;ID)Z\n"
+    ).unwrap();
+    std::fs::write(package.join("README.txt"), "Not a mapping").unwrap();
+    let result = EnigmaMappingsFormat::parse_directory(&directory);
+    std::fs::remove_dir_all(&directory).unwrap();
+    let expected = SrgMappingsFormat::parse_lines(&[
+        "CL: obfs net/techcable/minecraft/NoHax",
+        "MD: obfs/a (Lobf4;ID)Z net/techcable/minecraft/NoHax/isHacking (Lnet/techcable/minecraft/Player;ID)Z",
+        "CL: obf4 net/techcable/minecraft/Player",
+        "FD: obf4/a net/techcable/minecraft/Player/dead",
+    ]).unwrap();
+    assert!(result.unwrap().without_field_types().content_eq(&expected));
+}
+
+#[test]
+fn comments() {
+    let mut mappings = SimpleMappings::default();
+    let player = ReferenceType::from_internal_name("obf4");
+    let dead = FieldData::new("a".into(), player.clone());
+    let attack = MethodData::new("b".into(), player.clone(), MethodSignature::from_descriptor("(Lobf4;)V"));
+    let unnamed = MethodData::new("c".into(), player.clone(), MethodSignature::from_descriptor("()V"));
+    mappings.set_remapped_class(player.clone(), ReferenceType::from_internal_name("net/techcable/minecraft/Player"));
+    mappings.set_field_name(dead.clone(), "dead".into());
+    mappings.set_method_name(attack.clone(), "attack".into());
+    mappings.set_method_name(unnamed.clone(), "c".into());
+    mappings.set_class_comment(player.clone(), "Someone playing the game".into());
+    mappings.set_field_comment(dead.clone(), "Whether they've died,\nwhich\tis\\sad".into());
+    mappings.set_method_comment(attack.clone(), "Attack another player".into());
+    let check = |parsed: &FrozenMappings| {
+        assert_eq!(parsed.class_comment(&player), Some("Someone playing the game"));
+        assert_eq!(parsed.field_comment(&dead), Some("Whether they've died,\nwhich\tis\\sad"));
+        assert_eq!(parsed.method_comment(&attack), Some("Attack another player"));
+        assert_eq!(parsed.method_comment(&unnamed), None);
+    };
+    check(&EnigmaMappingsFormat::parse_lines(EnigmaMappingsFormat::write_line_array(&mappings)).unwrap());
+    check(&TinyV2MappingsFormat::parse_lines(TinyV2MappingsFormat::write_line_array(&mappings)).unwrap());
+    check(&mappings.frozen().rebuild().frozen());
+    // Comments are attached to the renamed data when inverting
+    let inverted = mappings.inverted();
+    assert_eq!(
+        inverted.method_comment(&MethodData::new(
+            "attack".into(),
+            ReferenceType::from_internal_name("net/techcable/minecraft/Player"),
+            MethodSignature::from_descriptor("(Lnet/techcable/minecraft/Player;)V")
+        )),
+        Some("Attack another player")
+    );
+    // Chaining keeps the comments from both mappings, attached to the oldest originals
+    let mut named = SimpleMappings::default();
+    let renamed_player = ReferenceType::from_internal_name("net/techcable/minecraft/Player");
+    let renamed_dead = FieldData::new("dead".into(), renamed_player.clone());
+    let renamed_unnamed = MethodData::new("c".into(), renamed_player, MethodSignature::from_descriptor("()V"));
+    named.set_field_name(renamed_dead.clone(), "isDead".into());
+    named.set_field_comment(renamed_dead, "Ignored, since the first mapping has a comment".into());
+    named.set_method_name(renamed_unnamed.clone(), "idle".into());
+    named.set_method_comment(renamed_unnamed, "Do nothing".into());
+    let chained = mappings.frozen().chain(named.frozen());
+    assert_eq!(chained.remap_field(&dead).name, "isDead");
+    assert_eq!(chained.class_comment(&player), Some("Someone playing the game"));
+    assert_eq!(chained.field_comment(&dead), Some("Whether they've died,\nwhich\tis\\sad"));
+    assert_eq!(chained.method_comment(&unnamed), Some("Do nothing"));
+    // Comments on arguments aren't attached to their method
+    let parsed = EnigmaMappingsFormat::parse_lines(&[
+        "CLASS obf4",
+        "\tMETHOD c ()V",
+        "\t\tARG 1 ignored",
+        "\t\t\tCOMMENT Not for the method",
+    ]).unwrap();
+    assert_eq!(parsed.method_comment(&unnamed), None);
+}
+
+#[test]
+fn mcp_csv() {
+    let base = SrgMappingsFormat::parse_lines(&[
+        "CL: a net/minecraft/entity/Entity",
+        "FD: a/a net/minecraft/entity/Entity/field_70170_p",
+        "FD: a/b net/minecraft/entity/Entity/field_99999_q",
+        "MD: a/c ()V net/minecraft/entity/Entity/func_70071_h_ ()V",
+        "MD: a/d ()V net/minecraft/entity/Entity/func_99999_z ()V",
+    ]).unwrap();
+    let fields_csv = "searge,name,side,desc\r\nfield_70170_p,world,2,\"The world, which this entity is in\"\r\n";
+    let methods_csv = "searge,name,side,desc\nfunc_70071_h_,onUpdate,2,Called to update the entity\n";
+    let expected = SrgMappingsFormat::parse_lines(&[
+        "CL: a net/minecraft/entity/Entity",
+        "FD: a/a net/minecraft/entity/Entity/world",
+        "FD: a/b net/minecraft/entity/Entity/field_99999_q",
+        "MD: a/c ()V net/minecraft/entity/Entity/onUpdate ()V",
+        "MD: a/d ()V net/minecraft/entity/Entity/func_99999_z ()V",
+    ]).unwrap();
+    apply_mcp_csv(&base, fields_csv, methods_csv).assert_equal(&expected);
+}
+
+#[test]
+fn mcp_csv_export() {
+    let names = SrgMappingsFormat::parse_lines(&[
+        "FD: net/minecraft/entity/Entity/field_70170_p net/minecraft/entity/Entity/world",
+        "MD: net/minecraft/entity/Entity/func_70071_h_ ()V net/minecraft/entity/Entity/onUpdate ()V",
+        "MD: net/minecraft/entity/EntityPlayer/func_70071_h_ ()V net/minecraft/entity/EntityPlayer/onUpdate ()V",
+        "MD: net/minecraft/entity/Entity/func_70012_b (DDD)Lnet/minecraft/entity/Entity; net/minecraft/entity/Entity/setPosition (DDD)Lnet/minecraft/entity/Entity;",
+    ]).unwrap();
+    let (fields_csv, methods_csv) = export_mcp_csv(&names);
+    assert_eq!(fields_csv, "searge,name,side,desc\nfield_70170_p,world,2,\n");
+    assert_eq!(methods_csv, concat!(
+        "searge,name,side,desc\n",
+        "func_70071_h_,onUpdate,2,()V\n",
+        "func_70012_b,setPosition,2,(DDD)Lnet/minecraft/entity/Entity;\n"
+    ));
+    let base = SrgMappingsFormat::parse_lines(&[
+        "FD: a/a net/minecraft/entity/Entity/field_70170_p",
+        "MD: a/c ()V net/minecraft/entity/Entity/func_70071_h_ ()V",
+    ]).unwrap();
+    let expected = SrgMappingsFormat::parse_lines(&[
+        "FD: a/a net/minecraft/entity/Entity/world",
+        "MD: a/c ()V net/minecraft/entity/Entity/onUpdate ()V",
+    ]).unwrap();
+    apply_mcp_csv(&base, &fields_csv, &methods_csv).assert_equal(&expected);
+}
+
+#[test]
+fn json() {
+    let expected = SrgMappingsFormat::parse_lines(TEST_LINES).unwrap();
+    let serialized = JsonMappingsFormat::write_string(&expected);
+    let actual = JsonMappingsFormat::parse_text(&serialized).unwrap();
+    actual.assert_equal(&expected);
+    // The iteration order is preserved too
+    assert_eq!(SrgMappingsFormat::write_string(&actual), SrgMappingsFormat::write_string(&expected));
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: obf4 net/techcable/minecraft/Player",
+        "FD: obf4/a net/techcable/minecraft/Player/dead",
+        "MD: obf4/b (Lobf4;)V net/techcable/minecraft/Player/kill (Lnet/techcable/minecraft/Player;)V",
+    ]).unwrap();
+    assert_eq!(JsonMappingsFormat::write_line_array(&mappings), vec![
+        "{",
+        "  \"classes\": [",
+        "    {\"original\": \"obf4\", \"renamed\": \"net/techcable/minecraft/Player\"}",
+        "  ],",
+        "  \"fields\": [",
+        "    {\"owner\": \"obf4\", \"original\": \"a\", \"renamedOwner\": \"net/techcable/minecraft/Player\", \"renamed\": \"dead\"}",
+        "  ],",
+        "  \"methods\": [",
+        "    {\"owner\": \"obf4\", \"original\": \"b\", \"descriptor\": \"(Lobf4;)V\", \"renamedOwner\": \"net/techcable/minecraft/Player\", \"renamed\": \"kill\", \"renamedDescriptor\": \"(Lnet/techcable/minecraft/Player;)V\"}",
+        "  ]",
+        "}",
+    ]);
+    let compact = JsonMappingsFormat::parse_text(
+        r#"{"methods": [], "fields": [{"owner": "obf4", "original": "a", "renamed": "\u0064ead", "extra": [1.5, true, null]}], "classes": []}"#
+    ).unwrap();
+    assert_eq!(compact.remap_field(&FieldData::new("a".into(), ReferenceType::from_internal_name("obf4"))).name, "dead");
+    let empty = JsonMappingsFormat::write_string(&FrozenMappings::empty());
+    assert_eq!(JsonMappingsFormat::parse_text(&empty).unwrap(), FrozenMappings::empty());
+    match JsonMappingsFormat::parse_lines(&["{\"classes\": [],", "  \"fields\": [{\"owner\": \"obf4\"}],", "\"methods\": []}"]) {
+        Err(MappingsParseError::InvalidLine { line, index, .. }) => {
+            assert_eq!(line, "  \"fields\": [{\"owner\": \"obf4\"}],");
+            assert_eq!(index, 13);
+        },
+        other => panic!("Unexpected result: {:?}", other)
+    }
+    assert!(JsonMappingsFormat::parse_text("{\"classes\": [}").is_err());
+    assert!(JsonMappingsFormat::parse_text("{\"classes\": [], \"fields\": [], \"methods\": []} trailing").is_err());
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn gzip() {
+    let expected = SrgMappingsFormat::parse_lines(TEST_LINES).unwrap();
+    let mut compressed = Vec::new();
+    TabSrgMappingsFormat::write_gzip(&expected, &mut compressed).unwrap();
+    // The output is an actual gzip stream, not just the text
+    assert_eq!(compressed[..2], [0x1f, 0x8b]);
+    TabSrgMappingsFormat::parse_gzip(&*compressed).unwrap().assert_equal(&expected);
+    let mut compressed = Vec::new();
+    TinyV2MappingsFormat::write_gzip(&expected, &mut compressed).unwrap();
+    TinyV2MappingsFormat::parse_gzip(&*compressed).unwrap().without_field_types().assert_equal(&expected);
+    match TabSrgMappingsFormat::parse_gzip(&b"not gzip"[..]) {
+        Err(MappingsParseError::Io(_)) => {},
+        other => panic!("Unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn borrowed() {
+    let text = TEST_LINES.join("\n");
+    let borrowed = BorrowedMappings::parse_srg(&text).unwrap();
+    let expected = SrgMappingsFormat::parse_lines(TEST_LINES).unwrap();
+    borrowed.frozen().assert_equal(&expected);
+    let player = ReferenceType::from_internal_name("obf4");
+    assert_eq!(borrowed.remap_class(&player).internal_name(), "net/techcable/minecraft/Player");
+    assert_eq!(borrowed.remap_field(&FieldData::new("a".into(), player.clone())), expected.remap_field(&FieldData::new("a".into(), player)));
+    let method = MethodData::new(
+        "a".into(), ReferenceType::from_internal_name("obfs"),
+        MethodSignature::from_descriptor("(Lobf4;ID)Z")
+    );
+    assert_eq!(borrowed.remap_method(&method), expected.remap_method(&method));
+    let compact_text = COMPACT_TEST_LINES.join("\n");
+    BorrowedMappings::parse_compact_srg(&compact_text).unwrap().frozen().assert_equal(&expected);
+    assert!(BorrowedMappings::parse_srg("MD: a/b (La)V c/d (La)V").is_err());
+    assert!(BorrowedMappings::parse_compact_srg("a b (I c").is_err());
+}
+
+#[test]
+fn try_all() {
+    let expected = SrgMappingsFormat::parse_lines(TEST_LINES).unwrap();
+    for (text, kind) in &[
+        (SrgMappingsFormat::write_string(&expected), MappingsFormatKind::Srg),
+        (TabSrgMappingsFormat::write_string(&expected), MappingsFormatKind::TabSrg),
+        (CompactSrgMappingsFormat::write_string(&expected), MappingsFormatKind::CompactSrg),
+    ] {
+        let (result, detected) = parse_try_all(text).unwrap();
+        assert_eq!(detected, *kind);
+        result.assert_equal(&expected);
+    }
+    let tiny = TINY_TEST_LINES_V2.join("\n");
+    let (_, detected) = parse_try_each(&tiny, &[MappingsFormatKind::Srg, MappingsFormatKind::TinyV2]).unwrap();
+    assert_eq!(detected, MappingsFormatKind::TinyV2);
+    assert_eq!(parse_try_all("this is not a mapping file").unwrap_err().len(), 3);
+}
+
+#[test]
+fn collapse_whitespace() {
+    let expected = SrgMappingsFormat::parse_lines(TEST_LINES).unwrap();
+    let lenient = ParseOptions::default().collapse_whitespace(true);
+    for (text, kind) in &[
+        (SrgMappingsFormat::write_string(&expected), MappingsFormatKind::Srg),
+        (TabSrgMappingsFormat::write_string(&expected), MappingsFormatKind::TabSrg),
+        (CompactSrgMappingsFormat::write_string(&expected), MappingsFormatKind::CompactSrg),
+    ] {
+        // Mix tabs and runs of spaces into the separators, keeping the indentation
+        let reformatted: Vec<String> = text.lines()
+            .map(|line| {
+                let content = line.trim_start();
+                format!("{}{}  ", &line[..line.len() - content.len()], content.replace(' ', " \t  "))
+            })
+            .collect();
+        let reformatted = reformatted.join("\n");
+        assert!(kind.parse_text(&reformatted).is_err());
+        kind.parse_text_with_options(&reformatted, lenient).unwrap().assert_equal(&expected);
+        kind.parse_text_with_options(text, ParseOptions::default()).unwrap().assert_equal(&expected);
+    }
+    let proguard = ProguardMappingsFormat::parse_text_with_options(
+        "a.b.Entity\t->  a:\n    int\t\tb ->   dead",
+        lenient
+    ).unwrap();
+    assert_eq!(proguard.remap_class_name("a/b/Entity").internal_name(), "a");
+    assert_eq!(proguard.field_count(), 1);
+}
+
+#[test]
+fn crlf_line_endings() {
+    let expected = SrgMappingsFormat::parse_lines(TEST_LINES).unwrap();
+    test_crlf::<SrgMappingsFormat>(&expected);
+    test_crlf::<ColonSrgMappingsFormat>(&expected);
+    test_crlf::<CompactSrgMappingsFormat>(&expected);
+    test_crlf::<TabSrgMappingsFormat>(&expected);
+    test_crlf::<TabSrg2MappingsFormat>(&expected);
+    test_crlf::<TinyV1MappingsFormat>(&expected);
+    test_crlf::<TinyV2MappingsFormat>(&expected);
+    test_crlf::<ProguardMappingsFormat>(&expected);
+    test_crlf::<EnigmaMappingsFormat>(&expected);
+    test_crlf::<JsonMappingsFormat>(&expected);
+    let trailing = TEST_LINES.iter().map(|line| format!("{}  ", line)).collect::<Vec<_>>().join("\n");
+    BorrowedMappings::parse_srg(&trailing).unwrap().frozen().assert_equal(&expected);
+}
+
 #[test]
 fn srg_packages() {
     let result = SrgMappingsFormat::parse_lines(&[
@@ -58,6 +753,259 @@ fn srg_packages() {
     assert_eq!(result.remap_class_name("b").internal_name(), "net/minecraft/server/bathroom");
 }
 
+#[test]
+fn srg_packages_lost_on_write() {
+    let lines = &[
+        "PK: ./ net/minecraft/server",
+        "PK: net/minecraft/client ./",
+        "CL: a food",
+        "CL: b net/minecraft/client/bathroom",
+    ];
+    // The package lines are baked into the classes, so writing only gives the classes back
+    let parsed = SrgMappingsFormat::parse_lines(lines).unwrap();
+    assert_eq!(parsed.remap_class_name("b").internal_name(), "bathroom");
+    let written = SrgMappingsFormat::write_line_array(&parsed);
+    assert_eq!(written, vec![
+        "CL: a net/minecraft/server/food",
+        "CL: b bathroom",
+    ]);
+    SrgMappingsFormat::parse_lines(&written).unwrap().assert_equal(&parsed);
+}
+
+#[test]
+fn srg_keep_packages() {
+    let lines = &[
+        "PK: ./ net/minecraft/server",
+        "CL: a food",
+        "CL: b net/minecraft/client/bathroom",
+    ];
+    let kept = SrgMappingsFormat::parse_lines_with_options(lines, ParseOptions::default().keep_packages(true)).unwrap();
+    assert_eq!(kept, SrgMappingsFormat::parse_lines(lines).unwrap());
+    assert_eq!(SrgMappingsFormat::parse_lines(lines).unwrap().package_mappings(), None);
+    let mut processor = SrgMappingsFormat::processor().keep_packages();
+    for line in lines {
+        processor.process_line(line).unwrap();
+    }
+    assert_eq!(processor.finish().unwrap().package_mappings(), kept.package_mappings());
+    let packages = kept.package_mappings().unwrap();
+    assert_eq!(packages.len(), 1);
+    assert_eq!(packages[""], "net/minecraft/server");
+    let written = SrgMappingsFormat::write_line_array(&kept);
+    assert_eq!(written, vec![
+        "PK: ./ net/minecraft/server",
+        "CL: a net/minecraft/server/food",
+        "CL: b net/minecraft/client/bathroom",
+    ]);
+    SrgMappingsFormat::parse_lines(&written).unwrap().assert_equal(&kept);
+    let inverted = kept.inverted();
+    assert_eq!(inverted.package_mappings().unwrap()["net/minecraft/server"], "");
+    assert_eq!(kept.sorted().package_mappings(), kept.package_mappings());
+    // Building new mappings drops them, but they can be added back
+    let chained = kept.chain(FrozenMappings::empty());
+    assert_eq!(chained.package_mappings(), None);
+    assert_eq!(chained.with_package_mappings(packages.clone()).package_mappings(), Some(packages));
+    assert_eq!(kept.with_package_mappings(Default::default()).package_mappings(), None);
+    assert_eq!(SrgMappingsFormat::write_line_array(&inverted)[0], "PK: net/minecraft/server ./");
+}
+
+#[test]
+fn srg_keep_swapped_packages() {
+    let mut processor = SrgMappingsFormat::processor().keep_packages();
+    for line in &["PK: a b", "PK: b a", "CL: a/X a/X"] {
+        processor.process_line(line).unwrap();
+    }
+    let kept = processor.finish().unwrap();
+    assert_eq!(kept.remap_class_name("a/X").internal_name(), "b/X");
+    // The package lines come first, so the class lines are written with the renames undone
+    let written = SrgMappingsFormat::write_line_array(&kept);
+    assert_eq!(written, vec!["PK: a b", "PK: b a", "CL: a/X a/X"]);
+    SrgMappingsFormat::parse_lines(&written).unwrap().assert_equal(&kept);
+    // The package renames survive sorting and inverting, and still give back the same classes
+    let sorted = SrgMappingsFormat::write_line_array(&kept.sorted());
+    SrgMappingsFormat::parse_lines(&sorted).unwrap().assert_equal(&kept);
+    let inverted = kept.inverted();
+    assert_eq!(inverted.package_mappings().unwrap()["b"], "a");
+    let written = SrgMappingsFormat::write_line_array(&inverted);
+    assert_eq!(written, vec!["PK: b a", "PK: a b", "CL: b/X b/X"]);
+    SrgMappingsFormat::parse_lines(&written).unwrap().assert_equal(&inverted);
+}
+
+#[test]
+fn srg_duplicate_packages() {
+    let lines = &[
+        "CL: a food",
+        "PK: ./ net/minecraft/server",
+        "PK: ./ net/minecraft/server",
+        "PK: ./ net/minecraft/client",
+    ];
+    // The last package directive wins by default
+    let result = SrgMappingsFormat::parse_lines(lines).unwrap();
+    assert_eq!(result.remap_class_name("a").internal_name(), "net/minecraft/client/food");
+    let mut processor = SrgMappingsFormat::processor().strict();
+    processor.process_line(lines[0]).unwrap();
+    processor.process_line(lines[1]).unwrap();
+    // Repeating the same rename is fine
+    processor.process_line(lines[2]).unwrap();
+    match processor.process_line(lines[3]) {
+        Err(MappingsParseError::InvalidLine { index, .. }) => assert_eq!(index, 7),
+        other => panic!("Unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn line_numbers() {
+    let text = "CL: obf4 net/minecraft/Player\r\n# A comment\r\n\r\nFD: obf4/a\r\n";
+    let check = |result: Result<FrozenMappings, MappingsParseError>| match result {
+        Err(MappingsParseError::InvalidLine { line, line_number, .. }) => {
+            assert_eq!(line, "FD: obf4/a");
+            assert_eq!(line_number, Some(4));
+        },
+        other => panic!("Unexpected result: {:?}", other)
+    };
+    check(SrgMappingsFormat::parse_text(text));
+    check(SrgMappingsFormat::parse_stream(text.as_bytes()));
+    let error = SrgMappingsFormat::parse_text(text).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid line 4 at 10: \"FD: obf4/a\" (Expected ' ', but got the end of the line)");
+    // Processing lines individually doesn't know the line number
+    match SrgMappingsFormat::processor().process_line("FD: obf4/a") {
+        Err(MappingsParseError::InvalidLine { line_number, .. }) => assert_eq!(line_number, None),
+        other => panic!("Unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn error_reasons() {
+    let reason = |result: Result<FrozenMappings, MappingsParseError>| match result {
+        Err(MappingsParseError::InvalidLine { reason, .. }) => reason.unwrap(),
+        other => panic!("Unexpected result: {:?}", other)
+    };
+    assert_eq!(
+        reason(SrgMappingsFormat::parse_text("XX: obf4 Player")),
+        "Expected CL, FD, MD or PK, but got \"XX:\""
+    );
+    // Multibyte characters aren't split when checking the kind of line
+    assert_eq!(
+        reason(SrgMappingsFormat::parse_text("é: obf4 Player")),
+        "Expected CL, FD, MD or PK, but got \"é:\""
+    );
+    assert_eq!(reason(SrgMappingsFormat::parse_text("é")), "Expected CL, FD, MD or PK, but got \"é\"");
+    assert!(BorrowedMappings::parse_srg("Cé: obf4 Player").is_err());
+    assert_eq!(
+        reason(SrgMappingsFormat::parse_text("CL: obf4 Player extra")),
+        "Unexpected trailing text: \"extra\""
+    );
+    assert_eq!(
+        reason(CompactSrgMappingsFormat::parse_text("obf4 a b c d")),
+        "Expected 2, 3 or 4 columns, but got 5: \"obf4 a b c d\""
+    );
+    assert_eq!(
+        reason(CompactSrgMappingsFormat::parse_text("obf4 a (Lobf4;X)V b")),
+        "Expected a type descriptor, but got \"X)V\""
+    );
+    assert_eq!(reason(TabSrgMappingsFormat::parse_text("\ta b")), "Missing current class");
+    assert_eq!(
+        reason(TabSrgMappingsFormat::parse_text("obf4 Player\n\ta b c d")),
+        "Expected 2 or 3 columns, but got 4: \"a b c d\""
+    );
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parse_parallel() {
+    let text = TEST_LINES.join("\n");
+    // Tiny chunks split before every class, so we actually merge the results
+    let parallel = SrgMappingsFormat::parse_parallel_chunks(&text, 1).unwrap();
+    parallel.assert_equal(&SrgMappingsFormat::parse_text(&text).unwrap());
+    assert_eq!(SrgMappingsFormat::write_string(&parallel), SrgMappingsFormat::write_string(&SrgMappingsFormat::parse_text(&text).unwrap()));
+    assert_eq!(SrgMappingsFormat::parse_parallel(&text).unwrap(), parallel);
+    let compact = CompactSrgMappingsFormat::write_string(&parallel);
+    CompactSrgMappingsFormat::parse_parallel_chunks(&compact, 1).unwrap().assert_equal(&parallel);
+    let nested = TabSrgMappingsFormat::write_string(&parallel);
+    TabSrgMappingsFormat::parse_parallel_chunks(&nested, 1).unwrap().assert_equal(&parallel);
+    // Comments inside a class don't end it
+    let commented = "a Foo\n\tb bar\n# c\n\tc baz\nd Qux\n\te quux";
+    TabSrgMappingsFormat::parse_parallel_chunks(commented, 1).unwrap()
+        .assert_equal(&TabSrgMappingsFormat::parse_text(commented).unwrap());
+    // Package directives apply to the whole file, even the classes in other chunks
+    let packages = SrgMappingsFormat::parse_parallel_chunks("CL: a net/minecraft/server/a\nPK: net/minecraft/server net/minecraft/client", 1).unwrap();
+    assert_eq!(packages.remap_class_name("a").internal_name(), "net/minecraft/client/a");
+    match SrgMappingsFormat::parse_parallel_chunks("CL: a b\nCL: c d\nXX: bad\nCL: e f", 1) {
+        Err(MappingsParseError::InvalidLine { line_number, .. }) => assert_eq!(line_number, Some(3)),
+        other => panic!("Unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn srg_without_packages() {
+    let result = SrgMappingsFormat::parse_lines(TEST_LINES).unwrap();
+    let transformed = result.transform_packages(|_| None);
+    transformed.assert_equal(&result);
+    assert_eq!(SrgMappingsFormat::write_string(&transformed), SrgMappingsFormat::write_string(&result));
+}
+
+#[test]
+fn inline_comments() {
+    let expected = SrgMappingsFormat::parse_lines(&[
+        "CL: obf4 net/techcable/minecraft/Player",
+        "FD: obf4/a net/techcable/minecraft/Player/dead",
+        "MD: obf4/b (Lobf4;)V net/techcable/minecraft/Player/attack (Lnet/techcable/minecraft/Player;)V",
+    ]).unwrap();
+    SrgMappingsFormat::parse_lines(&[
+        "# A whole line comment",
+        "CL: obf4 net/techcable/minecraft/Player # The player",
+        "FD: obf4/a net/techcable/minecraft/Player/dead\t#dead",
+        "MD: obf4/b (Lobf4;)V net/techcable/minecraft/Player/attack (Lnet/techcable/minecraft/Player;)V #",
+    ]).unwrap().assert_equal(&expected);
+    CompactSrgMappingsFormat::parse_lines(&[
+        "obf4 net/techcable/minecraft/Player # The player",
+        "obf4 a dead # dead",
+        "  # An indented comment",
+        "obf4 b (Lobf4;)V attack",
+    ]).unwrap().assert_equal(&expected);
+    TabSrgMappingsFormat::parse_lines(&[
+        "obf4 net/techcable/minecraft/Player # The player",
+        "\ta dead # dead",
+        "\t# An indented comment",
+        "\tb (Lobf4;)V attack",
+    ]).unwrap().assert_equal(&expected);
+    // Comment entries in tiny v2 are free text
+    let tiny = TinyV2MappingsFormat::parse_lines(&[
+        "tiny\t2\t0\tofficial\tnamed # Header",
+        "c\tobf4\tnet/techcable/minecraft/Player\t# The player",
+        "\tc\tSee #attack",
+        "\tf\tZ\ta\tdead",
+        "\tm\t(Lobf4;)V\tb\tattack",
+    ]).unwrap();
+    tiny.without_field_types().assert_equal(&expected);
+    let player = ReferenceType::from_internal_name("obf4");
+    assert_eq!(tiny.class_comment(&player), Some("See #attack"));
+    // A `#` in the middle of a name isn't a comment
+    let hash = SrgMappingsFormat::parse_lines(&["CL: obf4 net/techcable/Player#1"]).unwrap();
+    assert_eq!(hash.remap_class(&player).internal_name(), "net/techcable/Player#1");
+}
+
+#[test]
+fn write_sorted() {
+    let mappings = SrgMappingsFormat::parse_lines(TEST_LINES).unwrap();
+    let mut expected = TEST_LINES.to_vec();
+    expected.sort();
+    let mut buffer = Vec::new();
+    SrgMappingsFormat::write_sorted(&mappings, &mut buffer).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap().lines().collect::<Vec<_>>(), expected);
+    // Line-based formats without their own sorting write the sorted mappings
+    let mut buffer = Vec::new();
+    CompactSrgMappingsFormat::write_sorted(&mappings, &mut buffer).unwrap();
+    let compact = String::from_utf8(buffer).unwrap();
+    assert_eq!(compact.lines().next(), Some("obf4 net/techcable/minecraft/Player"));
+    CompactSrgMappingsFormat::parse_text(&compact).unwrap().assert_equal(&mappings.sorted());
+}
+
+#[test]
+fn parse_inverted() {
+    let inverted = SrgMappingsFormat::parse_lines_inverted(TEST_LINES).unwrap();
+    assert_eq!(inverted.remap_class_name("net.techcable.minecraft.Player").internal_name(), "obf4");
+    inverted.assert_equal(&SrgMappingsFormat::parse_text(&TEST_LINES.join("\n")).unwrap().inverted());
+}
 
 fn test_parse<F: MappingsFormat>(test_lines: &[&str]) {
     let result = F::parse_lines(test_lines).unwrap();
@@ -102,6 +1050,14 @@ fn test_parse<F: MappingsFormat>(test_lines: &[&str]) {
         ))
     );
 }
+/// Check that the format ignores a leftover `\r` and trailing spaces at the end of each line
+fn test_crlf<T: MappingsFormat>(mappings: &FrozenMappings) {
+    let text = T::write_string(mappings);
+    let expected = T::parse_text(&text).unwrap();
+    let lines: Vec<String> = text.lines().map(|line| format!("{} \r", line)).collect();
+    assert_eq!(T::parse_lines(&lines).unwrap(), expected);
+    assert_eq!(T::parse_stream(text.replace('\n', "\r\n").as_bytes()).unwrap(), expected);
+}
 fn test_serialize<T: MappingsFormat>(test_lines: &[&str]) {
     let expected = T::parse_lines(test_lines).unwrap();
     let serialized = T::write_line_array(&expected);
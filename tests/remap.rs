@@ -0,0 +1,754 @@
+extern crate srglib;
+
+use std::collections::HashMap;
+
+use srglib::prelude::*;
+use srglib::format::MappingsLineProcessor;
+use srglib::signature::GenericSignature;
+use srglib::utils::SimpleParse;
+use srglib::mappings::{MergeConflict, PackageCoverage, MappingsValidationError, MappingsVisitor};
+
+fn test_mappings() -> FrozenMappings {
+    SrgMappingsFormat::parse_lines(&[
+        "CL: obf4 net/techcable/minecraft/Player",
+        "CL: obfs net/techcable/minecraft/NoHax",
+        "FD: obf4/a net/techcable/minecraft/Player/dead",
+        "MD: obfs/a (Lobf4;ID)Z net/techcable/minecraft/NoHax/isHacking (Lnet/techcable/minecraft/Player;ID)Z",
+    ]).unwrap()
+}
+
+#[test]
+fn verification_types() {
+    let mappings = test_mappings();
+    assert_eq!(mappings.remap_verification_type("obf4"), "net/techcable/minecraft/Player");
+    assert_eq!(mappings.remap_verification_type("[[Lobf4;"), "[[Lnet/techcable/minecraft/Player;");
+    assert_eq!(mappings.remap_verification_type("[I"), "[I");
+    assert_eq!(mappings.remap_verification_type("java/lang/String"), "java/lang/String");
+    for token in &["Top", "Null", "UninitializedThis", "Integer", "Uninitialized(12)"] {
+        assert_eq!(mappings.remap_verification_type(token), *token);
+    }
+}
+
+#[test]
+fn descriptor_strings() {
+    let mappings = test_mappings();
+    assert_eq!(mappings.remap_descriptor_str("Lobf4;"), "Lnet/techcable/minecraft/Player;");
+    assert_eq!(mappings.remap_descriptor_str("[[Lobfs;"), "[[Lnet/techcable/minecraft/NoHax;");
+    assert_eq!(mappings.remap_descriptor_str("(Lobf4;ID)Z"), "(Lnet/techcable/minecraft/Player;ID)Z");
+    assert_eq!(mappings.remap_descriptor_str("()V"), "()V");
+    assert_eq!(mappings.remap_descriptor_str("J"), "J");
+    for invalid in &["", "(Lobf4;", "Lobf4", "Q"] {
+        assert_eq!(mappings.remap_descriptor_str(invalid), *invalid);
+    }
+    assert_eq!(mappings.remap_method_descriptor("(Lobf4;[Lobfs;)Lobf4;"), "(Lnet/techcable/minecraft/Player;[Lnet/techcable/minecraft/NoHax;)Lnet/techcable/minecraft/Player;");
+    assert_eq!(mappings.remap_method_descriptor("(Ljava/lang/String;)V"), "(Ljava/lang/String;)V");
+    assert_eq!(mappings.remap_method_descriptor("Lobf4;"), "Lobf4;");
+    let types = ["Lobf4;", "I", "Lobf4;", "[Lobfs;"].iter()
+        .map(|s| TypeDescriptor::parse_descriptor(s).unwrap())
+        .collect::<Vec<_>>();
+    let remapped = mappings.remap_types(&types);
+    let descriptors = remapped.iter().map(|t| t.descriptor()).collect::<Vec<_>>();
+    assert_eq!(descriptors, vec![
+        "Lnet/techcable/minecraft/Player;",
+        "I",
+        "Lnet/techcable/minecraft/Player;",
+        "[Lnet/techcable/minecraft/NoHax;"
+    ]);
+}
+
+#[test]
+fn entry_counts() {
+    let mappings = test_mappings();
+    assert_eq!((mappings.class_count(), mappings.field_count(), mappings.method_count()), (2, 1, 1));
+    assert_eq!(mappings.len(), 4);
+    assert!(!mappings.is_empty());
+    assert!(FrozenMappings::empty().is_empty());
+    assert_eq!(FrozenMappings::empty().len(), 0);
+}
+
+#[test]
+fn generic_signatures() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: obf4 net/techcable/minecraft/Player",
+        "CL: obfs net/techcable/minecraft/NoHax",
+        "CL: obfs$a net/techcable/minecraft/NoHax$Check",
+    ]).unwrap();
+    let remap = |signature: &str| mappings.remap_signature_string(signature);
+    // Fields
+    assert_eq!(remap("Lobf4;"), "Lnet/techcable/minecraft/Player;");
+    assert_eq!(remap("Ljava/util/List<Lobf4;>;"), "Ljava/util/List<Lnet/techcable/minecraft/Player;>;");
+    assert_eq!(remap("TT;"), "TT;");
+    assert_eq!(remap("[Ljava/util/Map<*+Lobfs;>;"), "[Ljava/util/Map<*+Lnet/techcable/minecraft/NoHax;>;");
+    // Methods
+    assert_eq!(
+        remap("<T:Lobf4;>(TT;Ljava/util/List<-Lobfs;>;I)TT;^Ljava/io/IOException;^TE;"),
+        "<T:Lnet/techcable/minecraft/Player;>(TT;Ljava/util/List<-Lnet/techcable/minecraft/NoHax;>;I)TT;^Ljava/io/IOException;^TE;"
+    );
+    assert_eq!(remap("()V"), "()V");
+    // Classes, with interface bounds and parameterized outer classes
+    assert_eq!(
+        remap("<LT::Ljava/lang/Comparable<TLT;>;>Lobfs<TLT;>.a<Lobf4;>;Ljava/lang/Runnable;"),
+        "<LT::Ljava/lang/Comparable<TLT;>;>Lnet/techcable/minecraft/NoHax<TLT;>.Check<Lnet/techcable/minecraft/Player;>;Ljava/lang/Runnable;"
+    );
+    for invalid in &["", "Lobf4", "(Lobf4;", "<T>V", "Ljava/util/List<>;", "Q"] {
+        assert_eq!(remap(invalid), *invalid);
+    }
+}
+
+#[test]
+fn generic_signature_model() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: obf4 net/techcable/minecraft/Player",
+        "CL: obfs net/techcable/minecraft/NoHax",
+        "CL: obfs$a net/techcable/minecraft/NoHax$Check",
+    ]).unwrap();
+    // Transforming the model agrees with remapping the string directly
+    for signature in &[
+        "Ljava/util/List<Lobf4;>;",
+        "[Ljava/util/Map<*+Lobfs;>;",
+        "<T:Lobf4;>(TT;Ljava/util/List<-Lobfs;>;I)TT;^Ljava/io/IOException;^TE;",
+        "<LT::Ljava/lang/Comparable<TLT;>;>Lobfs<TLT;>.a<Lobf4;>;Ljava/lang/Runnable;",
+    ] {
+        let parsed = GenericSignature::parse_text(signature).ok().unwrap();
+        assert_eq!(parsed.signature(), *signature);
+        assert_eq!(parsed.transform_class(&mappings).signature(), mappings.remap_signature_string(signature));
+    }
+}
+
+#[test]
+fn remapped_names() {
+    let mappings = test_mappings();
+    let player = ReferenceType::from_internal_name("obf4");
+    assert_eq!(mappings.remapped_simple_name(&player), "Player");
+    assert_eq!(mappings.remapped_package(&player), "net/techcable/minecraft");
+    let unmapped = ReferenceType::from_internal_name("Unmapped");
+    assert_eq!(mappings.remapped_simple_name(&unmapped), "Unmapped");
+    assert_eq!(mappings.remapped_package(&unmapped), "");
+}
+
+#[test]
+fn enclosing_method() {
+    let mappings = test_mappings();
+    let obfs = ReferenceType::from_internal_name("obfs");
+    let no_hax = ReferenceType::from_internal_name("net/techcable/minecraft/NoHax");
+    assert_eq!(
+        mappings.remap_enclosing_method(&obfs, Some("a"), Some("(Lobf4;ID)Z")),
+        (no_hax.clone(), Some("isHacking".into()), Some("(Lnet/techcable/minecraft/Player;ID)Z".into()))
+    );
+    assert_eq!(
+        mappings.remap_enclosing_method(&obfs, Some("b"), Some("(Lobf4;)V")),
+        (no_hax.clone(), Some("b".into()), Some("(Lnet/techcable/minecraft/Player;)V".into()))
+    );
+    assert_eq!(mappings.remap_enclosing_method(&obfs, None, None), (no_hax.clone(), None, None));
+    assert_eq!(
+        mappings.remap_enclosing_method(&obfs, Some("a"), Some("invalid")),
+        (no_hax, Some("a".into()), Some("invalid".into()))
+    );
+}
+
+#[test]
+fn record_components() {
+    let mappings = test_mappings();
+    let player = ReferenceType::from_internal_name("obf4");
+    assert_eq!(
+        mappings.remap_record_component(&player, "a", "[Lobf4;"),
+        ("dead".into(), "[Lnet/techcable/minecraft/Player;".into())
+    );
+    assert_eq!(mappings.remap_record_component(&player, "b", "I"), ("b".into(), "I".into()));
+    assert_eq!(mappings.remap_record_component(&player, "a", "invalid"), ("dead".into(), "invalid".into()));
+    // Components are told apart by their types, like their fields
+    let typed = TabSrg2MappingsFormat::parse_lines(&[
+        "tsrg2 obf srg",
+        "a net/techcable/minecraft/Settings",
+        "\tx Z flag",
+        "\tx I count",
+    ]).unwrap();
+    let settings = ReferenceType::from_internal_name("a");
+    assert_eq!(typed.remap_record_component(&settings, "x", "I"), ("count".into(), "I".into()));
+    assert_eq!(typed.remap_record_component(&settings, "x", "Z"), ("flag".into(), "Z".into()));
+}
+
+#[test]
+fn inner_class_entries() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: a net/techcable/XRay",
+        "CL: a$b net/techcable/XRay$Manager",
+        "CL: a$1c net/techcable/XRay$1Local",
+        "CL: a$d net/techcable/XRayInjector",
+    ]).unwrap();
+    let class = ReferenceType::from_internal_name;
+    let outer = class("a");
+    let (inner, remapped_outer, name) = mappings.remap_inner_class_entry(&class("a$b"), Some(&outer), Some("b"));
+    assert_eq!(inner.internal_name(), "net/techcable/XRay$Manager");
+    assert_eq!(remapped_outer.unwrap().internal_name(), "net/techcable/XRay");
+    assert_eq!(name.as_deref(), Some("Manager"));
+    let (_, _, name) = mappings.remap_inner_class_entry(&class("a$1c"), None, Some("c"));
+    assert_eq!(name.as_deref(), Some("Local"));
+    let (_, _, name) = mappings.remap_inner_class_entry(&class("a$d"), Some(&outer), Some("d"));
+    assert_eq!(name.as_deref(), Some("XRayInjector"));
+    // Anonymous classes don't have a name
+    let (inner, _, name) = mappings.remap_inner_class_entry(&class("a$1"), None, None);
+    assert_eq!((inner.internal_name(), name), ("a$1", None));
+    let (_, _, name) = mappings.remap_inner_class_entry(&class("Unmapped$Inner"), Some(&class("Unmapped")), Some("Inner"));
+    assert_eq!(name.as_deref(), Some("Inner"));
+}
+
+#[test]
+fn permitted_subclasses() {
+    let mappings = test_mappings();
+    let subclasses = [
+        ReferenceType::from_internal_name("obf4"),
+        ReferenceType::from_internal_name("Unmapped"),
+        ReferenceType::from_internal_name("obfs"),
+    ];
+    let remapped: Vec<String> = mappings.remap_permitted_subclasses(&subclasses)
+        .iter()
+        .map(|subclass| subclass.internal_name().into())
+        .collect();
+    assert_eq!(remapped, vec!["net/techcable/minecraft/Player", "Unmapped", "net/techcable/minecraft/NoHax"]);
+}
+
+#[test]
+fn remapped_members_with() {
+    let mappings = test_mappings();
+    let relocation = SrgMappingsFormat::parse_lines(&[
+        "CL: obf4 relocated/Player",
+    ]).unwrap();
+    let fields: Vec<_> = mappings.remapped_fields_with(&relocation).collect();
+    assert_eq!(fields, vec![(
+        &FieldData::new("a".into(), ReferenceType::from_internal_name("obf4")),
+        FieldData::new("a".into(), ReferenceType::from_internal_name("relocated/Player"))
+    )]);
+    let methods: Vec<_> = mappings.remapped_methods_with(&relocation)
+        .map(|(_, remapped)| remapped.signature().descriptor().to_string())
+        .collect();
+    assert_eq!(methods, vec!["(Lrelocated/Player;ID)Z"]);
+}
+
+#[test]
+fn reflection_table() {
+    let table = test_mappings().build_reflection_table();
+    assert_eq!(table.obf_class("net.techcable.minecraft.Player"), Some("obf4"));
+    assert_eq!(table.obf_class("obf4"), None);
+    assert_eq!(table.obf_field("net.techcable.minecraft.Player", "dead"), Some("a"));
+    assert_eq!(
+        table.obf_method(
+            "net.techcable.minecraft.NoHax", "isHacking",
+            "(Lnet/techcable/minecraft/Player;ID)Z"
+        ),
+        Some("a")
+    );
+    assert_eq!(table.obf_method("net.techcable.minecraft.NoHax", "isHacking", "()Z"), None);
+}
+
+#[test]
+fn config_values() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: org/spigotmc/XRay net/techcable/xray/XRay",
+        "CL: org/spigotmc/XRay$Manager net/techcable/xray/XRayManager",
+    ]).unwrap();
+    assert_eq!(mappings.remap_config_value("org.spigotmc.XRay"), "net.techcable.xray.XRay");
+    assert_eq!(mappings.remap_config_value("org.spigotmc.XRay$Manager"), "net.techcable.xray.XRayManager");
+    assert_eq!(mappings.remap_config_value("org.spigotmc.XRay$Injector"), "net.techcable.xray.XRay$Injector");
+    assert_eq!(mappings.remap_config_value("org.spigotmc.Unknown"), "org.spigotmc.Unknown");
+    assert_eq!(mappings.remap_config_value("Hello world!"), "Hello world!");
+}
+
+#[test]
+fn referenced_classes_of() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "MD: a/a (Lb;[[Lc;I)V Foo/first (LBar;[[LBaz;I)V",
+        "MD: a/b ()La; Foo/second ()LFoo;",
+        "MD: a/c ([Lb;)Ld; Foo/third ([LBar;)Ld;",
+        "MD: b/a ()Le; Bar/first ()Le;",
+    ]).unwrap();
+    let names: Vec<String> = mappings.referenced_classes_of(&ReferenceType::from_internal_name("a"))
+        .iter().map(|t| t.internal_name().to_string()).collect();
+    assert_eq!(names, vec!["b", "c", "d"]);
+}
+
+#[test]
+fn module_packages() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: org/spigotmc/XRay net/techcable/xray/XRay",
+        "CL: org/spigotmc/XRay$Manager net/techcable/xray/XRayManager",
+        "CL: org/spigotmc/Lag net/techcable/lag/Lag",
+    ]).unwrap();
+    assert_eq!(mappings.remap_module_package("org/spigotmc"), "net/techcable/xray");
+    assert_eq!(
+        mappings.remap_module_packages(vec!["org/spigotmc", "java/lang"]),
+        vec!["net/techcable/xray", "java/lang"]
+    );
+    let mut simple = SimpleMappings::default();
+    simple.merge_from(&mappings);
+    assert_eq!(simple.remap_module_package("org/spigotmc"), "net/techcable/xray");
+    assert_eq!(simple.remap_module_package("java/lang"), "java/lang");
+    assert_eq!(simple.remap_module_packages(vec!["org/spigotmc"]), vec!["net/techcable/xray"]);
+    // Kept package mappings are used before inferring the renames
+    let lines = &[
+        "PK: org/spigotmc net/techcable/spigot",
+        "CL: org/spigotmc/XRay org/spigotmc/XRay",
+        "CL: org/spigotmc/Lag net/techcable/lag/Lag",
+        "CL: org/spigotmc/LagMonitor net/techcable/lag/LagMonitor",
+    ];
+    let mut processor = SrgMappingsFormat::processor().keep_packages();
+    for line in lines {
+        processor.process_line(line).unwrap();
+    }
+    let kept = processor.finish().unwrap();
+    assert_eq!(kept.remap_module_package("org/spigotmc"), "net/techcable/spigot");
+    assert_eq!(kept.remap_module_packages(vec!["org/spigotmc"]), vec!["net/techcable/spigot"]);
+    let inferred = SrgMappingsFormat::parse_lines(lines).unwrap();
+    assert_eq!(inferred.remap_module_package("org/spigotmc"), "net/techcable/lag");
+}
+
+#[test]
+fn fully_remapped() {
+    let is_obfuscated = |t: &ReferenceType| t.simple_name().len() <= 4;
+    test_mappings().assert_fully_remapped(is_obfuscated).unwrap();
+    let incomplete = SrgMappingsFormat::parse_lines(&[
+        "CL: obfs net/techcable/minecraft/NoHax",
+        "MD: obfs/a ([[Lobf4;)Z net/techcable/minecraft/NoHax/isHacking ([[Lobf4;)Z",
+        "MD: obfs/b ()V net/techcable/minecraft/NoHax/tick ()V",
+    ]).unwrap();
+    let failures = incomplete.assert_fully_remapped(is_obfuscated).unwrap_err();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].name, "isHacking");
+}
+
+#[test]
+fn apply_names_by_searge_id() {
+    let searge = SrgMappingsFormat::parse_lines(&[
+        "CL: a net/minecraft/entity/Entity",
+        "FD: a/a net/minecraft/entity/Entity/field_70170_p",
+        "FD: a/b net/minecraft/entity/Entity/field_99999_q",
+        "MD: a/c ()V net/minecraft/entity/Entity/func_70071_h_ ()V",
+        "MD: a/d (La;)Z net/minecraft/entity/Entity/func_70028_i (Lnet/minecraft/entity/Entity;)Z",
+        "MD: a/e ()V net/minecraft/entity/Entity/unnamed ()V",
+    ]).unwrap();
+    let names = SrgMappingsFormat::parse_lines(&[
+        "FD: Unknown/field_70170_p Unknown/world",
+        "MD: Unknown/func_70071_h_ ()V Unknown/onUpdate ()V",
+        "MD: Unknown/func_70028_i (LUnknown;)Z Unknown/isEntityEqual (LUnknown;)Z",
+    ]).unwrap();
+    let expected = SrgMappingsFormat::parse_lines(&[
+        "CL: a net/minecraft/entity/Entity",
+        "FD: a/a net/minecraft/entity/Entity/world",
+        "FD: a/b net/minecraft/entity/Entity/field_99999_q",
+        "MD: a/c ()V net/minecraft/entity/Entity/onUpdate ()V",
+        "MD: a/d (La;)Z net/minecraft/entity/Entity/isEntityEqual (Lnet/minecraft/entity/Entity;)Z",
+        "MD: a/e ()V net/minecraft/entity/Entity/unnamed ()V",
+    ]).unwrap();
+    searge.apply_names_by_searge_id(&names).assert_equal(&expected);
+}
+
+#[test]
+fn regenerate_intermediate() {
+    let mappings = test_mappings();
+    let mut counter = 0;
+    let (first, second) = mappings.regenerate_intermediate(|_| {
+        counter += 1;
+        ReferenceType::from_internal_name(&format!("class_{}", counter))
+    });
+    first.assert_equal(&SrgMappingsFormat::parse_lines(&[
+        "CL: obf4 class_1",
+        "CL: obfs class_2",
+        "FD: obf4/a class_1/a",
+        "MD: obfs/a (Lobf4;ID)Z class_2/a (Lclass_1;ID)Z",
+    ]).unwrap());
+    second.assert_equal(&SrgMappingsFormat::parse_lines(&[
+        "CL: class_1 net/techcable/minecraft/Player",
+        "CL: class_2 net/techcable/minecraft/NoHax",
+        "FD: class_1/a net/techcable/minecraft/Player/dead",
+        "MD: class_2/a (Lclass_1;ID)Z net/techcable/minecraft/NoHax/isHacking (Lnet/techcable/minecraft/Player;ID)Z",
+    ]).unwrap());
+    assert!(first.chain(second).content_eq(&mappings));
+}
+
+#[test]
+fn merge() {
+    let mappings = test_mappings();
+    let other = SrgMappingsFormat::parse_lines(&[
+        "CL: obf4 net/techcable/minecraft/Player",
+        "CL: obf5 net/techcable/minecraft/World",
+        "FD: obf4/b net/techcable/minecraft/Player/blood",
+        "MD: obfs/a (Lobf4;ID)Z net/techcable/minecraft/NoHax/isHacking (Lnet/techcable/minecraft/Player;ID)Z",
+    ]).unwrap();
+    let merged = mappings.merge(&other).unwrap();
+    let world = ReferenceType::from_internal_name("obf5");
+    assert_eq!(merged.remap_class(&world).internal_name(), "net/techcable/minecraft/World");
+    let player = ReferenceType::from_internal_name("obf4");
+    assert_eq!(merged.remap_field(&FieldData::new("a".into(), player.clone())).name, "dead");
+    assert_eq!(merged.remap_field(&FieldData::new("b".into(), player)).name, "blood");
+    assert_eq!(merged.original_methods().count(), 1);
+    let conflicting = SrgMappingsFormat::parse_lines(&[
+        "MD: obfs/a (Lobf4;ID)Z net/techcable/minecraft/NoHax/isCheating (Lnet/techcable/minecraft/Player;ID)Z",
+    ]).unwrap();
+    match mappings.merge(&conflicting) {
+        Err(error @ MergeConflict::Method { .. }) => assert_eq!(
+            error.to_string(),
+            "Method obfs/a (Lobf4;ID)Z is renamed to both isHacking and isCheating"
+        ),
+        other => panic!("Unexpected result: {:?}", other)
+    }
+    let conflicting = SrgMappingsFormat::parse_lines(&["CL: obf4 Player"]).unwrap();
+    match mappings.merge(&conflicting) {
+        Err(MergeConflict::Class { first, second, .. }) => {
+            assert_eq!(first.internal_name(), "net/techcable/minecraft/Player");
+            assert_eq!(second.internal_name(), "Player");
+        },
+        other => panic!("Unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn original_lookups() {
+    let mappings = test_mappings();
+    let player = ReferenceType::from_internal_name("net/techcable/minecraft/Player");
+    assert_eq!(mappings.get_original_class(&player).map(|class| class.internal_name()), Some("obf4"));
+    assert_eq!(mappings.get_original_class(&ReferenceType::from_internal_name("obf4")), None);
+    assert_eq!(
+        mappings.get_original_field(&FieldData::new("dead".into(), player.clone())),
+        Some(&FieldData::new("a".into(), ReferenceType::from_internal_name("obf4")))
+    );
+    let renamed_method = MethodData::new(
+        "isHacking".into(),
+        ReferenceType::from_internal_name("net/techcable/minecraft/NoHax"),
+        MethodSignature::from_descriptor("(Lnet/techcable/minecraft/Player;ID)Z")
+    );
+    assert_eq!(mappings.get_original_method(&renamed_method).map(|method| &*method.name), Some("a"));
+    // Looking up the originals of the inverse goes back to our renamed names
+    let inverted = mappings.inverted();
+    assert_eq!(
+        inverted.get_original_class(&ReferenceType::from_internal_name("obf4")),
+        Some(&player)
+    );
+    // The inverse maps can be borrowed directly
+    assert_eq!(mappings.inverted_classes().len(), 2);
+    assert_eq!(mappings.inverted_classes().get(&player).map(|class| class.internal_name()), Some("obf4"));
+    assert_eq!(mappings.inverted_fields().keys().map(|field| &*field.name).collect::<Vec<_>>(), vec!["dead"]);
+    assert_eq!(mappings.inverted_methods().get(&renamed_method).map(|method| &*method.name), Some("a"));
+    assert!(inverted.inverted_classes().iter().eq(mappings.classes()));
+}
+
+#[test]
+fn member_refs() {
+    let mappings = test_mappings();
+    let player = ReferenceType::from_internal_name("net/techcable/minecraft/Player");
+    let no_hax = ReferenceType::from_internal_name("net/techcable/minecraft/NoHax");
+    assert_eq!(
+        mappings.remap_member_ref("obf4", "a", "Z"),
+        (player.clone(), "dead".into(), "Z".into())
+    );
+    assert_eq!(
+        mappings.remap_member_ref("obf4", "b", "[Lobfs;"),
+        (player.clone(), "b".into(), "[Lnet/techcable/minecraft/NoHax;".into())
+    );
+    assert_eq!(
+        mappings.remap_member_ref("obfs", "a", "(Lobf4;ID)Z"),
+        (no_hax.clone(), "isHacking".into(), "(Lnet/techcable/minecraft/Player;ID)Z".into())
+    );
+    assert_eq!(
+        mappings.remap_member_ref("obfs", "b", "(Lobf4;)V"),
+        (no_hax.clone(), "b".into(), "(Lnet/techcable/minecraft/Player;)V".into())
+    );
+    assert_eq!(
+        mappings.remap_member_ref("obfs", "a", "(invalid"),
+        (no_hax, "a".into(), "(invalid".into())
+    );
+    // Fields which only differ by their types are distinguished by the descriptor
+    let field = |descriptor: &str| FieldData::new("a".into(), ReferenceType::from_internal_name("obf4"))
+        .with_descriptor(TypeDescriptor::parse_descriptor(descriptor).unwrap());
+    let typed = FrozenMappings::builder()
+        .field(field("Z"), "dead")
+        .field(field("I"), "health")
+        .build();
+    assert_eq!(typed.remap_member_ref("obf4", "a", "I").1, "health");
+    assert_eq!(typed.remap_member_ref("obf4", "a", "Z").1, "dead");
+}
+
+#[test]
+fn contains() {
+    let mappings = test_mappings();
+    let obf4 = ReferenceType::from_internal_name("obf4");
+    let player = ReferenceType::from_internal_name("net/techcable/minecraft/Player");
+    assert!(mappings.contains_class(&obf4) && !mappings.contains_class(&player));
+    assert!(mappings.contains_original_class(&player) && !mappings.contains_original_class(&obf4));
+    assert!(mappings.contains_field(&FieldData::from_internal("obf4/a")));
+    assert!(!mappings.contains_field(&FieldData::from_internal("obf4/b")));
+    assert!(mappings.contains_original_field(&FieldData::from_internal("net/techcable/minecraft/Player/dead")));
+    assert!(mappings.contains_method(&MethodData::from_internal("obfs/a", "(Lobf4;ID)Z")));
+    assert!(!mappings.contains_method(&MethodData::from_internal("obfs/a", "()Z")));
+    assert!(mappings.contains_original_method(&MethodData::from_internal(
+        "net/techcable/minecraft/NoHax/isHacking",
+        "(Lnet/techcable/minecraft/Player;ID)Z"
+    )));
+}
+
+#[test]
+fn coverage_by_package() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: net/minecraft/a net/minecraft/server/Entity",
+        "CL: net/minecraft/b net/minecraft/b",
+        "CL: c Player",
+        "FD: net/minecraft/d/a net/minecraft/d/dead",
+    ]).unwrap();
+    let coverage = mappings.coverage_by_package();
+    assert_eq!(coverage.len(), 2);
+    assert_eq!(coverage["net/minecraft"], PackageCoverage { mapped: 1, unmapped: 2, total: 3 });
+    assert_eq!(coverage[""], PackageCoverage { mapped: 1, unmapped: 0, total: 1 });
+}
+
+#[test]
+fn packages() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: net/minecraft/a net/minecraft/server/Entity",
+        "CL: c Player",
+        "CL: net/minecraft/b net/minecraft/server/Cow",
+        "CL: d net/techcable/Hax",
+    ]).unwrap();
+    assert_eq!(mappings.packages().collect::<Vec<_>>(), vec!["net/minecraft", ""]);
+    assert_eq!(
+        mappings.renamed_packages().collect::<Vec<_>>(),
+        vec!["net/minecraft/server", "", "net/techcable"]
+    );
+}
+
+#[test]
+fn intersect() {
+    let mappings = test_mappings();
+    let other = SrgMappingsFormat::parse_lines(&[
+        "CL: obf4 net/techcable/minecraft/Player",
+        "CL: obfs net/techcable/minecraft/AntiCheat",
+        "CL: obf5 net/techcable/minecraft/World",
+        "FD: obf4/a net/techcable/minecraft/Player/dead",
+        "MD: obfs/a (Lobf4;ID)Z net/techcable/minecraft/AntiCheat/isHacking (Lnet/techcable/minecraft/Player;ID)Z",
+    ]).unwrap();
+    let intersection = mappings.intersect(&other);
+    intersection.assert_equal(&SrgMappingsFormat::parse_lines(&[
+        "CL: obf4 net/techcable/minecraft/Player",
+        "FD: obf4/a net/techcable/minecraft/Player/dead",
+    ]).unwrap());
+    // The method's name matches, but its declaring type was renamed differently
+    assert_eq!(intersection.original_methods().count(), 0);
+    assert!(mappings.intersect(&mappings).content_eq(&mappings));
+}
+
+#[test]
+fn builder() {
+    let player = ReferenceType::from_internal_name("obf4");
+    let no_hax = ReferenceType::from_internal_name("obfs");
+    let mappings = FrozenMappings::builder()
+        .class_name("obf4", "net.techcable.minecraft.Player")
+        .class(no_hax.clone(), ReferenceType::from_internal_name("net/techcable/minecraft/NoHax"))
+        .field(FieldData::new("a".into(), player), "dead")
+        .method(MethodData::new("a".into(), no_hax, MethodSignature::from_descriptor("(Lobf4;ID)Z")), "isHacking")
+        .build();
+    mappings.assert_equal(&test_mappings());
+}
+
+#[test]
+fn expand_inner_classes() {
+    let entry = ReferenceType::from_internal_name("obf4$a$b");
+    let explicit = ReferenceType::from_internal_name("obf4$c");
+    let mappings = FrozenMappings::builder()
+        .class_name("obf4", "net.techcable.minecraft.Player")
+        .class_name("obf4$c", "net.techcable.minecraft.Player$Inventory")
+        .field(FieldData::new("a".into(), entry.clone()), "value")
+        .method(MethodData::new("a".into(), explicit.clone(), MethodSignature::from_descriptor("(Lobf4$a$b;)V")), "add")
+        .method(MethodData::new("a".into(), ReferenceType::from_internal_name("obfs$a"), MethodSignature::from_descriptor("()V")), "run")
+        .build();
+    let expanded = mappings.expand_inner_classes();
+    assert_eq!(expanded.remap_class(&entry).internal_name(), "net/techcable/minecraft/Player$a$b");
+    assert_eq!(expanded.remap_class(&explicit).internal_name(), "net/techcable/minecraft/Player$Inventory");
+    assert_eq!(expanded.remap_class_name("obfs$a").internal_name(), "obfs$a");
+    // The outer classes in between are expanded too
+    assert_eq!(expanded.remap_class_name("obf4$a").internal_name(), "net/techcable/minecraft/Player$a");
+    // Members are remapped using the inferred names
+    assert_eq!(
+        expanded.remap_field(&FieldData::new("a".into(), entry)),
+        FieldData::new("value".into(), ReferenceType::from_internal_name("net/techcable/minecraft/Player$a$b"))
+    );
+    assert_eq!(
+        expanded.remap_method(&MethodData::new("a".into(), explicit, MethodSignature::from_descriptor("(Lobf4$a$b;)V"))).signature().descriptor(),
+        "(Lnet/techcable/minecraft/Player$a$b;)V"
+    );
+}
+
+#[test]
+fn validate() {
+    assert!(test_mappings().rebuild().validate().is_ok());
+    let mut mappings = test_mappings().rebuild();
+    let no_hax = ReferenceType::from_internal_name("obfs");
+    let renamed_player = ReferenceType::from_name("net.techcable.minecraft.Player");
+    mappings.set_remapped_class(ReferenceType::from_internal_name("obf5"), renamed_player.clone());
+    // Classes renamed to themselves don't count as dangling
+    mappings.set_remapped_class(no_hax.clone(), no_hax.clone());
+    mappings.set_field_name(FieldData::new("b".into(), no_hax), "hacking".into());
+    mappings.set_field_name(FieldData::new("dead".into(), renamed_player.clone()), "isDead".into());
+    match mappings.validate() {
+        Err(errors) => match &errors[..] {
+            [
+                MappingsValidationError::ClassCollision { renamed, first, second },
+                MappingsValidationError::DanglingField { original, renamed_from }
+            ] => {
+                assert_eq!(renamed, &renamed_player);
+                assert_eq!(first.internal_name(), "obf4");
+                assert_eq!(second.internal_name(), "obf5");
+                assert_eq!(original.declaring_type(), &renamed_player);
+                assert_eq!(renamed_from.internal_name(), "obf4");
+            },
+            other => panic!("Unexpected errors: {:?}", other)
+        },
+        Ok(()) => panic!("Expected errors")
+    }
+}
+
+#[test]
+fn signature_cache() {
+    let mappings = test_mappings();
+    mappings.enable_signature_cache(1);
+    // Unmapped methods still need their signatures remapped
+    let unmapped = MethodData::from_internal("obfs/b", "(Lobf4;ID)Z");
+    let expected = "(Lnet/techcable/minecraft/Player;ID)Z";
+    // Remapping again should give the same signature, even after other signatures evict it
+    assert_eq!(mappings.remap_method(&unmapped).signature().descriptor(), expected);
+    assert_eq!(mappings.remap_method(&unmapped).signature().descriptor(), expected);
+    assert_eq!(mappings.clone().remap_signature(&MethodSignature::from_descriptor("(Lobf4;)V")).descriptor(), "(Lnet/techcable/minecraft/Player;)V");
+    assert_eq!(mappings.remap_method(&unmapped).signature().descriptor(), expected);
+    assert_eq!(mappings.inverted().remap_signature(&MethodSignature::from_descriptor(expected)).descriptor(), "(Lobf4;ID)Z");
+}
+
+#[test]
+fn inheritance() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: a net/minecraft/Entity",
+        "CL: b net/minecraft/EntityLiving",
+        "CL: c net/minecraft/EntityPlayer",
+        "CL: i net/minecraft/Tickable",
+        "MD: a/a ()V net/minecraft/Entity/kill ()V",
+        "MD: b/b (La;)Z net/minecraft/EntityLiving/attack (Lnet/minecraft/Entity;)Z",
+        "MD: i/t ()V net/minecraft/Tickable/tick ()V",
+    ]).unwrap();
+    let class = ReferenceType::from_internal_name;
+    let mut parents = HashMap::new();
+    parents.insert(class("b"), vec![class("a"), class("i")]);
+    parents.insert(class("c"), vec![class("b")]);
+    let inherited = mappings.with_inheritance(&parents);
+    let remap = |joined: &str, descriptor: &str| {
+        let remapped = inherited.remap_method(&MethodData::from_internal(joined, descriptor));
+        format!("{}/{} {}", remapped.declaring_type().internal_name(), remapped.name, remapped.signature().descriptor())
+    };
+    assert_eq!(remap("c/a", "()V"), "net/minecraft/EntityPlayer/kill ()V");
+    assert_eq!(remap("c/b", "(La;)Z"), "net/minecraft/EntityPlayer/attack (Lnet/minecraft/Entity;)Z");
+    assert_eq!(remap("c/t", "()V"), "net/minecraft/EntityPlayer/tick ()V");
+    // The signature must match exactly
+    assert_eq!(remap("c/a", "(I)V"), "net/minecraft/EntityPlayer/a (I)V");
+    assert_eq!(remap("a/b", "(La;)Z"), "net/minecraft/Entity/b (Lnet/minecraft/Entity;)Z");
+    // Without the hierarchy, only the declaring class is checked
+    assert_eq!(mappings.remap_method(&MethodData::from_internal("c/a", "()V")).name, "a");
+}
+
+#[test]
+fn visitor() {
+    #[derive(Default)]
+    struct CountingVisitor {
+        classes: Vec<String>,
+        members: usize
+    }
+    impl MappingsVisitor for CountingVisitor {
+        fn visit_class(&mut self, original: &ReferenceType, _renamed: &ReferenceType) {
+            self.classes.push(original.internal_name().into());
+        }
+        fn visit_method(&mut self, _original: &MethodData, _renamed: &MethodData) {
+            self.members += 1;
+        }
+    }
+    let mappings = test_mappings();
+    let mut counter = CountingVisitor::default();
+    mappings.accept(&mut counter);
+    assert_eq!(counter.classes, vec!["obf4", "obfs"]);
+    assert_eq!(counter.members, 1);
+    let mut collected = SimpleMappings::default();
+    mappings.accept(&mut collected);
+    collected.frozen().assert_equal(&mappings);
+}
+
+#[test]
+fn owned_entries() {
+    let mappings = test_mappings();
+    let expected_classes: Vec<_> = mappings.classes()
+        .map(|(original, renamed)| (original.clone(), renamed.clone()))
+        .collect();
+    // Shared mappings have to be cloned
+    let shared = mappings.clone();
+    assert_eq!(shared.into_classes().collect::<Vec<_>>(), expected_classes);
+    let inverted = mappings.inverted();
+    let inverted_fields: Vec<_> = inverted.into_fields().collect();
+    assert_eq!(inverted_fields, vec![(
+        FieldData::from_internal("net/techcable/minecraft/Player/dead"),
+        FieldData::from_internal("obf4/a")
+    )]);
+    let methods: Vec<_> = mappings.into_methods().collect();
+    assert_eq!(methods.len(), 1);
+    assert_eq!(methods[0].1.name, "isHacking");
+    // Uniquely owned inverted mappings still give their own entries
+    let inverted = test_mappings().inverted();
+    let classes: Vec<_> = inverted.into_classes().map(|(original, _)| original.internal_name().to_string()).collect();
+    assert_eq!(classes, vec!["net/techcable/minecraft/Player", "net/techcable/minecraft/NoHax"]);
+}
+
+#[test]
+fn referenced_classes() {
+    let mappings = SrgMappingsFormat::parse_lines(&[
+        "CL: a Entity",
+        "CL: b Cow",
+        "CL: c World",
+        "FD: a/a Entity/dead",
+        "MD: b/a ([[Lc;I)Ljava/lang/String; Cow/love ([[LWorld;I)Ljava/lang/String;"
+    ]).unwrap();
+    let mut actual: Vec<String> = mappings.referenced_classes().into_iter()
+        .map(|class| class.internal_name().to_string())
+        .collect();
+    actual.sort();
+    assert_eq!(actual, vec!["Cow", "Entity", "World", "a", "b", "c", "java/lang/String"]);
+}
+
+#[test]
+fn rename_classes_only() {
+    let mappings = test_mappings();
+    let classes = mappings.rename_classes_only();
+    assert_eq!((classes.class_count(), classes.field_count(), classes.method_count()), (2, 0, 0));
+    let expected = SrgMappingsFormat::parse_lines(&[
+        "CL: obf4 net/techcable/minecraft/Player",
+        "CL: obfs net/techcable/minecraft/NoHax",
+    ]).unwrap();
+    expected.assert_equal(&classes);
+    // Signatures are still remapped, but member names aren't
+    let method = MethodData::from_internal("obfs/a", "(Lobf4;ID)Z");
+    assert_eq!(
+        classes.remap_method(&method),
+        MethodData::from_internal("net/techcable/minecraft/NoHax/a", "(Lnet/techcable/minecraft/Player;ID)Z")
+    );
+}
+
+#[test]
+fn merge_from() {
+    let mut merged = SimpleMappings::default();
+    merged.merge_from(&test_mappings());
+    merged.frozen().assert_equal(&test_mappings());
+    let later = SrgMappingsFormat::parse_lines(&[
+        "CL: obfs net/techcable/minecraft/AntiCheat",
+        "CL: obf5 net/techcable/minecraft/World",
+        "MD: obfs/a (Lobf4;ID)Z net/techcable/minecraft/AntiCheat/isCheating (Lnet/techcable/minecraft/Player;ID)Z",
+    ]).unwrap();
+    merged.merge_from(&later);
+    // Later sources win
+    merged.frozen().assert_equal(&SrgMappingsFormat::parse_lines(&[
+        "CL: obf4 net/techcable/minecraft/Player",
+        "CL: obfs net/techcable/minecraft/AntiCheat",
+        "CL: obf5 net/techcable/minecraft/World",
+        "FD: obf4/a net/techcable/minecraft/Player/dead",
+        "MD: obfs/a (Lobf4;ID)Z net/techcable/minecraft/AntiCheat/isCheating (Lnet/techcable/minecraft/Player;ID)Z",
+    ]).unwrap());
+}
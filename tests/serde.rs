@@ -0,0 +1,47 @@
+//! Round-trip the serde implementations through bincode,
+//! which needs to know the length of everything up front.
+#![cfg(feature = "serde")]
+extern crate srglib;
+extern crate bincode;
+
+use srglib::prelude::*;
+
+fn round_trip<T>(value: &T) -> T where T: serde::Serialize + serde::de::DeserializeOwned {
+    bincode::deserialize(&bincode::serialize(value).unwrap()).unwrap()
+}
+
+#[test]
+fn types() {
+    let player = ReferenceType::from_internal_name("net/techcable/minecraft/Player");
+    assert_eq!(round_trip(&player), player);
+    let blood = TypeDescriptor::parse_descriptor("[[I").unwrap();
+    assert_eq!(round_trip(&blood), blood);
+    let signature = MethodSignature::from_descriptor("(Lobf4;ID)Z");
+    assert_eq!(round_trip(&signature), signature);
+    let method = MethodData::new("a".into(), player.clone(), signature);
+    assert_eq!(round_trip(&method), method);
+    // Fields keep their descriptor, whether or not we know it
+    let dead = FieldData::new("dead".into(), player);
+    assert_eq!(round_trip(&dead).descriptor(), None);
+    let blood = dead.clone().with_descriptor(blood);
+    assert_eq!(round_trip(&blood), blood);
+    assert_eq!(round_trip(&blood).descriptor(), blood.descriptor());
+}
+
+#[test]
+fn mappings() {
+    let player = ReferenceType::from_internal_name("obf4");
+    let mappings = FrozenMappings::builder()
+        .class_name("obf4", "net.techcable.minecraft.Player")
+        .class_name("obfs", "net.techcable.minecraft.NoHax")
+        .field(FieldData::new("a".into(), player.clone()), "dead")
+        .field(
+            FieldData::new("b".into(), player.clone()).with_descriptor(TypeDescriptor::parse_descriptor("[[I").unwrap()),
+            "blood"
+        )
+        .method(MethodData::new("a".into(), player, MethodSignature::from_descriptor("(Lobfs;ID)Z")), "isHacking")
+        .build();
+    let result: FrozenMappings = round_trip(&mappings);
+    result.assert_equal(&mappings);
+    assert_eq!(result, mappings);
+}
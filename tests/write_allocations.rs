@@ -0,0 +1,66 @@
+//! Check that writing mappings doesn't allocate for every line.
+//!
+//! This needs its own test binary, since it counts allocations with a global allocator.
+extern crate srglib;
+
+use std::io;
+use std::alloc::{GlobalAlloc, System, Layout};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use srglib::prelude::*;
+
+struct CountingAllocator;
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+unsafe impl GlobalAlloc for CountingAllocator {
+    #[inline]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+type WriteFunc = fn(&FrozenMappings) -> io::Result<()>;
+
+fn count_allocations<F: FnOnce()>(func: F) -> usize {
+    let start = ALLOCATIONS.load(Ordering::SeqCst);
+    func();
+    ALLOCATIONS.load(Ordering::SeqCst) - start
+}
+
+#[test]
+fn write_allocations() {
+    let mut lines = Vec::new();
+    for class in 0..10 {
+        lines.push(format!("CL: obf{} net/techcable/Class{}", class, class));
+        for member in 0..500 {
+            lines.push(format!("FD: obf{0}/f{1} net/techcable/Class{0}/field{1}", class, member));
+            lines.push(format!(
+                "MD: obf{0}/m{1} ([Lobf{0};I)V net/techcable/Class{0}/method{1} ([Lnet/techcable/Class{0};I)V",
+                class, member
+            ));
+        }
+    }
+    let mappings = SrgMappingsFormat::parse_lines(&lines).unwrap();
+    let limit = lines.len() / 10;
+    let checks: &[(&str, WriteFunc)] = &[
+        ("srg", |mappings| SrgMappingsFormat::write(mappings, io::sink())),
+        ("csrg", |mappings| CompactSrgMappingsFormat::write(mappings, io::sink())),
+        ("tsrg", |mappings| TabSrgMappingsFormat::write(mappings, io::sink())),
+        ("tiny v2", |mappings| TinyV2MappingsFormat::write(mappings, io::sink())),
+        ("proguard", |mappings| ProguardMappingsFormat::write(mappings, io::sink())),
+    ];
+    for &(name, write) in checks {
+        let allocations = count_allocations(|| write(&mappings).unwrap());
+        assert!(
+            allocations < limit,
+            "Writing {} lines of {} made {} allocations",
+            lines.len(), name, allocations
+        );
+    }
+}